@@ -0,0 +1,232 @@
+// src/credits.rs
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Deep Searches a new install may run before a license key is needed.
+pub const TRIAL_CREDITS: u32 = 100;
+
+/// Keys look like `MP3-<credits>-<serial>-<check>`, e.g. `MP3-500-7F3A9C21-1B2C3D4E`.
+const KEY_PREFIX: &str = "MP3";
+
+/// The Deep Search credits left, stored as JSON next to the library index.
+/// Every provider lookup of a file costs one credit, however many requests it takes.
+pub struct Ledger {
+    /// Where the ledger is saved; `None` for one that only lives in memory.
+    path: Option<PathBuf>,
+    balance: u32,
+    /// Serials of the license keys already added, so each only counts once.
+    redeemed: Vec<String>,
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Ledger { path: None, balance: TRIAL_CREDITS, redeemed: Vec::new() }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LedgerFile {
+    balance: u32,
+    redeemed: Vec<String>,
+}
+
+impl Ledger {
+    /// The ledger location, e.g. `~/.local/share/mp3-cli/credits.json` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("mp3-cli").join("credits.json"))
+    }
+
+    /// Open the ledger at the default location.
+    pub fn open_default() -> io::Result<Ledger> {
+        let path = Self::default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory for this user"))?;
+        Self::open(path)
+    }
+
+    /// Open the ledger at `path`. A missing file starts the trial.
+    pub fn open(path: PathBuf) -> io::Result<Ledger> {
+        match fs::read_to_string(&path) {
+            Ok(data) => {
+                let file: LedgerFile = serde_json::from_str(&data)?;
+                Ok(Ledger { path: Some(path), balance: file.balance, redeemed: file.redeemed })
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Ledger { path: Some(path), ..Ledger::default() }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the ledger back to disk, through a temporary file like the library index.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = LedgerFile { balance: self.balance, redeemed: self.redeemed.clone() };
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(&file)?)?;
+        fs::rename(&temp_path, path)
+    }
+
+    pub fn balance(&self) -> u32 {
+        self.balance
+    }
+
+    /// Charge for a Deep Search that was run.
+    pub fn spend(&mut self) {
+        self.balance = self.balance.saturating_sub(1);
+    }
+
+    /// The credits a scrape may spend, shared by its workers.
+    pub fn allowance(&self) -> Allowance {
+        Allowance(AtomicU32::new(self.balance))
+    }
+
+    /// Add the credits of the license key in the file at `path`, returning how
+    /// many were added. Keys are checked offline, so the check digits only
+    /// catch mistyped keys.
+    pub fn redeem(&mut self, path: &Path) -> io::Result<u32> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // The first line that isn't blank or a `#` comment holds the key
+        let data = fs::read_to_string(path)?;
+        let key = data
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .ok_or_else(|| invalid("no license key in the file"))?;
+
+        let [prefix, credits, serial, check] = key.split('-').collect::<Vec<_>>()[..] else {
+            return Err(invalid("not a license key"));
+        };
+        let credits = credits.parse::<u32>().map_err(|_| invalid("not a license key"))?;
+        if prefix != KEY_PREFIX || serial.is_empty() || !check.eq_ignore_ascii_case(&key_check(credits, serial)) {
+            return Err(invalid("not a valid license key"));
+        }
+
+        let serial = serial.to_ascii_uppercase();
+        if self.redeemed.contains(&serial) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, "this license key was already redeemed"));
+        }
+        self.redeemed.push(serial);
+        self.balance = self.balance.saturating_add(credits);
+        Ok(credits)
+    }
+}
+
+/// Check digits of a key: FNV-1a of its credits and serial, in hex.
+fn key_check(credits: u32, serial: &str) -> String {
+    let hash = format!("{credits}-{}", serial.to_ascii_uppercase())
+        .bytes()
+        .fold(0x811c9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193));
+    format!("{hash:08X}")
+}
+
+/// Credits set aside for one scrape. Workers take one before each lookup, so a
+/// run never spends more than the ledger held when it started. The default
+/// allowance holds none.
+#[derive(Default)]
+pub struct Allowance(AtomicU32);
+
+impl Allowance {
+    /// Take a credit, or return false when none are left.
+    pub fn take(&self) -> bool {
+        self.0.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| left.checked_sub(1)).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::testing::TempFile;
+
+    const KEY: &str = "MP3-500-7F3A9C21-7B288C7B";
+
+    #[test]
+    fn key_checks_are_fnv_hashes_of_credits_and_serial() {
+        assert_eq!(key_check(500, "7F3A9C21"), "7B288C7B");
+        assert_eq!(key_check(500, "7f3a9c21"), "7B288C7B");
+        assert_ne!(key_check(501, "7F3A9C21"), "7B288C7B");
+    }
+
+    #[test]
+    fn redeem_adds_a_key_once() {
+        let file = TempFile::new("license.txt", b"# My license\n\n  MP3-500-7f3a9c21-7b288c7b  \n");
+        let mut ledger = Ledger::default();
+        assert_eq!(ledger.redeem(file.path()).unwrap(), 500);
+        assert_eq!(ledger.balance(), TRIAL_CREDITS + 500);
+
+        let error = ledger.redeem(file.path()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(ledger.balance(), TRIAL_CREDITS + 500);
+    }
+
+    #[test]
+    fn redeem_rejects_bad_keys() {
+        for (name, contents) in [
+            ("license-empty.txt", "# only a comment\n"),
+            ("license-parts.txt", "MP3-500-7F3A9C21"),
+            ("license-credits.txt", "MP3-lots-7F3A9C21-7B288C7B"),
+            ("license-prefix.txt", "MP4-500-7F3A9C21-7B288C7B"),
+            ("license-typo.txt", "MP3-500-7F3A9C12-7B288C7B"),
+            ("license-serial.txt", "MP3-500--7B288C7B"),
+        ] {
+            let file = TempFile::new(name, contents.as_bytes());
+            let mut ledger = Ledger::default();
+            let error = ledger.redeem(file.path()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{contents}");
+            assert_eq!(ledger.balance(), TRIAL_CREDITS);
+        }
+    }
+
+    #[test]
+    fn ledgers_are_saved_and_reopened() {
+        let file = TempFile::new("credits.json", b"");
+        fs::remove_file(file.path()).unwrap();
+        let mut ledger = Ledger::open(file.path().to_path_buf()).unwrap();
+        assert_eq!(ledger.balance(), TRIAL_CREDITS);
+
+        let key = TempFile::new("license-saved.txt", KEY.as_bytes());
+        ledger.redeem(key.path()).unwrap();
+        ledger.spend();
+        ledger.save().unwrap();
+        assert!(!file.path().with_extension("json.tmp").exists());
+
+        let mut ledger = Ledger::open(file.path().to_path_buf()).unwrap();
+        assert_eq!(ledger.balance(), TRIAL_CREDITS + 499);
+        assert_eq!(ledger.redeem(key.path()).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn spending_stops_at_zero() {
+        let mut ledger = Ledger { balance: 1, ..Ledger::default() };
+        ledger.spend();
+        ledger.spend();
+        assert_eq!(ledger.balance(), 0);
+    }
+
+    #[test]
+    fn allowances_hand_out_the_balance_once() {
+        let ledger = Ledger { balance: 50, ..Ledger::default() };
+        let allowance = ledger.allowance();
+        let taken = thread::scope(|scope| {
+            let workers = (0..4)
+                .map(|_| scope.spawn(|| (0..20).filter(|_| allowance.take()).count()))
+                .collect::<Vec<_>>();
+            workers.into_iter().map(|worker| worker.join().unwrap()).sum::<usize>()
+        });
+        assert_eq!(taken, 50);
+        assert!(!allowance.take());
+        assert!(!Allowance::default().take());
+        assert_eq!(ledger.balance(), 50);
+    }
+}
@@ -0,0 +1,131 @@
+// src/decode.rs
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{self, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
+
+/// Decodes the first audio track of a file into interleaved 16-bit samples.
+pub struct Decoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn codecs::Decoder>,
+    track_id: u32,
+    /// Units of the track's timestamps, when the container states them.
+    time_base: Option<TimeBase>,
+    samples: Option<SampleBuffer<i16>>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Length of the track, when the container states it.
+    pub duration: Option<Duration>,
+}
+
+impl Decoder {
+    pub fn open(path: &Path) -> io::Result<Decoder> {
+        let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(to_io)?;
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no audio track"))?;
+
+        let params = &track.codec_params;
+        let sample_rate = params
+            .sample_rate
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown sample rate"))?;
+        let channels = params.channels.map_or(0, |channels| channels.count()) as u16;
+        let duration = params
+            .n_frames
+            .map(|frames| Duration::from_secs_f64(frames as f64 / sample_rate as f64));
+        let decoder = symphonia::default::get_codecs()
+            .make(params, &DecoderOptions::default())
+            .map_err(to_io)?;
+
+        Ok(Decoder {
+            track_id: track.id,
+            time_base: params.time_base,
+            format,
+            decoder,
+            samples: None,
+            sample_rate,
+            channels,
+            duration,
+        })
+    }
+
+    /// Decode the next block of samples, returning false at the end of the
+    /// track. Corrupt packets are skipped.
+    pub fn decode_next(&mut self) -> io::Result<bool> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(to_io(e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(Error::DecodeError(_)) => continue,
+                Err(e) => return Err(to_io(e)),
+            };
+
+            let spec = *decoded.spec();
+            self.channels = spec.channels.count() as u16;
+            let samples = match &mut self.samples {
+                Some(samples) if samples.capacity() >= decoded.capacity() * spec.channels.count() => samples,
+                samples => samples.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+            };
+            samples.copy_interleaved_ref(decoded);
+            return Ok(true);
+        }
+    }
+
+    /// Move to `position` in the track, returning where decoding resumes, which
+    /// may be a little earlier: the start of the packet holding `position`.
+    pub fn seek(&mut self, position: Duration) -> io::Result<Duration> {
+        let to = SeekTo::Time { time: Time::from(position), track_id: Some(self.track_id) };
+        let seeked = self.format.seek(SeekMode::Accurate, to).map_err(to_io)?;
+        // Samples decoded before the seek must not bleed into what follows
+        self.decoder.reset();
+        Ok(match self.time_base {
+            Some(time_base) => {
+                let time = time_base.calc_time(seeked.actual_ts);
+                Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+            }
+            None => position,
+        })
+    }
+
+    /// The interleaved samples of the last decoded block.
+    pub fn samples(&self) -> &[i16] {
+        self.samples.as_ref().map_or(&[], |samples| samples.samples())
+    }
+}
+
+fn to_io(error: Error) -> io::Error {
+    match error {
+        Error::IoError(e) => e,
+        Error::Unsupported(what) => io::Error::new(io::ErrorKind::Unsupported, format!("unsupported {what}")),
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
@@ -0,0 +1,53 @@
+// src/downloader/console.rs
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Lines of output kept above the visible screen.
+const SCROLLBACK: usize = 5_000;
+
+/// The screen of a program running in a pseudo-terminal, shared between the
+/// thread reading its output and the UI drawing it.
+#[derive(Clone)]
+pub struct Console {
+    parser: Arc<Mutex<vt100::Parser>>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console::new(24, 80)
+    }
+}
+
+impl Console {
+    pub fn new(rows: u16, columns: u16) -> Console {
+        Console { parser: Arc::new(Mutex::new(vt100::Parser::new(rows, columns, SCROLLBACK))) }
+    }
+
+    /// Interpret a chunk of the program's output, escape sequences and all.
+    pub fn process(&self, bytes: &[u8]) {
+        self.parser.lock().unwrap().process(bytes);
+    }
+
+    pub fn resize(&self, rows: u16, columns: u16) {
+        self.parser.lock().unwrap().set_size(rows, columns);
+    }
+
+    /// Scroll back towards older output, as far as the scrollback goes.
+    pub fn scroll_up(&self, lines: usize) {
+        let mut parser = self.parser.lock().unwrap();
+        let scrollback = parser.screen().scrollback();
+        parser.set_scrollback(scrollback + lines);
+    }
+
+    /// Scroll forward towards the live screen.
+    pub fn scroll_down(&self, lines: usize) {
+        let mut parser = self.parser.lock().unwrap();
+        let scrollback = parser.screen().scrollback();
+        parser.set_scrollback(scrollback.saturating_sub(lines));
+    }
+
+    /// The parser, locked for drawing its screen.
+    pub fn lock(&self) -> MutexGuard<'_, vt100::Parser> {
+        self.parser.lock().unwrap()
+    }
+}
@@ -0,0 +1,164 @@
+// src/downloader/matcher.rs
+
+use std::io;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::downloader::spotify::Track;
+use crate::downloader::{entry_url, DownloadEvent, Extractor};
+use crate::messages::Message;
+
+/// Search results weighed per query.
+const SEARCH_RESULTS: usize = 5;
+
+/// Below this score, 0-100, a result is more likely another recording than the track.
+const MIN_MATCH_SCORE: u16 = 60;
+
+/// Durations this many seconds apart still count as the same recording.
+const DURATION_TOLERANCE: f64 = 3.0;
+
+/// How much further apart before a result's duration counts for nothing.
+const DURATION_FALLOFF: f64 = 30.0;
+
+/// Words marking another version of a song; they only count against a
+/// result when the track's own title doesn't have them.
+const VERSION_WORDS: [&str; 10] =
+    ["live", "cover", "remix", "karaoke", "instrumental", "acoustic", "nightcore", "sped", "slowed", "reverb"];
+
+/// Audio found for a Spotify track.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AudioMatch {
+    pub url: String,
+    pub title: String,
+    /// How well the audio matches the track, 0-100.
+    pub score: u16,
+}
+
+/// Search for the audio of queue item `item` in the background, reported as
+/// `DownloadEvent::Matched`.
+pub fn search(extractor: Extractor, track: Track, item: usize, messages: Sender<Message>) {
+    thread::spawn(move || {
+        let result = match find_audio(&extractor, &track) {
+            Ok(Some(audio)) => Ok(audio),
+            Ok(None) => Err("no search result is close enough to the track".to_string()),
+            Err(e) => Err(format!("cannot search for the track: {e}")),
+        };
+        let _ = messages.send(Message::Download { item, event: DownloadEvent::Matched(result) });
+    });
+}
+
+/// Search the extractor for the audio of `track`: first by artist and title,
+/// then by ISRC, which finds the label uploads titled after the recording code.
+pub fn find_audio(extractor: &Extractor, track: &Track) -> io::Result<Option<AudioMatch>> {
+    let mut queries = vec![format!("ytsearch{SEARCH_RESULTS}:{} - {}", track.artist, track.title)];
+    if !track.isrc.is_empty() {
+        queries.push(format!("ytsearch{SEARCH_RESULTS}:\"{}\"", track.isrc));
+    }
+
+    let mut best: Option<AudioMatch> = None;
+    for query in queries {
+        let results = extractor.dump_json(&query)?;
+        for result in &results {
+            let Some(url) = entry_url(result) else {
+                continue;
+            };
+            let score = score(track, result);
+            if score >= MIN_MATCH_SCORE && best.as_ref().is_none_or(|best| score > best.score) {
+                let title = result["title"].as_str().unwrap_or_default().to_string();
+                best = Some(AudioMatch { url: url.to_string(), title, score });
+            }
+        }
+        if best.is_some() {
+            break;
+        }
+    }
+    Ok(best)
+}
+
+/// How well a search result matches a track, 0-100: mostly the words of the
+/// title and artist it shares, then how close its duration is.
+fn score(track: &Track, result: &Value) -> u16 {
+    let title = result["title"].as_str().unwrap_or_default();
+    let channel = ["channel", "uploader"].iter().find_map(|key| result[key].as_str()).unwrap_or_default();
+    let found = words(&format!("{title} {channel}"));
+    let wanted_title = words(&track.title);
+    let wanted_artist = words(&track.artist);
+    let share = |wanted: &[String]| match wanted.len() {
+        0 => 1.0,
+        len => wanted.iter().filter(|word| found.contains(word)).count() as f64 / len as f64,
+    };
+    let words_score = 0.7 * share(&wanted_title) + 0.3 * share(&wanted_artist);
+
+    // Flat-playlist searches usually report durations; without one, give half marks
+    let duration_score = match result["duration"].as_f64() {
+        Some(duration) if track.duration > 0 => {
+            let difference = (duration - track.duration as f64).abs();
+            (1.0 - (difference - DURATION_TOLERANCE).max(0.0) / DURATION_FALLOFF).max(0.0)
+        }
+        _ => 0.5,
+    };
+
+    let other_version = VERSION_WORDS
+        .iter()
+        .any(|word| words(title).iter().any(|found| found == word) && !wanted_title.iter().any(|wanted| wanted == word));
+    let penalty = if other_version { 0.5 } else { 1.0 };
+
+    ((60.0 * words_score + 40.0 * duration_score) * penalty).round() as u16
+}
+
+/// Lowercase words, ignoring punctuation.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn track() -> Track {
+        Track { title: "One More Time".to_string(), artist: "Daft Punk".to_string(), duration: 320, ..Track::default() }
+    }
+
+    #[test]
+    fn scores_search_results() {
+        for (result, expected) in [
+            (json!({ "title": "Daft Punk - One More Time (Official Video)", "duration": 322 }), 100),
+            (json!({ "title": "One More Time", "channel": "Daft Punk", "duration": 320.4 }), 100),
+            (json!({ "title": "One More Time", "uploader": "Daft Punk - Topic" }), 80),
+            (json!({ "title": "One More Time", "duration": 320 }), 82),
+            (json!({ "title": "Daft Punk - One More Time", "duration": 338 }), 80),
+            (json!({ "title": "Daft Punk - One More Time", "duration": 400 }), 60),
+            (json!({ "title": "Daft Punk - One More Time (Live)", "duration": 320 }), 50),
+            (json!({ "title": "Daft Punk - Aerodynamic", "duration": 212 }), 18),
+            (json!({}), 20),
+        ] {
+            assert_eq!(score(&track(), &result), expected, "{result}");
+        }
+    }
+
+    #[test]
+    fn version_words_in_the_track_title_are_not_penalised() {
+        let track = Track { title: "One More Time (Live)".to_string(), ..track() };
+        assert_eq!(score(&track, &json!({ "title": "Daft Punk - One More Time Live", "duration": 320 })), 100);
+    }
+
+    #[test]
+    fn tracks_without_a_duration_give_half_marks_for_it() {
+        let track = Track { duration: 0, ..track() };
+        assert_eq!(score(&track, &json!({ "title": "Daft Punk - One More Time", "duration": 320 })), 80);
+    }
+
+    #[test]
+    fn words_are_lowercase_without_punctuation() {
+        assert_eq!(words("Don't Stop -- Me Now!"), ["don", "t", "stop", "me", "now"]);
+        assert!(words(" - ").is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+// src/downloader/mod.rs
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::log::Level;
+use crate::messages::Message;
+use crate::scraper::Scraped;
+use crate::Metadata;
+use console::Console;
+use matcher::AudioMatch;
+use progress::Output;
+use queue::QueueItem;
+
+pub mod console;
+pub mod matcher;
+pub mod progress;
+pub mod queue;
+pub mod spotify;
+pub mod tagger;
+
+/// Run when the Settings tab doesn't name another extractor.
+const DEFAULT_EXTRACTOR: &str = "yt-dlp";
+
+/// Where in the output directory files land, in the extractor's template syntax.
+const OUTPUT_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+/// Progress of a download job, reported as `Message::Download` along with
+/// the index of the queue item it downloads.
+pub enum DownloadEvent {
+    /// A line of output worth showing in the log.
+    Log(Level, String),
+    Progress(DownloadProgress),
+    /// A Spotify track was matched to audio the extractor can download, or wasn't.
+    Matched(Result<AudioMatch, String>),
+    /// The audio of an item was written to this file.
+    Saved(PathBuf),
+    /// A downloaded file was tagged and scraped for its library row, or couldn't be.
+    Tagged { path: PathBuf, result: Result<Box<Scraped>, String> },
+    /// The extractor exited; the job is over.
+    Exited(Result<(), String>),
+}
+
+/// How far the current download has got, as last reported by the extractor.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadProgress {
+    /// Of the whole job, counting finished playlist items, 0-100.
+    pub percent: f64,
+    /// 1-based index and count of playlist items; `(1, 1)` for a single URL.
+    pub item: (usize, usize),
+    pub size: String,
+    pub speed: String,
+    pub eta: String,
+}
+
+/// What the Download tab asks for.
+pub struct DownloadRequest {
+    pub url: String,
+    pub output: PathBuf,
+    /// Audio bitrate in kbit/s, e.g. "320" or "192k"; empty for the best available.
+    pub quality: String,
+}
+
+/// A yt-dlp compatible command line tool that downloads and extracts audio.
+#[derive(Clone)]
+pub struct Extractor {
+    pub program: PathBuf,
+}
+
+impl Extractor {
+    /// The extractor named in the Settings tab, or yt-dlp from `PATH`.
+    pub fn from_setting(setting: &str) -> Extractor {
+        match setting.trim() {
+            "" => Extractor { program: PathBuf::from(DEFAULT_EXTRACTOR) },
+            program => Extractor { program: PathBuf::from(program) },
+        }
+    }
+
+    /// The command downloading `request` as MP3 at the requested bitrate.
+    pub fn command(&self, request: &DownloadRequest) -> io::Result<CommandBuilder> {
+        let mut command = CommandBuilder::new(&self.program);
+        // One progress line per update rather than redrawing with \r, so it can be parsed
+        command.arg("--newline");
+        command.args(["--extract-audio", "--audio-format", "mp3"]);
+        command.args(["--audio-quality", &audio_quality(&request.quality)?]);
+        command.arg("--output");
+        command.arg(request.output.join(OUTPUT_TEMPLATE));
+        command.args(["--", &request.url]);
+        // Relative output paths are relative to where the app was started
+        command.cwd(env::current_dir()?);
+        Ok(command)
+    }
+
+    /// List the entries of a playlist URL in the background, reported as
+    /// `Message::Expanded`. Anything that isn't a playlist lists as itself.
+    pub fn expand(&self, request: DownloadRequest, messages: Sender<Message>) {
+        let extractor = self.clone();
+        thread::spawn(move || {
+            let items = extractor.dump_json(&request.url).map(|entries| list_entries(entries, &request));
+            let _ = messages.send(Message::Expanded { url: request.url, items: items.map_err(|e| e.to_string()) });
+        });
+    }
+
+    /// The JSON `--dump-json` prints for each entry of `target`, a URL or a
+    /// search like `ytsearch5:artist - title`, without downloading anything.
+    pub fn dump_json(&self, target: &str) -> io::Result<Vec<Value>> {
+        let output = Command::new(&self.program)
+            .args(["--flat-playlist", "--dump-json", "--", target])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!("the extractor failed ({})", output.status)));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines = stdout.lines().filter(|line| !line.trim().is_empty());
+        Ok(lines.map(serde_json::from_str).collect::<Result<_, _>>()?)
+    }
+}
+
+/// The page of an entry `--dump-json` printed, if it names one.
+fn entry_url(entry: &Value) -> Option<&str> {
+    ["webpage_url", "url", "original_url"].iter().find_map(|key| entry[key].as_str())
+}
+
+/// Queue items for the entries of a playlist, one per entry.
+fn list_entries(entries: Vec<Value>, request: &DownloadRequest) -> Vec<QueueItem> {
+    entries
+        .iter()
+        .map(|entry| QueueItem {
+            url: entry_url(entry).unwrap_or(&request.url).to_string(),
+            title: entry["title"].as_str().unwrap_or_default().to_string(),
+            output: request.output.clone(),
+            quality: request.quality.clone(),
+            metadata: Some(entry_metadata(entry)),
+            cover_url: entry_thumbnail(entry).unwrap_or_default(),
+            ..QueueItem::default()
+        })
+        .collect()
+}
+
+/// The tags an entry's page has, for the music sites that tell them apart
+/// from the page title. Anything else is left to be inferred from the file name.
+fn entry_metadata(entry: &Value) -> Metadata {
+    let field = |keys: &[&str]| keys.iter().find_map(|key| entry[key].as_str()).unwrap_or_default().to_string();
+    // YouTube's auto-generated "Artist - Topic" channels only carry one artist's songs
+    let topic = entry["channel"].as_str().and_then(|channel| channel.strip_suffix(" - Topic"));
+    let artist = match field(&["artist", "creator"]) {
+        artist if artist.is_empty() => topic.unwrap_or_default().to_string(),
+        artist => artist,
+    };
+    // Pages of a known artist are titled after the song
+    let title = match field(&["track"]) {
+        track if track.is_empty() && !artist.is_empty() => field(&["title"]),
+        track => track,
+    };
+    Metadata {
+        title,
+        artist,
+        album: field(&["album"]),
+        ..Metadata::default()
+    }
+}
+
+/// The entry's largest thumbnail that can be embedded as a cover, which only
+/// JPEG and PNG can; listings give several sizes, the largest last. YouTube
+/// lists mostly WebP ones, but every video has a JPEG one as well.
+fn entry_thumbnail(entry: &Value) -> Option<String> {
+    let listed = entry["thumbnails"].as_array().into_iter().flatten().rev().map(|thumbnail| &thumbnail["url"]);
+    let mut urls = listed.chain([&entry["thumbnail"]]).filter_map(Value::as_str);
+    let embeddable = |url: &&str| {
+        let path = url.split(['?', '#']).next().unwrap_or_default().to_ascii_lowercase();
+        [".jpg", ".jpeg", ".png"].iter().any(|extension| path.ends_with(extension))
+    };
+    if let Some(url) = urls.clone().find(embeddable) {
+        return Some(url.to_string());
+    }
+    let extractor = entry["extractor_key"].as_str().or_else(|| entry["ie_key"].as_str());
+    match (extractor, entry["id"].as_str()) {
+        (Some("Youtube"), Some(id)) => Some(format!("https://i.ytimg.com/vi/{id}/hqdefault.jpg")),
+        _ => urls.next().map(str::to_string),
+    }
+}
+
+/// The extractor's `--audio-quality` for a bitrate typed into the Download tab.
+pub fn audio_quality(quality: &str) -> io::Result<String> {
+    let quality = quality.trim();
+    let bitrate = quality.trim_end_matches(['k', 'K']);
+    if quality.is_empty() {
+        // VBR quality 0, the best the source allows
+        return Ok("0".to_string());
+    }
+    match bitrate.parse::<u16>() {
+        Ok(bitrate) if bitrate > 0 => Ok(format!("{bitrate}K")),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("quality \"{quality}\" is not a bitrate like 320 or 192k"),
+        )),
+    }
+}
+
+/// An extractor process running in a pseudo-terminal, and the thread relaying its output.
+pub struct DownloadJob {
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    /// Closing the terminal would hang the extractor up, so it's kept until the job is dropped.
+    terminal: Box<dyn MasterPty + Send>,
+    input: Box<dyn Write + Send>,
+    /// Whether the user cancelled the download, so its failure is expected.
+    pub interrupted: bool,
+    coordinator: Option<JoinHandle<()>>,
+}
+
+impl DownloadJob {
+    /// Start downloading `request` into its output directory, creating it if
+    /// needed. The extractor runs in a terminal of `console`'s size and draws on it.
+    pub fn start(
+        extractor: &Extractor,
+        request: &DownloadRequest,
+        item: usize,
+        console: Console,
+        messages: Sender<Message>,
+    ) -> io::Result<DownloadJob> {
+        fs::create_dir_all(&request.output)?;
+        let command = extractor.command(request)?;
+
+        let (rows, cols) = console.lock().screen().size();
+        let pair = native_pty_system()
+            .openpty(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 })
+            .map_err(io::Error::other)?;
+        let mut child = pair
+            .slave
+            .spawn_command(command)
+            .map_err(|e| io::Error::other(format!("{e}; install it or set the Extractor Command in Settings")))?;
+        // Only the extractor holds the terminal open now, so its output ends when it exits
+        drop(pair.slave);
+
+        let terminal = pair.master;
+        let output = terminal.try_clone_reader().map_err(io::Error::other)?;
+        let input = terminal.take_writer().map_err(io::Error::other)?;
+        let killer = child.clone_killer();
+        let coordinator = thread::spawn(move || {
+            relay(output, item, &console, &messages);
+            let status = child.wait();
+            let event = DownloadEvent::Exited(exit_result(status));
+            let _ = messages.send(Message::Download { item, event });
+        });
+        Ok(DownloadJob { killer, terminal, input, interrupted: false, coordinator: Some(coordinator) })
+    }
+
+    /// Type Ctrl-C into the extractor's terminal, which stops it and whatever
+    /// it runs, e.g. ffmpeg.
+    pub fn interrupt(&mut self) -> io::Result<()> {
+        self.interrupted = true;
+        self.input.write_all(b"\x03")?;
+        self.input.flush()
+    }
+
+    /// Resize the extractor's terminal, so it can redraw for the new size.
+    pub fn resize(&self, rows: u16, cols: u16) -> io::Result<()> {
+        self.terminal
+            .resize(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 })
+            .map_err(io::Error::other)
+    }
+}
+
+impl Drop for DownloadJob {
+    fn drop(&mut self) {
+        let _ = self.interrupt();
+        let _ = self.killer.kill();
+        if let Some(coordinator) = self.coordinator.take() {
+            let _ = coordinator.join();
+        }
+    }
+}
+
+/// Draw the extractor's output on the console and report what it says,
+/// until the terminal closes.
+fn relay(mut output: impl Read, item: usize, console: &Console, messages: &Sender<Message>) {
+    let send = |event| {
+        let _ = messages.send(Message::Download { item, event });
+    };
+
+    let mut progress = DownloadProgress { item: (1, 1), ..DownloadProgress::default() };
+    let mut buffer = [0; 4096];
+    let mut line = Vec::new();
+    loop {
+        // Linux reports a closed terminal as an error rather than end of file
+        let read = match output.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => read,
+        };
+        console.process(&buffer[..read]);
+
+        for &byte in &buffer[..read] {
+            if byte != b'\n' && byte != b'\r' {
+                line.push(byte);
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let text = progress::strip_escapes(&String::from_utf8_lossy(&line));
+            line.clear();
+            match progress::parse(&text) {
+                Output::Progress { percent, size, speed, eta } => {
+                    let (index, count) = progress.item;
+                    progress = DownloadProgress {
+                        percent: ((index - 1) as f64 * 100.0 + percent) / count as f64,
+                        size,
+                        speed,
+                        eta,
+                        ..progress
+                    };
+                    send(DownloadEvent::Progress(progress.clone()));
+                }
+                Output::Item { index, count } => {
+                    progress.item = (index.clamp(1, count.max(1)), count.max(1));
+                    send(DownloadEvent::Log(Level::Info, text));
+                }
+                Output::Saved(path) => send(DownloadEvent::Saved(path)),
+                // The console shows everything else; only problems go to the log too
+                Output::Text(level, text) if level >= Level::Warn => send(DownloadEvent::Log(level, text)),
+                Output::Text(..) => {}
+            }
+        }
+    }
+}
+
+fn exit_result(status: io::Result<ExitStatus>) -> Result<(), String> {
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("the extractor failed ({status})")),
+        Err(e) => Err(format!("cannot wait for the extractor: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audio_quality_takes_bitrates() {
+        for (quality, expected) in [("", "0"), ("  ", "0"), ("320", "320K"), ("192k", "192K"), (" 128K ", "128K")] {
+            assert_eq!(audio_quality(quality).unwrap(), expected, "{quality:?}");
+        }
+        for quality in ["0", "k", "best", "-128", "99999", "128kbps"] {
+            let error = audio_quality(quality).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{quality:?}");
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// src/downloader/progress.rs
+
+use std::path::PathBuf;
+
+use crate::log::Level;
+
+/// What a line of extractor output says, as far as the Download tab cares.
+pub enum Output {
+    /// `[download]  42.0% of ~3.45MiB at 1.20MiB/s ETA 00:03`
+    Progress { percent: f64, size: String, speed: String, eta: String },
+    /// `[download] Downloading item 3 of 10`, as a playlist moves on to its next entry.
+    Item { index: usize, count: usize },
+    /// `[ExtractAudio] Destination: out/Title.mp3`, the file the audio lands in.
+    Saved(PathBuf),
+    /// Anything else, logged at the level its prefix suggests.
+    Text(Level, String),
+}
+
+/// Parse a line printed by a yt-dlp compatible extractor.
+pub fn parse(line: &str) -> Output {
+    let line = line.trim_end();
+    if let Some(rest) = line.strip_prefix("[download]") {
+        let rest = rest.trim();
+        if let Some(progress) = parse_progress(rest) {
+            return progress;
+        }
+        // Older youtube-dl builds say "video" rather than "item"
+        let item = rest.strip_prefix("Downloading item ").or_else(|| rest.strip_prefix("Downloading video "));
+        if let Some((index, count)) = item.and_then(|item| item.split_once(" of ")) {
+            if let (Ok(index), Ok(count)) = (index.trim().parse(), count.trim().parse()) {
+                return Output::Item { index, count };
+            }
+        }
+        if let Some(path) = rest.strip_suffix(" has already been downloaded") {
+            return Output::Saved(PathBuf::from(path));
+        }
+    }
+    if let Some(path) = line.strip_prefix("[ExtractAudio] Destination: ") {
+        return Output::Saved(PathBuf::from(path));
+    }
+
+    let level = if line.starts_with("ERROR:") {
+        Level::Error
+    } else if line.starts_with("WARNING:") {
+        Level::Warn
+    } else if line.starts_with("[debug]") {
+        Level::Debug
+    } else {
+        Level::Info
+    };
+    Output::Text(level, line.to_string())
+}
+
+/// `42.0% of ~3.45MiB at 1.20MiB/s ETA 00:03`, where everything after the
+/// percentage is optional and "~" marks an estimated size.
+fn parse_progress(rest: &str) -> Option<Output> {
+    let (percent, rest) = rest.split_once('%')?;
+    let percent = percent.trim().parse::<f64>().ok()?;
+    let mut words = rest.split_whitespace();
+    let (mut size, mut speed, mut eta) = (String::new(), String::new(), String::new());
+    while let Some(word) = words.next() {
+        match word {
+            // Newer builds pad estimates as "~  3.45MiB"
+            "of" => match words.next().unwrap_or_default() {
+                "~" => size = words.next().unwrap_or_default().to_string(),
+                word => size = word.trim_start_matches('~').to_string(),
+            },
+            "at" => speed = words.next().unwrap_or_default().to_string(),
+            "ETA" => eta = words.next().unwrap_or_default().to_string(),
+            _ => {}
+        }
+    }
+    Some(Output::Progress { percent: percent.clamp(0.0, 100.0), size, speed, eta })
+}
+
+/// Remove the ANSI escape sequences colouring a line of terminal output.
+pub fn strip_escapes(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        // A control sequence runs from "ESC [" to a letter; other escapes are one character long
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The parsed line in a comparable form.
+    fn parsed(line: &str) -> String {
+        match parse(line) {
+            Output::Progress { percent, size, speed, eta } => format!("{percent}% {size} {speed} {eta}"),
+            Output::Item { index, count } => format!("item {index}/{count}"),
+            Output::Saved(path) => format!("saved {}", path.display()),
+            Output::Text(level, text) => format!("{} {text}", level.label()),
+        }
+    }
+
+    #[test]
+    fn parses_extractor_lines() {
+        for (line, expected) in [
+            ("[download]  42.0% of ~3.45MiB at 1.20MiB/s ETA 00:03", "42% 3.45MiB 1.20MiB/s 00:03"),
+            ("[download]  42.0% of ~  3.45MiB at  1.20MiB/s ETA 00:03", "42% 3.45MiB 1.20MiB/s 00:03"),
+            ("[download] 100% of 3.45MiB in 00:02", "100% 3.45MiB  "),
+            ("[download]   5.5% of 10.00MiB at Unknown B/s ETA Unknown", "5.5% 10.00MiB Unknown Unknown"),
+            ("[download] 120.0%", "100%   "),
+            ("[download] Downloading item 3 of 10", "item 3/10"),
+            ("[download] Downloading video 1 of 2\r\n", "item 1/2"),
+            ("[download] Downloading item three of ten", "INFO [download] Downloading item three of ten"),
+            ("[download] out/Title.mp3 has already been downloaded", "saved out/Title.mp3"),
+            ("[ExtractAudio] Destination: out/Title.mp3", "saved out/Title.mp3"),
+            ("[download] Destination: out/Title.webm", "INFO [download] Destination: out/Title.webm"),
+            ("ERROR: Video unavailable", "ERROR ERROR: Video unavailable"),
+            ("WARNING: falling back to generic", "WARN WARNING: falling back to generic"),
+            ("[debug] Command-line config", "DEBUG [debug] Command-line config"),
+            ("[youtube] abc: Downloading webpage", "INFO [youtube] abc: Downloading webpage"),
+        ] {
+            assert_eq!(parsed(line), expected, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn strips_terminal_escapes() {
+        for (line, expected) in [
+            ("\x1b[0;94m[download]\x1b[0m  42.0%", "[download]  42.0%"),
+            ("\x1b[K\x1b[1;31mERROR:\x1b[0m gone", "ERROR: gone"),
+            ("\x1b7saved\x1b8", "saved"),
+            ("plain", "plain"),
+            ("cut off \x1b[0", "cut off "),
+        ] {
+            assert_eq!(strip_escapes(line), expected, "{line:?}");
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// src/downloader/queue.rs
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::downloader::console::Console;
+use crate::downloader::spotify::Track;
+use crate::downloader::DownloadProgress;
+use crate::Metadata;
+
+/// Parallel downloads when the Settings tab doesn't say otherwise.
+pub const DEFAULT_PARALLEL_DOWNLOADS: usize = 2;
+
+#[derive(Clone, Copy, Default, Display, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemStatus {
+    #[default]
+    Queued,
+    /// Searching for the audio of a Spotify track.
+    Matching,
+    Downloading,
+    Done,
+    Failed,
+    Skipped,
+}
+
+/// One video or track to download, e.g. an entry of a playlist.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueItem {
+    pub url: String,
+    pub title: String,
+    pub output: PathBuf,
+    pub quality: String,
+    pub status: ItemStatus,
+    pub progress: DownloadProgress,
+    /// Audio files the download produced.
+    pub files: Vec<PathBuf>,
+    /// The Spotify track the item was queued for, if it came from a Spotify link.
+    pub track: Option<Track>,
+    /// Where the audio of a Spotify track was found; empty until it's matched.
+    pub audio_url: String,
+    /// Tags known before downloading, for the files the download produces.
+    pub metadata: Option<Metadata>,
+    /// Cover art for the files; empty for none.
+    pub cover_url: String,
+    /// The download session the item was queued in, named after when it started.
+    pub session: String,
+    /// What the extractor drew while downloading the item; not kept across launches.
+    #[serde(skip)]
+    pub console: Console,
+}
+
+impl QueueItem {
+    /// The title, or the URL until the extractor has named the item.
+    pub fn name(&self) -> &str {
+        match self.title.as_str() {
+            "" => &self.url,
+            title => title,
+        }
+    }
+
+    /// Whether the item still has to be matched to audio before it can download.
+    pub fn needs_match(&self) -> bool {
+        self.track.is_some() && self.audio_url.is_empty()
+    }
+
+    /// What the extractor downloads: the matched audio of a Spotify track, or the URL itself.
+    pub fn download_url(&self) -> &str {
+        match self.audio_url.as_str() {
+            "" => &self.url,
+            audio_url => audio_url,
+        }
+    }
+}
+
+/// Every item queued from the Download tab, saved as JSON next to the library
+/// index so an interrupted queue carries on at the next launch.
+#[derive(Default)]
+pub struct DownloadQueue {
+    /// Where the queue is saved; `None` for one that only lives in memory.
+    path: Option<PathBuf>,
+    /// Items are only ever appended, so an index identifies an item for good.
+    pub items: Vec<QueueItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QueueFile {
+    items: Vec<QueueItem>,
+}
+
+impl DownloadQueue {
+    /// The queue location, e.g. `~/.local/share/mp3-cli/downloads.json` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("mp3-cli").join("downloads.json"))
+    }
+
+    /// Open the queue at the default location.
+    pub fn open_default() -> io::Result<DownloadQueue> {
+        let path = Self::default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory for this user"))?;
+        Self::open(path)
+    }
+
+    /// Open the queue at `path`, queueing items that were cut off mid-download
+    /// or mid-search again. A missing file is an empty queue.
+    pub fn open(path: PathBuf) -> io::Result<DownloadQueue> {
+        let mut items = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str::<QueueFile>(&data)?.items,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        for item in items.iter_mut().filter(|item| matches!(item.status, ItemStatus::Matching | ItemStatus::Downloading)) {
+            item.status = ItemStatus::Queued;
+            item.progress = DownloadProgress::default();
+        }
+        Ok(DownloadQueue { path: Some(path), items })
+    }
+
+    /// Write the queue back to disk, through a temporary file like the library index.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = QueueFile { items: self.items.clone() };
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(&file)?)?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Index of the next item waiting to be downloaded.
+    pub fn next_queued(&self) -> Option<usize> {
+        self.items.iter().position(|item| item.status == ItemStatus::Queued)
+    }
+
+    /// Items being matched or downloaded, which count against the parallel download limit.
+    pub fn active(&self) -> usize {
+        self.count(ItemStatus::Matching) + self.count(ItemStatus::Downloading)
+    }
+
+    pub fn count(&self, status: ItemStatus) -> usize {
+        self.items.iter().filter(|item| item.status == status).count()
+    }
+
+    /// Progress of the whole queue, 0-100, leaving skipped items out.
+    pub fn percent(&self) -> f64 {
+        let items = self.items.iter().filter(|item| item.status != ItemStatus::Skipped);
+        let (total, count) = items.fold((0.0, 0), |(total, count), item| match item.status {
+            ItemStatus::Done => (total + 100.0, count + 1),
+            _ => (total + item.progress.percent, count + 1),
+        });
+        match count {
+            0 => 0.0,
+            count => total / count as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    fn item(url: &str, status: ItemStatus) -> QueueItem {
+        let progress = DownloadProgress { percent: 40.0, item: (2, 5), ..DownloadProgress::default() };
+        QueueItem { url: url.to_string(), status, progress, ..QueueItem::default() }
+    }
+
+    #[test]
+    fn reopening_resumes_interrupted_items() {
+        let file = TempFile::new("downloads.json", b"");
+        fs::remove_file(file.path()).unwrap();
+        let mut queue = DownloadQueue::open(file.path().to_path_buf()).unwrap();
+        assert!(queue.items.is_empty());
+
+        let track = Track { id: "4u7EnebtmKWzUH433cf5Qv".to_string(), ..Track::default() };
+        let audio_url = "https://youtu.be/a".to_string();
+        let matched = QueueItem { audio_url, ..item("spotify:b", ItemStatus::Downloading) };
+        queue.items = vec![
+            item("https://youtu.be/done", ItemStatus::Done),
+            QueueItem { track: Some(track.clone()), ..item("spotify:a", ItemStatus::Matching) },
+            QueueItem { track: Some(track), ..matched },
+            item("https://youtu.be/failed", ItemStatus::Failed),
+            item("https://youtu.be/skipped", ItemStatus::Skipped),
+            item("https://youtu.be/queued", ItemStatus::Queued),
+        ];
+        queue.save().unwrap();
+        assert!(!file.path().with_extension("json.tmp").exists());
+
+        let queue = DownloadQueue::open(file.path().to_path_buf()).unwrap();
+        let statuses = queue.items.iter().map(|item| item.status.to_string()).collect::<Vec<_>>();
+        assert_eq!(statuses, ["Done", "Queued", "Queued", "Failed", "Skipped", "Queued"]);
+        assert_eq!(queue.items[0].progress.percent, 40.0);
+        assert_eq!(queue.items[1].progress.percent, 0.0);
+        assert_eq!(queue.items[2].progress.item, (0, 0));
+
+        // A track that was already matched downloads its audio straight away
+        assert!(queue.items[1].needs_match());
+        assert!(!queue.items[2].needs_match());
+        assert_eq!(queue.items[2].download_url(), "https://youtu.be/a");
+        assert_eq!(queue.next_queued(), Some(1));
+        assert_eq!(queue.active(), 0);
+    }
+
+    #[test]
+    fn percent_leaves_skipped_items_out() {
+        let mut queue = DownloadQueue::default();
+        assert_eq!(queue.percent(), 0.0);
+        queue.items = vec![
+            item("a", ItemStatus::Done),
+            item("b", ItemStatus::Downloading),
+            item("c", ItemStatus::Skipped),
+            QueueItem { progress: DownloadProgress::default(), ..item("d", ItemStatus::Queued) },
+        ];
+        assert_eq!(queue.percent(), (100.0 + 40.0) / 3.0);
+        assert_eq!(queue.active(), 1);
+        assert_eq!(queue.items[0].name(), "a");
+    }
+}
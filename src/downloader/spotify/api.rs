@@ -0,0 +1,148 @@
+// src/downloader/spotify/api.rs
+
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::downloader::spotify::{Kind, Link, Track, TrackProvider};
+use crate::tags::leading_number;
+
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const API_URL: &str = "https://api.spotify.com/v1";
+
+/// Tracks the API returns in full per request.
+const TRACKS_PER_REQUEST: usize = 50;
+
+/// Resolves links through the Spotify Web API with an app's client
+/// credentials, which give access to public catalogue data only.
+pub struct SpotifyApi {
+    client_id: String,
+    client_secret: String,
+    /// The access token and when it runs out, fetched on first use.
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl SpotifyApi {
+    pub fn new(client_id: &str, client_secret: &str) -> SpotifyApi {
+        SpotifyApi {
+            client_id: client_id.trim().to_string(),
+            client_secret: client_secret.trim().to_string(),
+            token: Mutex::new(None),
+        }
+    }
+
+    fn token(&self) -> io::Result<String> {
+        let mut token = self.token.lock().unwrap();
+        if let Some((token, _)) = token.as_ref().filter(|(_, expires)| Instant::now() < *expires) {
+            return Ok(token.clone());
+        }
+
+        let response: Value = ureq::post(TOKEN_URL)
+            .send_form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+            ])
+            .map_err(io::Error::other)?
+            .into_json()?;
+        let access_token = text(&response, "access_token");
+        if access_token.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Spotify refused the client credentials"));
+        }
+        // Renew a minute early rather than have a request fail halfway through a playlist
+        let lifetime = response["expires_in"].as_u64().unwrap_or(3600).saturating_sub(60);
+        *token = Some((access_token.clone(), Instant::now() + Duration::from_secs(lifetime)));
+        Ok(access_token)
+    }
+
+    /// GET an API path, or a full URL such as the `next` page of a listing.
+    fn get(&self, path: &str) -> io::Result<Value> {
+        let url = match path.starts_with("https://") {
+            true => path.to_string(),
+            false => format!("{API_URL}{path}"),
+        };
+        let response = ureq::get(&url)
+            .set("Authorization", &format!("Bearer {}", self.token()?))
+            .call()
+            .map_err(io::Error::other)?;
+        response.into_json()
+    }
+
+    /// Every item of a paged listing, following its `next` links.
+    fn pages(&self, mut page: Value) -> io::Result<Vec<Value>> {
+        let mut items = array(&page, "items").to_vec();
+        while let Some(next) = page["next"].as_str() {
+            page = self.get(next)?;
+            items.extend_from_slice(array(&page, "items"));
+        }
+        Ok(items)
+    }
+
+    /// Full track objects for ids; albums only list simplified ones without an ISRC.
+    fn full_tracks(&self, ids: &[String]) -> io::Result<Vec<Track>> {
+        let mut tracks = Vec::new();
+        for ids in ids.chunks(TRACKS_PER_REQUEST) {
+            let response = self.get(&format!("/tracks?ids={}", ids.join(",")))?;
+            tracks.extend(array(&response, "tracks").iter().filter(|track| track.is_object()).map(track));
+        }
+        Ok(tracks)
+    }
+}
+
+impl TrackProvider for SpotifyApi {
+    fn tracks(&self, link: &Link) -> io::Result<Vec<Track>> {
+        match link.kind {
+            Kind::Track => Ok(vec![track(&self.get(&format!("/tracks/{}", link.id))?)]),
+            Kind::Album => {
+                let album = self.get(&format!("/albums/{}", link.id))?;
+                let items = self.pages(album["tracks"].clone())?;
+                let ids: Vec<String> = items.iter().map(|item| text(item, "id")).collect();
+                self.full_tracks(&ids)
+            }
+            Kind::Playlist => {
+                let listing = self.get(&format!("/playlists/{}/tracks", link.id))?;
+                let items = self.pages(listing)?;
+                // Removed tracks come back as null and podcast episodes have another type
+                Ok(items
+                    .iter()
+                    .map(|item| &item["track"])
+                    .filter(|track| track["type"] == "track" && !text(track, "id").is_empty())
+                    .map(track)
+                    .collect())
+            }
+        }
+    }
+}
+
+/// A full track object, as `/tracks` and playlists return it.
+fn track(value: &Value) -> Track {
+    let album = &value["album"];
+    Track {
+        id: text(value, "id"),
+        title: text(value, "name"),
+        artist: artists(value),
+        album: text(album, "name"),
+        album_artist: artists(album),
+        isrc: text(&value["external_ids"], "isrc"),
+        duration: (value["duration_ms"].as_u64().unwrap_or(0) / 1000) as u32,
+        year: leading_number(&text(album, "release_date")),
+        track: value["track_number"].as_u64().unwrap_or(0) as u16,
+        disc: value["disc_number"].as_u64().unwrap_or(0) as u16,
+        // Images come largest first
+        cover_url: array(album, "images").first().map(|image| text(image, "url")).unwrap_or_default(),
+    }
+}
+
+fn artists(value: &Value) -> String {
+    array(value, "artists").iter().map(|artist| text(artist, "name")).collect::<Vec<_>>().join(", ")
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value[key].as_str().unwrap_or_default().to_string()
+}
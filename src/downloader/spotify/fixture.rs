@@ -0,0 +1,116 @@
+// src/downloader/spotify/fixture.rs
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::downloader::spotify::{Kind, Link, Track, TrackProvider};
+
+/// An offline stand-in for the Spotify Web API answering from a JSON file,
+/// so Spotify links can be tried out without an API key. Albums and
+/// playlists are made up of the tracks that name them:
+///
+/// ```json
+/// { "tracks": [
+///     { "id": "0DiWol3AO6WpXZgp0goxAV", "title": "One More Time", "artist": "Daft Punk",
+///       "album": "Discovery", "isrc": "GBDUW0000053", "duration": 320, "track": 1,
+///       "album_id": "2noRn2Aes5aoNVsU6iWThc", "playlists": ["37i9dQZF1DX4o1oenSJRJd"] }
+/// ] }
+/// ```
+pub struct Fixture {
+    tracks: Vec<FixtureTrack>,
+}
+
+#[derive(Deserialize)]
+struct FixtureFile {
+    tracks: Vec<FixtureTrack>,
+}
+
+#[derive(Deserialize)]
+struct FixtureTrack {
+    #[serde(flatten)]
+    track: Track,
+    #[serde(default)]
+    album_id: String,
+    #[serde(default)]
+    playlists: Vec<String>,
+}
+
+impl Fixture {
+    pub fn open(path: PathBuf) -> io::Result<Fixture> {
+        let file: FixtureFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Fixture { tracks: file.tracks })
+    }
+}
+
+impl TrackProvider for Fixture {
+    fn tracks(&self, link: &Link) -> io::Result<Vec<Track>> {
+        let matches = |entry: &&FixtureTrack| match link.kind {
+            Kind::Track => entry.track.id == link.id,
+            Kind::Album => entry.album_id == link.id,
+            Kind::Playlist => entry.playlists.contains(&link.id),
+        };
+        let mut tracks: Vec<Track> = self.tracks.iter().filter(matches).map(|entry| entry.track.clone()).collect();
+        if link.kind == Kind::Album {
+            tracks.sort_by_key(|track| (track.disc, track.track));
+        }
+        if tracks.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("nothing in the fixture has id {}", link.id)));
+        }
+        Ok(tracks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::downloader::spotify;
+    use crate::testing::TempFile;
+
+    fn fixture(name: &str) -> (TempFile, Fixture) {
+        let tracks = json!({ "tracks": [
+            { "id": "t3", "title": "Digital Love", "track": 3, "album_id": "discovery", "playlists": ["mix"] },
+            { "id": "t1", "title": "One More Time", "track": 1, "album_id": "discovery" },
+            { "id": "t9", "title": "Voyager", "track": 1, "disc": 2, "album_id": "discovery", "playlists": ["mix"] },
+            { "id": "x1", "title": "Around the World", "track": 1, "album_id": "homework", "playlists": ["mix"] }
+        ] });
+        let file = TempFile::new(name, tracks.to_string().as_bytes());
+        let fixture = Fixture::open(file.path().to_path_buf()).unwrap();
+        (file, fixture)
+    }
+
+    fn ids(fixture: &Fixture, kind: Kind, id: &str) -> io::Result<Vec<String>> {
+        let tracks = fixture.tracks(&Link { kind, id: id.to_string() })?;
+        Ok(tracks.into_iter().map(|track| track.id).collect())
+    }
+
+    #[test]
+    fn links_resolve_to_the_tracks_naming_them() {
+        let (_file, fixture) = fixture("spotify-fixture.json");
+        assert_eq!(ids(&fixture, Kind::Track, "t1").unwrap(), ["t1"]);
+        assert_eq!(ids(&fixture, Kind::Album, "discovery").unwrap(), ["t1", "t3", "t9"]);
+        // Playlists keep the order of the file
+        assert_eq!(ids(&fixture, Kind::Playlist, "mix").unwrap(), ["t3", "t9", "x1"]);
+    }
+
+    #[test]
+    fn unknown_ids_are_not_found() {
+        let (_file, fixture) = fixture("spotify-fixture-missing.json");
+        let error = ids(&fixture, Kind::Album, "t1").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn fixture_tracks_keep_their_tags() {
+        let (file, _) = fixture("spotify-fixture-setting.json");
+        let provider = spotify::from_setting(&format!("fixture:{}", file.path().display())).unwrap().unwrap();
+        let tracks = provider.tracks(&Link { kind: Kind::Track, id: "t9".to_string() }).unwrap();
+        let metadata = tracks[0].metadata();
+        assert_eq!((metadata.title.as_str(), metadata.track, metadata.disc_no), ("Voyager", 1, 2));
+        assert_eq!(tracks[0].url(), "https://open.spotify.com/track/t9");
+    }
+}
@@ -0,0 +1,182 @@
+// src/downloader/spotify/mod.rs
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::downloader::queue::QueueItem;
+use crate::downloader::DownloadRequest;
+use crate::messages::Message;
+use crate::Metadata;
+
+pub mod api;
+pub mod fixture;
+
+/// What a Spotify link points at.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Track,
+    Album,
+    Playlist,
+}
+
+/// A parsed `https://open.spotify.com/track/<id>` style link, or a `spotify:track:<id>` URI.
+#[derive(Clone)]
+pub struct Link {
+    pub kind: Kind,
+    pub id: String,
+}
+
+impl Link {
+    /// `None` for anything that isn't a Spotify track, album or playlist.
+    pub fn parse(url: &str) -> Option<Link> {
+        let url = url.trim();
+        let path = match url.strip_prefix("spotify:") {
+            Some(uri) => uri.replace(':', "/"),
+            None => {
+                let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+                rest.strip_prefix("open.spotify.com/")?.to_string()
+            }
+        };
+        // Localised links look like open.spotify.com/intl-de/track/<id>
+        let mut segments = path.split(['/', '?', '#']).filter(|segment| !segment.starts_with("intl-"));
+        let kind = match segments.next()? {
+            "track" => Kind::Track,
+            "album" => Kind::Album,
+            "playlist" => Kind::Playlist,
+            _ => return None,
+        };
+        let id = segments.next().filter(|id| !id.is_empty())?;
+        Some(Link { kind, id: id.to_string() })
+    }
+}
+
+/// A track as Spotify describes it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Track {
+    pub id: String,
+    pub title: String,
+    /// Every credited artist, joined with ", ".
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub isrc: String,
+    /// Length in seconds.
+    pub duration: u32,
+    pub year: u16,
+    pub track: u16,
+    pub disc: u16,
+    /// The largest album cover Spotify offers.
+    pub cover_url: String,
+}
+
+impl Track {
+    pub fn url(&self) -> String {
+        format!("https://open.spotify.com/track/{}", self.id)
+    }
+
+    /// Tags for the file the track is downloaded to.
+    pub fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            album: self.album.clone(),
+            album_artist: self.album_artist.clone(),
+            year: self.year,
+            track: self.track,
+            disc_no: self.disc,
+            ..Metadata::default()
+        }
+    }
+}
+
+/// Somewhere Spotify links can be resolved into tracks.
+pub trait TrackProvider: Send + Sync {
+    /// The tracks a link points at, in album or playlist order.
+    fn tracks(&self, link: &Link) -> io::Result<Vec<Track>>;
+}
+
+/// Build the track provider named in the Settings tab: empty for none,
+/// `api:<client id>:<client secret>` for the Spotify Web API or `fixture:<path>`.
+pub fn from_setting(setting: &str) -> io::Result<Option<Box<dyn TrackProvider>>> {
+    let setting = setting.trim();
+    let (kind, argument) = setting.split_once(':').unwrap_or((setting, ""));
+    match kind {
+        "" | "none" => Ok(None),
+        "api" => match argument.split_once(':') {
+            Some((client_id, client_secret)) => Ok(Some(Box::new(api::SpotifyApi::new(client_id, client_secret)))),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "use api:<client id>:<client secret>")),
+        },
+        "fixture" => Ok(Some(Box::new(fixture::Fixture::open(PathBuf::from(argument))?))),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown Spotify source \"{kind}\""))),
+    }
+}
+
+/// Resolve a Spotify link into queue items in the background, reported as
+/// `Message::Expanded`. Each item still has to be matched to audio before it downloads.
+pub fn expand(provider: Box<dyn TrackProvider>, link: Link, request: DownloadRequest, messages: Sender<Message>) {
+    thread::spawn(move || {
+        let items = provider.tracks(&link).map_err(|e| e.to_string()).map(|tracks| {
+            tracks
+                .into_iter()
+                .map(|track| QueueItem {
+                    url: track.url(),
+                    title: format!("{} - {}", track.artist, track.title),
+                    output: request.output.clone(),
+                    quality: request.quality.clone(),
+                    metadata: Some(track.metadata()),
+                    cover_url: track.cover_url.clone(),
+                    track: Some(track),
+                    ..QueueItem::default()
+                })
+                .collect()
+        });
+        let _ = messages.send(Message::Expanded { url: request.url, items });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_links_and_uris() {
+        for (url, kind, id) in [
+            ("https://open.spotify.com/track/4u7EnebtmKWzUH433cf5Qv", Kind::Track, "4u7EnebtmKWzUH433cf5Qv"),
+            (" http://open.spotify.com/album/2noRn2Aes5aoNVsU6iWThc?si=abc ", Kind::Album, "2noRn2Aes5aoNVsU6iWThc"),
+            ("https://open.spotify.com/intl-de/playlist/37i9dQZF1DX4o1oe#top", Kind::Playlist, "37i9dQZF1DX4o1oe"),
+            ("spotify:track:0DiWol3AO6WpXZgp0goxAV", Kind::Track, "0DiWol3AO6WpXZgp0goxAV"),
+        ] {
+            let link = Link::parse(url).unwrap();
+            assert!(link.kind == kind, "{url}");
+            assert_eq!(link.id, id);
+        }
+    }
+
+    #[test]
+    fn ignores_other_links() {
+        for url in [
+            "",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://open.spotify.com/artist/4tZwfgrHOc3mvqYlEYSvVi",
+            "https://open.spotify.com/track/",
+            "spotify:album",
+            "ftp://open.spotify.com/track/4u7EnebtmKWzUH433cf5Qv",
+        ] {
+            assert!(Link::parse(url).is_none(), "{url}");
+        }
+    }
+
+    #[test]
+    fn settings_name_the_source() {
+        assert!(from_setting(" ").unwrap().is_none());
+        assert!(from_setting("none").unwrap().is_none());
+        assert!(from_setting("api:id:secret").unwrap().is_some());
+        assert_eq!(from_setting("api:id").err().unwrap().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(from_setting("deezer").err().unwrap().kind(), io::ErrorKind::InvalidInput);
+    }
+}
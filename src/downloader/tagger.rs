@@ -0,0 +1,176 @@
+// src/downloader/tagger.rs
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::downloader::DownloadEvent;
+use crate::log::Level;
+use crate::messages::Message;
+use crate::scraper::{Scraped, Scraper};
+use crate::tags::{self, Picture, WriteOptions};
+use crate::Metadata;
+
+/// Covers larger than this are not embedded.
+const MAX_COVER_SIZE: u64 = 10 * 1024 * 1024;
+
+/// What to tag the files of a finished download with.
+pub struct TagRequest {
+    pub files: Vec<PathBuf>,
+    /// Tags from the download's source, e.g. the Spotify track; empty fields are left for the scraper.
+    pub metadata: Metadata,
+    /// Cover art to embed; empty for none.
+    pub cover_url: String,
+    /// Put on the library rows, so a download session's files can be found together.
+    pub session_name: String,
+    pub options: WriteOptions,
+}
+
+/// Tag the files of queue item `item` in the background and scrape them
+/// offline for their library rows, reported as `DownloadEvent::Tagged` per file.
+pub fn start(request: TagRequest, scraper: Scraper, item: usize, messages: Sender<Message>) {
+    thread::spawn(move || {
+        let send = |event| {
+            let _ = messages.send(Message::Download { item, event });
+        };
+
+        // One cover serves every file of the item
+        let cover = match request.cover_url.as_str() {
+            "" => None,
+            url => fetch_cover(url)
+                .map_err(|e| send(DownloadEvent::Log(Level::Debug, format!("No cover art from {url}: {e}"))))
+                .ok(),
+        };
+
+        for path in &request.files {
+            // The extractor reports paths relative to where it ran; the library keeps absolute ones
+            let path = &fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            let mut notes = Vec::new();
+            let result = tag(path, &request, &scraper, cover.as_ref(), &mut notes);
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            for (level, note) in notes {
+                send(DownloadEvent::Log(level, format!("{file_name}: {note}")));
+            }
+            send(DownloadEvent::Tagged { path: path.clone(), result: result.map(Box::new).map_err(|e| e.to_string()) });
+        }
+    });
+}
+
+/// Write the source tags over the ones the extractor gave the file at `path`,
+/// scrape it offline to fill the gaps from its file name and rate it, and save
+/// what the scrape settled on back to the file along with the cover, so the
+/// file and its library row agree.
+fn tag(
+    path: &Path,
+    request: &TagRequest,
+    scraper: &Scraper,
+    cover: Option<&Picture>,
+    notes: &mut Vec<(Level, String)>,
+) -> io::Result<Scraped> {
+    // The extractor may have tagged the file already; only what the source knows replaces that
+    let extracted = tags::read_metadata(path)?;
+    let mut sourced = extracted.clone();
+    tags::overlay(&mut sourced, request.metadata.clone());
+    if sourced != extracted {
+        tags::write_metadata(path, &sourced, request.options)?;
+    }
+    let entry = Metadata {
+        path: path.to_string_lossy().into_owned(),
+        session_name: request.session_name.clone(),
+        ..Metadata::default()
+    };
+    let scraped = scraper.scrape(&entry, notes)?;
+    tags::write_metadata(path, &scraped.metadata, request.options)?;
+    // A missing cover is no reason to fail the file
+    if let Some(Err(e)) = cover.map(|cover| tags::write_cover(path, cover)) {
+        notes.push((Level::Debug, format!("Cover art not embedded: {e}")));
+    }
+    Ok(scraped)
+}
+
+fn fetch_cover(url: &str) -> io::Result<Picture> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    let mut data = Vec::new();
+    response.into_reader().take(MAX_COVER_SIZE).read_to_end(&mut data)?;
+    Picture::from_bytes(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credits::Allowance;
+    use crate::scraper::infer::Inference;
+    use crate::tags::tests::jpeg;
+    use crate::testing::TempFile;
+
+    fn scraper() -> Scraper {
+        Scraper { inference: Inference::default(), provider: None, allowance: Allowance::default(), save_to_file: None }
+    }
+
+    fn request(metadata: Metadata) -> TagRequest {
+        TagRequest {
+            files: Vec::new(),
+            metadata,
+            cover_url: String::new(),
+            session_name: "Download 2024-05-01 12:00".to_string(),
+            options: WriteOptions::default(),
+        }
+    }
+
+    #[test]
+    fn source_tags_replace_the_extractors_and_the_cover_is_embedded() {
+        let file = TempFile::new("tagger.mp3", b"\xFF\xFB\x90\x00 not really an MPEG frame");
+        let extracted = Metadata {
+            title: "Daft Punk - One More Time (Official Video)".to_string(),
+            artist: "Daft Punk".to_string(),
+            album: "Discovery".to_string(),
+            ..Metadata::default()
+        };
+        tags::write_metadata(file.path(), &extracted, WriteOptions::default()).unwrap();
+
+        let sourced = Metadata { title: "One More Time".to_string(), year: 2001, track: 1, ..Metadata::default() };
+        let cover = Picture::from_bytes(jpeg()).unwrap();
+        let mut notes = Vec::new();
+        let scraped = tag(file.path(), &request(sourced), &scraper(), Some(&cover), &mut notes).unwrap();
+
+        let metadata = tags::read_metadata(file.path()).unwrap();
+        assert_eq!(metadata.title, "One More Time");
+        assert_eq!(metadata.artist, "Daft Punk");
+        assert_eq!(metadata.album, "Discovery");
+        assert_eq!((metadata.year, metadata.track), (2001, 1));
+        assert_eq!(tags::read_cover(file.path()).unwrap().unwrap().data, jpeg());
+
+        // The library row matches the file
+        assert_eq!(scraped.metadata.title, "One More Time");
+        assert_eq!(scraped.metadata.session_name, "Download 2024-05-01 12:00");
+        assert_eq!(scraped.metadata.path, file.path().to_string_lossy());
+        assert!(scraped.fingerprint.is_none());
+        assert!(!scraped.deep_search);
+        assert!(notes.iter().any(|(_, note)| note.starts_with("Not fingerprinted")));
+    }
+
+    #[test]
+    fn a_cover_that_cannot_be_embedded_is_only_noted() {
+        let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+        flac.extend([0; 34]);
+        flac.extend(b"\xFF\xF8 audio frames");
+        let file = TempFile::new("tagger.flac", &flac);
+
+        let sourced = Metadata { title: "Voyager".to_string(), ..Metadata::default() };
+        let cover = Picture::from_bytes(jpeg()).unwrap();
+        let mut notes = Vec::new();
+        tag(file.path(), &request(sourced), &scraper(), Some(&cover), &mut notes).unwrap();
+
+        assert_eq!(tags::read_metadata(file.path()).unwrap().title, "Voyager");
+        assert!(notes.iter().any(|(_, note)| note.starts_with("Cover art not embedded")));
+    }
+
+    #[test]
+    fn missing_files_fail() {
+        let path = std::env::temp_dir().join(format!("mp3-cli-test-{}-tagger-missing.mp3", std::process::id()));
+        let mut notes = Vec::new();
+        assert!(tag(&path, &request(Metadata::default()), &scraper(), None, &mut notes).is_err());
+    }
+}
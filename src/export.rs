@@ -0,0 +1,356 @@
+// src/export.rs
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use strum::Display;
+
+use crate::fingerprint::Fingerprint;
+use crate::library::Library;
+use crate::scraper::Lookup;
+use crate::Metadata;
+
+/// What the library index can be exported as, from the Scraper tab in developer mode.
+#[derive(Clone, Copy, Default, Display, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    #[strum(to_string = "JSON")]
+    Json,
+    #[strum(to_string = "CSV")]
+    Csv,
+    #[strum(to_string = "SQLite")]
+    Sqlite,
+}
+
+impl Format {
+    pub fn next(self) -> Format {
+        match self {
+            Format::Json => Format::Csv,
+            Format::Csv => Format::Sqlite,
+            Format::Sqlite => Format::Json,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Sqlite => "sqlite",
+        }
+    }
+}
+
+/// Where a new export goes: a file named after the current time in the
+/// `exports` folder of the per-user data directory, created empty so the name
+/// is taken. A second export within the same second gets a `-2` suffix, and so on.
+pub fn default_path(format: Format) -> io::Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory for this user"))?
+        .join("mp3-cli")
+        .join("exports");
+    reserve(&dir, &format!("library-{}", Local::now().format("%Y%m%d-%H%M%S")), format)
+}
+
+/// Create the first file in `dir` named `stem` with a free count suffix.
+fn reserve(dir: &Path, stem: &str, format: Format) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    for count in 1.. {
+        let path = match count {
+            1 => dir.join(format!("{stem}.{}", format.extension())),
+            count => dir.join(format!("{stem}-{count}.{}", format.extension())),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("every count is taken")
+}
+
+/// Everything the index knows about one file.
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(flatten)]
+    metadata: &'a Metadata,
+    fingerprint: Option<&'a Fingerprint>,
+    lookup: Option<&'a Lookup>,
+}
+
+/// Export every entry of `library` with its fingerprint and the provider's
+/// answers to `path`, through a temporary file like the library index.
+pub fn export(library: &Library, path: &Path, format: Format) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let records = library
+        .entries
+        .iter()
+        .map(|entry| Record {
+            metadata: entry,
+            fingerprint: library.fingerprint(&entry.path),
+            lookup: library.lookup(&entry.path),
+        })
+        .collect::<Vec<_>>();
+
+    let temp_path = path.with_extension(format!("{}.tmp", format.extension()));
+    match format {
+        Format::Json => {
+            let exported = serde_json::json!({ "exported": Local::now().to_rfc3339(), "entries": records });
+            fs::write(&temp_path, serde_json::to_vec_pretty(&exported)?)?;
+        }
+        Format::Csv => fs::write(&temp_path, csv(&records)?)?,
+        Format::Sqlite => {
+            // A leftover from a failed export would be added to
+            if let Err(e) = fs::remove_file(&temp_path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+            sqlite(&records, &temp_path).map_err(io::Error::other)?;
+        }
+    }
+    fs::rename(&temp_path, path)
+}
+
+/// The fields of `Metadata` as they're serialized, path first.
+fn metadata_columns() -> io::Result<Vec<(String, Value)>> {
+    let Value::Object(fields) = serde_json::to_value(Metadata::default())? else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "metadata isn't a JSON object"));
+    };
+    let mut columns: Vec<_> = fields.into_iter().collect();
+    columns.sort_by_key(|(name, _)| name != "path");
+    Ok(columns)
+}
+
+/// A JSON value as one spreadsheet cell or database column.
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// One row per file: its metadata, then its fingerprint and the provider's answers as JSON.
+fn csv(records: &[Record]) -> io::Result<String> {
+    let columns = metadata_columns()?;
+    let mut header: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+    header.extend(["fingerprint_duration", "fingerprint_hash", "fingerprint", "lookup_provider", "lookup_matched", "lookup_responses"]);
+    let mut csv = csv_row(header.into_iter().map(str::to_string));
+
+    for record in records {
+        let metadata = serde_json::to_value(record.metadata)?;
+        let mut row: Vec<String> = columns.iter().map(|(name, _)| text(&metadata[name])).collect();
+        match record.fingerprint {
+            Some(fingerprint) => row.extend([fingerprint.duration.to_string(), fingerprint.hash.to_string(), fingerprint.encoded.clone()]),
+            None => row.extend([String::new(), String::new(), String::new()]),
+        }
+        match record.lookup {
+            Some(lookup) => row.extend([lookup.provider.clone(), lookup.matched.clone(), serde_json::to_string(&lookup.responses)?]),
+            None => row.extend([String::new(), String::new(), String::new()]),
+        }
+        csv.push_str(&csv_row(row.into_iter()));
+    }
+    Ok(csv)
+}
+
+/// A line of comma-separated values, quoting the ones that need it.
+fn csv_row(values: impl Iterator<Item = String>) -> String {
+    let values: Vec<String> = values
+        .map(|value| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value
+            }
+        })
+        .collect();
+    values.join(",") + "\r\n"
+}
+
+/// A database with a table for the entries, one for their fingerprints, and
+/// one each for the provider's answers and the recordings in them.
+fn sqlite(records: &[Record], path: &Path) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    let columns = metadata_columns().map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, value)| match (name.as_str(), value) {
+            ("path", _) => "\"path\" TEXT PRIMARY KEY".to_string(),
+            (name, Value::Number(_)) => format!("\"{name}\" INTEGER"),
+            (name, _) => format!("\"{name}\" TEXT"),
+        })
+        .collect();
+    connection.execute_batch(&format!(
+        "CREATE TABLE entries ({});
+         CREATE TABLE fingerprints (path TEXT PRIMARY KEY REFERENCES entries(path), duration INTEGER, hash INTEGER, encoded TEXT);
+         CREATE TABLE responses (id INTEGER PRIMARY KEY, path TEXT REFERENCES entries(path), provider TEXT, query TEXT, error TEXT, matched TEXT);
+         CREATE TABLE candidates (response INTEGER REFERENCES responses(id), id TEXT, score INTEGER, metadata TEXT);",
+        definitions.join(", "),
+    ))?;
+
+    let transaction = connection.transaction()?;
+    {
+        let names: Vec<String> = columns.iter().map(|(name, _)| format!("\"{name}\"")).collect();
+        let placeholders = vec!["?"; names.len()].join(", ");
+        let mut insert_entry =
+            transaction.prepare(&format!("INSERT OR REPLACE INTO entries ({}) VALUES ({placeholders})", names.join(", ")))?;
+        let mut insert_fingerprint = transaction.prepare("INSERT OR REPLACE INTO fingerprints VALUES (?, ?, ?, ?)")?;
+        let mut insert_response =
+            transaction.prepare("INSERT INTO responses (path, provider, query, error, matched) VALUES (?, ?, ?, ?, ?)")?;
+        let mut insert_candidate = transaction.prepare("INSERT INTO candidates VALUES (?, ?, ?, ?)")?;
+
+        for record in records {
+            let metadata = serde_json::to_value(record.metadata).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            let values: Vec<rusqlite::types::Value> = columns
+                .iter()
+                .map(|(name, _)| match &metadata[name] {
+                    Value::Number(number) => number.as_i64().map_or(rusqlite::types::Value::Null, rusqlite::types::Value::Integer),
+                    value => rusqlite::types::Value::Text(text(value)),
+                })
+                .collect();
+            insert_entry.execute(rusqlite::params_from_iter(values))?;
+
+            let path = &record.metadata.path;
+            if let Some(fingerprint) = record.fingerprint {
+                insert_fingerprint.execute(params![path, fingerprint.duration, fingerprint.hash, fingerprint.encoded])?;
+            }
+            let Some(lookup) = record.lookup else {
+                continue;
+            };
+            for response in &lookup.responses {
+                let matched = response.candidates.iter().any(|candidate| candidate.id == lookup.matched);
+                let matched = if matched { lookup.matched.as_str() } else { "" };
+                insert_response.execute(params![path, lookup.provider, response.query, response.error, matched])?;
+                let id = transaction.last_insert_rowid();
+                for candidate in &response.candidates {
+                    let metadata =
+                        serde_json::to_string(&candidate.metadata).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                    insert_candidate.execute(params![id, candidate.id, candidate.score, metadata])?;
+                }
+            }
+        }
+    }
+    transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::Candidate;
+    use crate::scraper::Response;
+    use crate::testing::TempFile;
+
+    fn library() -> Library {
+        let mut library = Library::default();
+        for path in ["/music/a.mp3", "/music/b, \"live\".mp3"] {
+            let title = format!("Title of {path}");
+            library.update(Metadata { path: path.to_string(), title, track: 3, ..Metadata::default() });
+        }
+        library.set_fingerprint("/music/a.mp3", Fingerprint { duration: 200, encoded: "AQAA".to_string(), hash: 42 });
+        let candidate = Candidate { id: "rec-1".to_string(), metadata: Metadata::default(), score: 90 };
+        let responses = vec![
+            Response { query: "search \"A\"".to_string(), candidates: vec![candidate], error: String::new() },
+            Response { query: "fingerprint".to_string(), candidates: Vec::new(), error: "offline".to_string() },
+        ];
+        let lookup = Lookup { provider: "fixture".to_string(), responses, matched: "rec-1".to_string() };
+        library.set_lookup("/music/a.mp3", lookup);
+        library
+    }
+
+    #[test]
+    fn csv_rows_quote_what_needs_it() {
+        let row = |values: &[&str]| csv_row(values.iter().map(|value| value.to_string()));
+        assert_eq!(row(&["plain", "", "3"]), "plain,,3\r\n");
+        assert_eq!(row(&["a,b", "say \"hi\""]), "\"a,b\",\"say \"\"hi\"\"\"\r\n");
+        assert_eq!(row(&["two\nlines", "cr\r"]), "\"two\nlines\",\"cr\r\"\r\n");
+    }
+
+    #[test]
+    fn csv_has_a_row_per_entry() {
+        let file = TempFile::new("export.csv", b"");
+        export(&library(), file.path(), Format::Csv).unwrap();
+        let csv = String::from_utf8(file.bytes()).unwrap();
+        let lines = csv.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("path,"));
+        assert!(lines[0].contains(",fingerprint_duration,fingerprint_hash,fingerprint,lookup_provider,"));
+        assert!(lines[0].ends_with(",lookup_provider,lookup_matched,lookup_responses"));
+        assert!(lines[1].starts_with("/music/a.mp3,"));
+        assert!(lines[1].contains(",200,42,AQAA,fixture,rec-1,\"[{\"\"query\"\":"));
+        assert!(lines[2].starts_with("\"/music/b, \"\"live\"\".mp3\","));
+        assert!(lines[2].ends_with(",,,,,,"));
+        assert_eq!(lines[3], "");
+    }
+
+    #[test]
+    fn sqlite_links_every_table_to_the_entries() {
+        let file = TempFile::new("export.sqlite", b"");
+        // A leftover of a failed export isn't added to
+        let temp_path = file.path().with_extension("sqlite.tmp");
+        fs::write(&temp_path, b"not a database").unwrap();
+        export(&library(), file.path(), Format::Sqlite).unwrap();
+        assert!(!temp_path.exists());
+
+        let connection = Connection::open(file.path()).unwrap();
+        let tables = connection
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(tables, ["candidates", "entries", "fingerprints", "responses"]);
+
+        for (table, parent) in [("fingerprints", "entries"), ("responses", "entries"), ("candidates", "responses")] {
+            let references: String = connection
+                .query_row(&format!("SELECT \"table\" FROM pragma_foreign_key_list('{table}')"), [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(references, parent, "{table}");
+        }
+        let count = |sql: &str| connection.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT count(*) FROM pragma_foreign_key_check"), 0);
+        assert_eq!(count("SELECT count(*) FROM entries WHERE track = 3"), 2);
+        assert_eq!(count("SELECT hash FROM fingerprints WHERE path = '/music/a.mp3'"), 42);
+        assert_eq!(count("SELECT count(*) FROM responses WHERE matched = 'rec-1'"), 1);
+        assert_eq!(count("SELECT count(*) FROM responses WHERE error = 'offline' AND matched = ''"), 1);
+        assert_eq!(count("SELECT score FROM candidates JOIN responses ON responses.id = response"), 90);
+    }
+
+    #[test]
+    fn json_replaces_the_file_through_a_temporary_one() {
+        let file = TempFile::new("export.json", b"an older export");
+        export(&library(), file.path(), Format::Json).unwrap();
+        assert!(!file.path().with_extension("json.tmp").exists());
+
+        let exported: Value = serde_json::from_slice(&file.bytes()).unwrap();
+        assert_eq!(exported["entries"][0]["path"], "/music/a.mp3");
+        assert_eq!(exported["entries"][0]["fingerprint"]["hash"], 42);
+        assert_eq!(exported["entries"][0]["lookup"]["matched"], "rec-1");
+        assert!(exported["entries"][1]["fingerprint"].is_null());
+    }
+
+    #[test]
+    fn reserved_names_are_unique_within_a_second() {
+        let dir = std::env::temp_dir().join(format!("mp3-cli-test-{}-exports", std::process::id()));
+        let stem = "library-20250101-120000";
+        let paths = (0..3).map(|_| reserve(&dir, stem, Format::Csv).unwrap()).collect::<Vec<_>>();
+        let names = paths.iter().map(|path| path.file_name().unwrap().to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(names, [format!("{stem}.csv"), format!("{stem}-2.csv"), format!("{stem}-3.csv")]);
+        assert!(paths.iter().all(|path| path.is_file()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn formats_cycle() {
+        assert!(Format::default().next() == Format::Csv);
+        assert!(Format::Csv.next().next() == Format::Json);
+        assert_eq!(Format::Sqlite.to_string(), "SQLite");
+    }
+}
@@ -0,0 +1,160 @@
+// src/fingerprint.rs
+
+use std::io;
+use std::path::Path;
+
+use rusty_chromaprint::{Configuration, FingerprintCompressor, Fingerprinter};
+use serde::{Deserialize, Serialize};
+
+use crate::decode::Decoder;
+
+/// Only the start of a file is fingerprinted, as `fpcalc` and AcoustID do.
+const MAX_SECONDS: u32 = 120;
+
+/// Files whose lengths differ by more than this are never duplicates.
+pub const DUPLICATE_MAX_DURATION_DIFFERENCE: u32 = 2;
+/// Most bits two hashes of the same recording may differ in.
+const DUPLICATE_MAX_HASH_DISTANCE: u32 = 2;
+
+/// A Chromaprint acoustic fingerprint of a file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Length of the whole file in seconds.
+    pub duration: u32,
+    /// The compressed fingerprint in the URL-safe base64 form `fpcalc` prints
+    /// and AcoustID expects.
+    pub encoded: String,
+    /// A 32-bit similarity hash of the fingerprint; similar audio gives similar hashes.
+    pub hash: u32,
+}
+
+impl Fingerprint {
+    /// Decode the audio of the file at `path` and fingerprint it.
+    pub fn compute(path: &Path) -> io::Result<Fingerprint> {
+        let mut decoder = Decoder::open(path)?;
+        let config = Configuration::preset_test2();
+        let mut fingerprinter = Fingerprinter::new(&config);
+
+        let known_duration = decoder.duration;
+        let mut limit = None;
+        let mut samples_read = 0;
+        while decoder.decode_next()? {
+            let samples = decoder.samples();
+            // Some streams only reveal their channel layout once decoding starts
+            let limit = match limit {
+                Some(limit) => limit,
+                None => {
+                    fingerprinter
+                        .start(decoder.sample_rate, decoder.channels as u32)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
+                    *limit.insert((MAX_SECONDS * decoder.sample_rate) as usize * decoder.channels as usize)
+                }
+            };
+            if samples_read < limit {
+                fingerprinter.consume(&samples[..samples.len().min(limit - samples_read)]);
+            } else if known_duration.is_some() {
+                break;
+            }
+            // Without a stated length the rest is decoded just to measure it
+            samples_read += samples.len();
+        }
+        fingerprinter.finish();
+
+        let frames = samples_read / decoder.channels.max(1) as usize;
+        let duration = known_duration.map_or(frames as u32 / decoder.sample_rate, |duration| duration.as_secs() as u32);
+        let raw = fingerprinter.fingerprint();
+        if raw.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too little audio to fingerprint"));
+        }
+
+        Ok(Fingerprint {
+            duration,
+            encoded: base64_url(&FingerprintCompressor::from(&config).compress(raw)),
+            hash: simhash(raw),
+        })
+    }
+
+    /// Whether two fingerprints are most likely of the same recording.
+    pub fn is_duplicate(&self, other: &Fingerprint) -> bool {
+        self.duration.abs_diff(other.duration) <= DUPLICATE_MAX_DURATION_DIFFERENCE
+            && (self.hash ^ other.hash).count_ones() <= DUPLICATE_MAX_HASH_DISTANCE
+    }
+}
+
+/// Chromaprint's fingerprint hash: each bit is set when it is set in more
+/// than half of the sub-fingerprints.
+fn simhash(raw: &[u32]) -> u32 {
+    (0..32).fold(0, |hash, bit| {
+        let set = raw.iter().filter(|value| *value & (1 << bit) != 0).count();
+        if set * 2 > raw.len() {
+            hash | 1 << bit
+        } else {
+            hash
+        }
+    })
+}
+
+/// Base64 with the URL-safe alphabet and no padding.
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    fn fingerprint(duration: u32, hash: u32) -> Fingerprint {
+        Fingerprint { duration, encoded: String::new(), hash }
+    }
+
+    #[test]
+    fn simhash_keeps_the_bits_most_sub_fingerprints_share() {
+        assert_eq!(simhash(&[0b011, 0b110, 0b010]), 0b010);
+        assert_eq!(simhash(&[0b1, 0b1, 0b0, 0b0]), 0);
+        assert_eq!(simhash(&[u32::MAX]), u32::MAX);
+        assert_eq!(simhash(&[]), 0);
+    }
+
+    #[test]
+    fn base64_url_matches_fpcalc() {
+        assert_eq!(base64_url(b""), "");
+        assert_eq!(base64_url(b"f"), "Zg");
+        assert_eq!(base64_url(b"fo"), "Zm8");
+        assert_eq!(base64_url(b"foo"), "Zm9v");
+        assert_eq!(base64_url(&[0xFB, 0xFF, 0xBF]), "-_-_");
+    }
+
+    #[test]
+    fn duplicates_need_close_lengths_and_hashes() {
+        let original = fingerprint(200, 0b1010_1010);
+        assert!(original.is_duplicate(&fingerprint(200 + DUPLICATE_MAX_DURATION_DIFFERENCE, 0b1010_1001)));
+        assert!(original.is_duplicate(&fingerprint(199, 0b1010_1010)));
+        assert!(!original.is_duplicate(&fingerprint(201 + DUPLICATE_MAX_DURATION_DIFFERENCE, 0b1010_1010)));
+        assert!(!original.is_duplicate(&fingerprint(200, 0b1010_0101)));
+    }
+
+    #[test]
+    fn fingerprints_are_stored_as_json_objects() {
+        let stored = Fingerprint { duration: 215, encoded: "AQADtE".to_string(), hash: 0xDEAD_BEEF };
+        let value = serde_json::to_value(&stored).unwrap();
+        assert_eq!(value, serde_json::json!({ "duration": 215, "encoded": "AQADtE", "hash": 0xDEAD_BEEFu32 }));
+
+        let loaded: Fingerprint = serde_json::from_value(value).unwrap();
+        assert_eq!((loaded.duration, loaded.encoded, loaded.hash), (215, "AQADtE".to_string(), 0xDEAD_BEEF));
+    }
+
+    #[test]
+    fn compute_rejects_files_that_are_not_audio() {
+        let file = TempFile::new("not-audio.mp3", b"not audio at all");
+        assert!(Fingerprint::compute(file.path()).is_err());
+    }
+}
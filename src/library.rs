@@ -0,0 +1,432 @@
+// src/library.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::Display;
+
+use crate::fingerprint::{Fingerprint, DUPLICATE_MAX_DURATION_DIFFERENCE};
+use crate::messages::Message;
+use crate::scraper::score::Status;
+use crate::scraper::Lookup;
+use crate::{tags, Metadata};
+
+/// Version of the on-disk index layout. Bump it together with a new step in `migrate`.
+const SCHEMA_VERSION: u64 = 3;
+
+/// Where versions before the library index kept their entries, relative to
+/// the directory the app was started in.
+const LEGACY_PATH: &str = "src/data.json";
+
+/// The persistent index of every file the scraper has seen, stored as JSON in
+/// the per-user data directory.
+#[derive(Default)]
+pub struct Library {
+    /// Where the index is saved; `None` for a library that only lives in memory.
+    path: Option<PathBuf>,
+    pub entries: Vec<Metadata>,
+    /// Acoustic fingerprints of the entries, by path.
+    fingerprints: BTreeMap<String, Fingerprint>,
+    /// What the metadata provider answered for the entries it was asked about, by path.
+    lookups: BTreeMap<String, Lookup>,
+}
+
+/// Orders the Edit tab can sort the library in. Confidence and status put the
+/// files most in need of attention first.
+#[derive(Clone, Copy, Default, Display, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    #[strum(to_string = "path")]
+    Path,
+    #[strum(to_string = "confidence")]
+    Confidence,
+    #[strum(to_string = "status")]
+    Status,
+}
+
+impl SortOrder {
+    pub fn next(self) -> SortOrder {
+        match self {
+            SortOrder::Path => SortOrder::Confidence,
+            SortOrder::Confidence => SortOrder::Status,
+            SortOrder::Status => SortOrder::Path,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LibraryFile {
+    version: u64,
+    entries: Vec<Metadata>,
+    fingerprints: BTreeMap<String, Fingerprint>,
+    lookups: BTreeMap<String, Lookup>,
+}
+
+impl Library {
+    /// The index location, e.g. `~/.local/share/mp3-cli/library.json` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("mp3-cli").join("library.json"))
+    }
+
+    /// Open the index at the default location. The first time, the entries of
+    /// an old `data.json` are imported into it.
+    pub fn open_default() -> io::Result<Library> {
+        let path = Self::default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory for this user"))?;
+        if !path.exists() {
+            if let Some(library) = Self::import_legacy(path.clone(), Path::new(LEGACY_PATH))? {
+                return Ok(library);
+            }
+        }
+        Self::open(path)
+    }
+
+    /// Start a new index at `path` from the `data.json` at `legacy`, which is
+    /// upgraded like any other version 0 index and left where it is.
+    /// Returns `None` when there is no such file.
+    fn import_legacy(path: PathBuf, legacy: &Path) -> io::Result<Option<Library>> {
+        let data = match fs::read_to_string(legacy) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let file = migrate(serde_json::from_str(&data)?)?;
+        let library = Library { path: Some(path), entries: file.entries, fingerprints: file.fingerprints, lookups: file.lookups };
+        library.save()?;
+        Ok(Some(library))
+    }
+
+    /// Open the index at `path`, upgrading older layouts. A missing file is an empty library.
+    pub fn open(path: PathBuf) -> io::Result<Library> {
+        let file = match fs::read_to_string(&path) {
+            Ok(data) => migrate(serde_json::from_str(&data)?)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                LibraryFile {
+                    version: SCHEMA_VERSION,
+                    entries: Vec::new(),
+                    fingerprints: BTreeMap::new(),
+                    lookups: BTreeMap::new(),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Library { path: Some(path), entries: file.entries, fingerprints: file.fingerprints, lookups: file.lookups })
+    }
+
+    /// Write the index back to disk, through a temporary file so a crash
+    /// mid-write never loses the previous index.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = LibraryFile {
+            version: SCHEMA_VERSION,
+            entries: self.entries.clone(),
+            fingerprints: self.fingerprints.clone(),
+            lookups: self.lookups.clone(),
+        };
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(&file)?)?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Queue a freshly scanned file for scraping. Files already in the index
+    /// keep what was scraped for them and are only marked pending again.
+    pub fn add_pending(&mut self, metadata: Metadata) {
+        match self.entries.iter_mut().find(|entry| entry.path == metadata.path) {
+            Some(entry) => entry.status = metadata.status,
+            None => self.entries.push(metadata),
+        }
+    }
+
+    /// Replace the indexed entry for `metadata.path` with a newly scraped one.
+    pub fn update(&mut self, metadata: Metadata) {
+        match self.entries.iter_mut().find(|entry| entry.path == metadata.path) {
+            Some(entry) => *entry = metadata,
+            None => self.entries.push(metadata),
+        }
+    }
+
+    /// Remember the fingerprint of the file at `path`.
+    pub fn set_fingerprint(&mut self, path: &str, fingerprint: Fingerprint) {
+        self.fingerprints.insert(path.to_string(), fingerprint);
+    }
+
+    /// Number of entries with a fingerprint.
+    pub fn fingerprinted(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Number of entries the metadata provider was asked about.
+    pub fn looked_up(&self) -> usize {
+        self.lookups.len()
+    }
+
+    pub fn fingerprint(&self, path: &str) -> Option<&Fingerprint> {
+        self.fingerprints.get(path)
+    }
+
+    /// Remember what the metadata provider answered for the file at `path`.
+    pub fn set_lookup(&mut self, path: &str, lookup: Lookup) {
+        self.lookups.insert(path.to_string(), lookup);
+    }
+
+    pub fn lookup(&self, path: &str) -> Option<&Lookup> {
+        self.lookups.get(path)
+    }
+
+    /// Groups of paths whose audio is most likely the same recording.
+    pub fn duplicates(&self) -> Vec<Vec<&str>> {
+        // Only files of about the same length can match, so compare neighbours by length
+        let mut by_duration = self.fingerprints.iter().collect::<Vec<_>>();
+        by_duration.sort_by_key(|(_, fingerprint)| fingerprint.duration);
+
+        let mut group_of = vec![None::<usize>; by_duration.len()];
+        let mut groups: Vec<Vec<&str>> = Vec::new();
+        for (i, (path, fingerprint)) in by_duration.iter().enumerate() {
+            for j in i + 1..by_duration.len() {
+                let (other_path, other) = by_duration[j];
+                if other.duration > fingerprint.duration + DUPLICATE_MAX_DURATION_DIFFERENCE {
+                    break;
+                }
+                if group_of[j].is_some() || !fingerprint.is_duplicate(other) {
+                    continue;
+                }
+                let group = *group_of[i].get_or_insert_with(|| {
+                    groups.push(vec![path.as_str()]);
+                    groups.len() - 1
+                });
+                groups[group].push(other_path.as_str());
+                group_of[j] = Some(group);
+            }
+        }
+        groups
+    }
+
+    /// Sort the entries; ties keep their current order.
+    pub fn sort(&mut self, order: SortOrder) {
+        match order {
+            SortOrder::Path => self.entries.sort_by(|a, b| a.path.cmp(&b.path)),
+            SortOrder::Confidence => self.entries.sort_by_key(|entry| entry.percentage),
+            SortOrder::Status => self.entries.sort_by_key(|entry| (Status::of(entry), entry.percentage)),
+        }
+    }
+
+    /// Fold tags re-read from the file at `on_disk.path` into its entry, so the
+    /// index reflects edits made by other programs. Only tags the file has
+    /// replace indexed values: ones the scraper filled in from the file name or
+    /// a provider are kept, as are the scraper-owned fields.
+    pub fn merge_from_disk(&mut self, on_disk: Metadata) {
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.path == on_disk.path) else {
+            return;
+        };
+        tags::overlay(entry, on_disk);
+    }
+}
+
+/// Re-read the tags of every indexed file that still exists in the
+/// background, reported as `Message::Refreshed` for `Library::merge_from_disk`.
+pub fn refresh(library: &Library, messages: Sender<Message>) {
+    let paths: Vec<String> = library.entries.iter().map(|entry| entry.path.clone()).collect();
+    thread::spawn(move || {
+        let entries = paths
+            .iter()
+            .map(Path::new)
+            .filter(|path| path.is_file())
+            .filter_map(|path| tags::read_metadata(path).ok())
+            .collect();
+        let _ = messages.send(Message::Refreshed(entries));
+    });
+}
+
+/// Bring an index of any known layout up to `SCHEMA_VERSION`, one version at a time.
+fn migrate(mut value: Value) -> io::Result<LibraryFile> {
+    loop {
+        let version = match &value {
+            // Version 0 was the bare list of entries the Edit tab used to read from data.json
+            Value::Array(_) => 0,
+            _ => value
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "library index has no version"))?,
+        };
+
+        value = match version {
+            0 => serde_json::json!({ "version": 1, "entries": value }),
+            // Version 2 added acoustic fingerprints
+            1 => {
+                value["version"] = 2.into();
+                value["fingerprints"] = serde_json::json!({});
+                value
+            }
+            // Version 3 added the provider's answers to lookups
+            2 => {
+                value["version"] = 3.into();
+                value["lookups"] = serde_json::json!({});
+                value
+            }
+            SCHEMA_VERSION => return Ok(serde_json::from_value(value)?),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("library index version {version} is newer than this build supports"),
+                ))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::TempFile;
+
+    fn entry(path: &str) -> Value {
+        json!({ "path": path, "title": "Title", "percentage": 80, "status": "Partial" })
+    }
+
+    fn fingerprint(duration: u32, hash: u32) -> Fingerprint {
+        Fingerprint { duration, encoded: String::new(), hash }
+    }
+
+    #[test]
+    fn migrates_every_version_to_the_current_one() {
+        let fingerprints = json!({ "/a.mp3": { "duration": 200, "encoded": "AQAA", "hash": 7 } });
+        let lookup = json!({ "provider": "fixture", "responses": [], "matched": "" });
+        let versions = [
+            json!([entry("/a.mp3")]),
+            json!({ "version": 1, "entries": [entry("/a.mp3")] }),
+            json!({ "version": 2, "entries": [entry("/a.mp3")], "fingerprints": fingerprints }),
+            json!({ "version": 3, "entries": [entry("/a.mp3")], "fingerprints": {}, "lookups": { "/a.mp3": lookup } }),
+        ];
+
+        for (version, value) in versions.into_iter().enumerate() {
+            let file = migrate(value).unwrap();
+            assert_eq!(file.version, SCHEMA_VERSION);
+            assert_eq!(file.entries.len(), 1, "version {version}");
+            assert_eq!((file.entries[0].path.as_str(), file.entries[0].percentage), ("/a.mp3", 80));
+            assert_eq!(file.fingerprints.len(), usize::from(version == 2));
+            assert_eq!(file.lookups.len(), usize::from(version == 3));
+        }
+    }
+
+    #[test]
+    fn refuses_unknown_versions() {
+        let newer = migrate(json!({ "version": SCHEMA_VERSION + 1, "entries": [] })).err().unwrap();
+        assert!(newer.to_string().contains("newer than this build supports"));
+        assert!(migrate(json!({ "entries": [] })).is_err());
+    }
+
+    #[test]
+    fn save_upgrades_the_file_on_disk() {
+        let file = TempFile::new("library.json", json!([entry("/a.mp3")]).to_string().as_bytes());
+        let mut library = Library::open(file.path().to_path_buf()).unwrap();
+        library.set_fingerprint("/a.mp3", fingerprint(200, 7));
+        library.save().unwrap();
+
+        let saved: Value = serde_json::from_slice(&file.bytes()).unwrap();
+        assert_eq!(saved["version"], SCHEMA_VERSION);
+        let library = Library::open(file.path().to_path_buf()).unwrap();
+        assert_eq!(library.entries[0].title, "Title");
+        assert_eq!(library.fingerprint("/a.mp3").map(|fingerprint| fingerprint.hash), Some(7));
+    }
+
+    #[test]
+    fn imports_the_legacy_data_json() {
+        let legacy = TempFile::new("data.json", json!([entry("/a.mp3"), entry("/b.mp3")]).to_string().as_bytes());
+        let index = TempFile::new("imported-library.json", b"");
+        fs::remove_file(index.path()).unwrap();
+
+        let library = Library::import_legacy(index.path().to_path_buf(), legacy.path()).unwrap().unwrap();
+        assert_eq!(library.entries.len(), 2);
+        let saved: Value = serde_json::from_slice(&index.bytes()).unwrap();
+        assert_eq!(saved["version"], SCHEMA_VERSION);
+        assert_eq!(saved["entries"][1]["path"], "/b.mp3");
+        assert!(legacy.path().exists());
+
+        let missing = legacy.path().with_extension("missing");
+        assert!(Library::import_legacy(index.path().to_path_buf(), &missing).unwrap().is_none());
+    }
+
+    #[test]
+    fn merge_from_disk_keeps_scraped_values() {
+        let mut library = Library::default();
+        let scraped = Metadata {
+            path: "/a.mp3".to_string(),
+            title: "Inferred".to_string(),
+            album: "Inferred Album".to_string(),
+            percentage: 70,
+            ..Metadata::default()
+        };
+        library.update(scraped);
+
+        let on_disk = Metadata { path: "/a.mp3".to_string(), title: "Edited".to_string(), ..Metadata::default() };
+        library.merge_from_disk(on_disk);
+        library.merge_from_disk(Metadata { path: "/unknown.mp3".to_string(), ..Metadata::default() });
+
+        assert_eq!(library.entries.len(), 1);
+        let entry = &library.entries[0];
+        assert_eq!((entry.title.as_str(), entry.album.as_str(), entry.percentage), ("Edited", "Inferred Album", 70));
+    }
+
+    #[test]
+    fn sorts_and_finds_duplicates() {
+        let mut library = Library::default();
+        for (path, percentage, status) in [("/c", 90, "Complete"), ("/a", 40, "Partial"), ("/b", 60, "Needs Review")] {
+            let status = status.to_string();
+            library.update(Metadata { path: path.to_string(), percentage, status, ..Metadata::default() });
+        }
+        let paths = |library: &Library| library.entries.iter().map(|entry| entry.path.clone()).collect::<Vec<_>>();
+        library.sort(SortOrder::Path);
+        assert_eq!(paths(&library), ["/a", "/b", "/c"]);
+        library.sort(SortOrder::Confidence);
+        assert_eq!(paths(&library), ["/a", "/b", "/c"]);
+        library.sort(SortOrder::Status);
+        assert_eq!(paths(&library), ["/b", "/a", "/c"]);
+
+        library.set_fingerprint("/a", fingerprint(200, 0b1111));
+        library.set_fingerprint("/b", fingerprint(201, 0b1100));
+        library.set_fingerprint("/c", fingerprint(200, 0b1111_0000));
+        assert_eq!(library.duplicates(), [["/a", "/b"]]);
+    }
+
+    #[test]
+    fn duplicates_chain_and_stay_in_one_group() {
+        let mut library = Library::default();
+        library.set_fingerprint("/a", fingerprint(100, 0b0000));
+        library.set_fingerprint("/b", fingerprint(101, 0b0001));
+        library.set_fingerprint("/c", fingerprint(102, 0b0011));
+        library.set_fingerprint("/d", fingerprint(105, 0b0011));
+        library.set_fingerprint("/e", fingerprint(500, 0b0000));
+        assert_eq!(library.duplicates(), [vec!["/a", "/b", "/c"]]);
+        assert_eq!(library.fingerprinted(), 5);
+    }
+
+    #[test]
+    fn fingerprints_are_saved_with_the_index() {
+        let file = TempFile::new("fingerprints-library.json", b"");
+        fs::remove_file(file.path()).unwrap();
+        let mut library = Library::open(file.path().to_path_buf()).unwrap();
+        library.update(serde_json::from_value(entry("/a.mp3")).unwrap());
+        library.set_fingerprint("/a.mp3", Fingerprint { duration: 200, encoded: "AQAA".to_string(), hash: 7 });
+        library.save().unwrap();
+
+        let saved: Value = serde_json::from_slice(&file.bytes()).unwrap();
+        assert_eq!(saved["fingerprints"]["/a.mp3"], json!({ "duration": 200, "encoded": "AQAA", "hash": 7 }));
+        let library = Library::open(file.path().to_path_buf()).unwrap();
+        assert_eq!(library.fingerprint("/a.mp3").unwrap().encoded, "AQAA");
+        assert!(library.fingerprint("/b.mp3").is_none());
+    }
+}
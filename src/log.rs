@@ -0,0 +1,221 @@
+// src/log.rs
+
+use std::collections::VecDeque;
+
+use chrono::Local;
+
+/// Oldest entries are dropped once a log holds this many.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+
+    /// The next stricter level, wrapping back around to `Debug`.
+    fn next(self) -> Level {
+        match self {
+            Level::Debug => Level::Info,
+            Level::Info => Level::Warn,
+            Level::Warn => Level::Error,
+            Level::Error => Level::Debug,
+        }
+    }
+}
+
+pub struct Entry {
+    /// Local wall-clock time the entry was logged, as `HH:MM:SS`.
+    pub time: String,
+    pub level: Level,
+    pub text: String,
+}
+
+/// A timestamped log shown in a tab's Logs pane, with a minimum level
+/// filter and a scroll position counted up from the newest entry.
+#[derive(Default)]
+pub struct Log {
+    entries: VecDeque<Entry>,
+    /// Entries below this level are hidden.
+    pub filter: Level,
+    /// Number of visible entries scrolled back from the bottom; 0 follows new entries.
+    scroll: usize,
+}
+
+impl Log {
+    pub fn push(&mut self, level: Level, text: impl Into<String>) {
+        let evicted = if self.entries.len() == MAX_ENTRIES { self.entries.pop_front() } else { None };
+        self.entries.push_back(Entry { time: Local::now().format("%H:%M:%S").to_string(), level, text: text.into() });
+
+        // Keep the view still while scrolled back
+        if self.scroll > 0 && level >= self.filter {
+            self.scroll += 1;
+        }
+        // ...but never past the oldest entry left once a shown one was dropped
+        if self.scroll > 0 && evicted.is_some_and(|entry| entry.level >= self.filter) {
+            self.scroll = self.scroll.min(self.shown().saturating_sub(1));
+        }
+    }
+
+    /// Number of entries passing the filter.
+    fn shown(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.level >= self.filter).count()
+    }
+
+    pub fn debug(&mut self, text: impl Into<String>) {
+        self.push(Level::Debug, text);
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Level::Info, text);
+    }
+
+    pub fn warn(&mut self, text: impl Into<String>) {
+        self.push(Level::Warn, text);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Level::Error, text);
+    }
+
+    /// The entries passing the filter that fit in `height` lines at the current scroll position.
+    pub fn visible(&self, height: usize) -> Vec<&Entry> {
+        let shown = self.entries.iter().filter(|entry| entry.level >= self.filter).collect::<Vec<_>>();
+        let end = shown.len().saturating_sub(self.scroll);
+        shown[end.saturating_sub(height)..end].to_vec()
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.shown().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// Show only stricter levels, jumping back to the newest entry.
+    pub fn cycle_filter(&mut self) {
+        self.filter = self.filter.next();
+        self.scroll = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(log: &Log, height: usize) -> Vec<&str> {
+        log.visible(height).iter().map(|entry| entry.text.as_str()).collect()
+    }
+
+    fn numbered(count: usize) -> Log {
+        let mut log = Log::default();
+        for i in 0..count {
+            log.info(i.to_string());
+        }
+        log
+    }
+
+    #[test]
+    fn visible_shows_the_newest_entries_passing_the_filter() {
+        let mut log = Log::default();
+        log.debug("a");
+        log.info("b");
+        log.warn("c");
+        log.error("d");
+        assert_eq!(texts(&log, 2), ["c", "d"]);
+        assert_eq!(texts(&log, 10), ["a", "b", "c", "d"]);
+
+        log.cycle_filter();
+        log.cycle_filter();
+        assert_eq!(texts(&log, 10), ["c", "d"]);
+        assert_eq!(texts(&log, 0), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn scroll_up_stops_at_the_oldest_entry() {
+        let mut log = numbered(5);
+        log.scroll_up(2);
+        assert_eq!(texts(&log, 2), ["1", "2"]);
+        log.scroll_up(100);
+        assert_eq!(texts(&log, 2), ["0"]);
+        log.scroll_down(1);
+        assert_eq!(texts(&log, 2), ["0", "1"]);
+        log.scroll_down(100);
+        assert_eq!(texts(&log, 2), ["3", "4"]);
+    }
+
+    #[test]
+    fn push_keeps_a_scrolled_back_view_still() {
+        let mut log = numbered(5);
+        log.scroll_up(1);
+        log.info("5");
+        assert_eq!(texts(&log, 2), ["2", "3"]);
+
+        // Hidden entries don't move the view
+        log.filter = Level::Warn;
+        log.warn("6");
+        log.warn("7");
+        log.scroll_up(1);
+        log.warn("8");
+        log.info("9");
+        assert_eq!(texts(&log, 1), ["6"]);
+    }
+
+    #[test]
+    fn push_follows_new_entries_when_not_scrolled() {
+        let mut log = numbered(3);
+        log.info("3");
+        assert_eq!(texts(&log, 2), ["2", "3"]);
+    }
+
+    #[test]
+    fn cycle_filter_wraps_and_jumps_to_the_newest_entry() {
+        let mut log = numbered(5);
+        log.scroll_up(3);
+        log.cycle_filter();
+        assert!(log.filter == Level::Info);
+        assert_eq!(log.scroll, 0);
+        log.cycle_filter();
+        log.cycle_filter();
+        assert!(log.filter == Level::Error);
+        log.cycle_filter();
+        assert!(log.filter == Level::Debug);
+    }
+
+    #[test]
+    fn push_drops_the_oldest_entry_when_full() {
+        let mut log = numbered(MAX_ENTRIES);
+        log.info("last");
+        assert_eq!(log.entries.len(), MAX_ENTRIES);
+        assert_eq!(log.entries.front().unwrap().text, "1");
+        assert_eq!(texts(&log, 1), ["last"]);
+    }
+
+    #[test]
+    fn eviction_keeps_the_scroll_on_the_oldest_entry() {
+        let mut log = numbered(MAX_ENTRIES);
+        log.scroll_up(MAX_ENTRIES);
+        assert_eq!(texts(&log, 2), ["0"]);
+        log.info("last");
+        assert_eq!(texts(&log, 2), ["1"]);
+
+        // A view in the middle stays on the same entries
+        log.scroll = 10;
+        log.info("more");
+        assert_eq!(texts(&log, 1), [(MAX_ENTRIES - 10).to_string()]);
+    }
+}
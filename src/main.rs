@@ -1,8 +1,26 @@
+mod credits;
+mod decode;
+mod downloader;
+mod export;
+mod fingerprint;
+mod library;
+mod log;
+mod messages;
+mod player;
+mod providers;
+mod scraper;
+mod settings;
 mod tabs;
+mod tags;
+#[cfg(test)]
+mod testing;
+use tabs::download::DownloadTab;
+use tabs::play;
 use tabs::SelectedTab;
+use chrono::Local;
 use color_eyre::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -13,10 +31,31 @@ use ratatui::{
     style::{palette::tailwind, Color, Stylize},
     symbols,
     text::Line,
-    widgets::{Block, Padding, Tabs, Widget},
+    widgets::{Block, Clear, Padding, Paragraph, Tabs, Widget},
     Terminal,
 };
+use credits::{Allowance, Ledger};
+use downloader::console::Console;
+use downloader::queue::{DownloadQueue, ItemStatus, QueueItem};
+use downloader::tagger::{self, TagRequest};
+use downloader::{matcher, spotify, DownloadEvent, DownloadJob, DownloadProgress, DownloadRequest, Extractor};
+use library::{Library, SortOrder};
+use messages::{Message, Messages};
+use player::browser::{LibraryTree, Node};
+use player::queue::PlayQueue;
+use player::{sink, Command, PlaybackEvent, PlaybackState, Player};
+use log::{Level, Log};
+use scraper::infer::Inference;
+use scraper::{Scraped, Scraper};
+use scraper::pool::{ScrapeEvent, ScrapePool, ScrapeProgress};
+use scraper::scanner::ScanOptions;
+use scraper::score::{self, Status};
+use serde::{Deserialize, Serialize};
+use settings::{Action, Settings, SettingsForm, Theme};
+use std::collections::BTreeMap;
 use std::io::{self, stdout};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use strum::IntoEnumIterator;
 
 fn main() -> Result<()> {
@@ -26,7 +65,35 @@ fn main() -> Result<()> {
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let terminal = Terminal::new(backend)?;
-    let app_result = App::default().run(terminal);
+    let mut app = App::default();
+    match Settings::open_default() {
+        Ok(settings) => app.apply_settings(settings),
+        Err(e) => app.settings_status = format!("Failed to open the settings, using the defaults: {e}"),
+    }
+    match Library::open_default() {
+        Ok(library) => {
+            library::refresh(&library, app.messages.sender());
+            app.library = library;
+        }
+        Err(e) => app.edit_status = format!("Failed to open the library index: {e}"),
+    }
+    match Ledger::open_default() {
+        Ok(credits) => app.credits = credits,
+        Err(e) => app.scraper_log.error(format!("Failed to open the Deep Search credits: {e}")),
+    }
+    match DownloadQueue::open_default() {
+        Ok(queue) => app.download_queue = queue,
+        Err(e) => app.download_log.error(format!("Failed to open the download queue: {e}")),
+    }
+    let queued = app.download_queue.count(ItemStatus::Queued);
+    if queued > 0 {
+        app.download_log.info(format!("Resuming {queued} queued downloads"));
+        app.pump_downloads();
+    }
+    app.player = Some(Player::start(sink::open_default, app.messages.sender()));
+    app.select_file(0);
+    app.play_tree.rebuild(&app.library.entries);
+    let app_result = app.run(terminal);
     disable_raw_mode()?;
     execute!(io::stdout(), LeaveAlternateScreen)?;
     app_result
@@ -37,26 +104,55 @@ struct App {
     state: AppState,
     mode: AppMode,
     selected_tab: SelectedTab,
-    
+    messages: Messages, // Progress reported by background jobs
+
     // Scraper-related fields
     pub scraper_directory: String,
-    pub scraper_progress: u16,
+    pub scraper_progress: ScrapeProgress,
+    pub scraper_log: Log,
+    pub scraper_pool: Option<ScrapePool>,
+    pub credits: Ledger, // Deep Search credits left for provider lookups
+    pub scraper_export_format: export::Format,
 
     // Download-related fields
     pub download_url: String,
     pub download_output: String,
     pub download_quality: String,
+    pub download_log: Log,
+    pub download_queue: DownloadQueue, // Every item queued for download, saved across launches
+    pub download_jobs: BTreeMap<usize, DownloadJob>, // Running downloads, by queue item
+    pub download_selected: usize, // Queue item shown in the console
+
+    // Playback-related fields
+    pub player: Option<Player>, // Started in main; `None` means nothing can be played
+    pub playback: PlaybackState,
+    pub play_tree: LibraryTree, // The library by artist and album, for picking tracks
+    pub play_queue: PlayQueue,
+    pub play_selected: usize, // Selected row of the play queue
     pub edit_selected_field: usize,
 
     // Metadata fields for editing
-    pub metadata: Vec<Metadata>, // List of metadata entries for all files
+    pub library: Library,         // Index of every scraped file
     pub selected_file: Metadata,  // The file currently selected for editing
-    
+    pub edit_selected_row: usize, // Row of `library` loaded into `selected_file`
+    pub edit_changed: bool,       // Whether `selected_file` has edits not saved yet
+    pub edit_cover: String,       // What cover art the file being edited has
+    pub edit_status: String,      // Outcome of the last save
+    pub edit_sort: SortOrder,     // Order of the metadata table
+
+    // Settings-related fields
+    pub settings: Settings, // As last saved; what the rest of the app goes by
+    pub settings_form: SettingsForm, // The Settings tab's fields, as edited
+    pub settings_license: String, // Path of a license key file to redeem for Deep Search credits
+    pub settings_status: String, // Outcome of the last save or license key redeemed
+
     home_scroll: u16,
+    debug_overlay: bool, // Internal state drawn over every tab, in developer mode
 }
 
 // Metadata struct for holding file metadata
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metadata {
     pub file_name: String,
     pub percentage: u16,
@@ -77,6 +173,33 @@ pub struct Metadata {
     pub session_name: String,
 }
 
+/// Index of the last field of the Settings tab's form (License Key File, after the settings).
+const SETTINGS_LAST_FIELD: usize = settings::FIELDS.len();
+
+/// The Play tab's focus is on the play queue rather than the library tree.
+const PLAY_QUEUE_FIELD: usize = 1;
+
+/// Seconds the seek keys seek by.
+const SEEK_STEP: i64 = 5;
+
+/// Percentage points the volume keys change the volume by.
+const VOLUME_STEP: u8 = 10;
+
+/// Index of the last field of the Download tab's form (the queue table).
+const DOWNLOAD_LAST_FIELD: usize = 5;
+
+/// Index of the Scraper tab's Export Index button, its last field in developer mode.
+const SCRAPER_EXPORT_FIELD: usize = 3;
+
+/// How long the event loop waits for input before checking on background jobs.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Lines a Logs pane scrolls per PgUp/PgDn.
+const LOG_PAGE: usize = 10;
+
+/// Index of the last field of the Edit tab's form (Session Name).
+const EDIT_LAST_FIELD: usize = 14;
+
 #[derive(Default, Clone, Copy, PartialEq, Eq)]
 enum AppState {
     #[default]
@@ -96,53 +219,144 @@ impl App {
         while self.state == AppState::Running {
             terminal.draw(|frame| frame.render_widget(&self, frame.size()))?;
             self.handle_events()?;
+            self.handle_messages();
         }
         Ok(())
     }
 
+    /// Handle at most one input event, waiting up to `TICK` for it so
+    /// background jobs still get to update the screen while no key is pressed.
     fn handle_events(&mut self) -> std::io::Result<()> {
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
+        if !event::poll(TICK)? {
+            return Ok(());
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                let is_save = key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('s');
+                if is_save && self.mode == AppMode::InsideTab && self.selected_tab == SelectedTab::Edit {
+                    self.save_selected_file();
+                    return Ok(());
+                }
+                if is_save && self.mode == AppMode::InsideTab && self.selected_tab == SelectedTab::Settings {
+                    self.save_settings();
+                    return Ok(());
+                }
+
+                let is_interrupt = key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+                if is_interrupt && self.selected_tab == SelectedTab::Download {
+                    self.interrupt_download();
+                    return Ok(());
+                }
+
                 match self.mode {
                     AppMode::Navigation => self.handle_navigation_mode(key.code),
                     AppMode::InsideTab => self.handle_inside_tab_mode(key.code),
                 }
             }
+            Event::Resize(columns, rows) => self.resize_console(columns, rows),
+            _ => {}
         }
         Ok(())
     }
 
     fn handle_navigation_mode(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Char('l') | KeyCode::Right => self.next_tab(),
-            KeyCode::Char('h') | KeyCode::Left => self.previous_tab(),
-            KeyCode::Char('q') | KeyCode::Esc => self.quit(),
-            KeyCode::Enter => self.mode = AppMode::InsideTab,
+        if self.handle_global_key(key) {
+            return;
+        }
+        match (key, self.settings.keybindings.action(key)) {
+            (KeyCode::Right, _) | (_, Some(Action::NextTab)) => self.next_tab(),
+            (KeyCode::Left, _) | (_, Some(Action::PreviousTab)) => self.previous_tab(),
+            (KeyCode::Esc, _) | (_, Some(Action::Quit)) => self.quit(),
+            (KeyCode::Enter, _) => self.mode = AppMode::InsideTab,
             _ => {}
         }
     }
 
     fn handle_inside_tab_mode(&mut self, key: KeyCode) {
+        // Typing into a field takes every character, but not the function keys
+        let typed = self.is_typing() && matches!(key, KeyCode::Char(_));
+        if !typed && self.handle_global_key(key) {
+            return;
+        }
         match key {
             KeyCode::Esc => self.mode = AppMode::Navigation,
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Download && self.edit_selected_field == 4 => {
+                self.start_download();
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Scraper && self.edit_selected_field == 1 => {
+                self.start_scrape();
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Scraper && self.edit_selected_field == 2 => {
+                self.start_estimate();
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Scraper && self.edit_selected_field == SCRAPER_EXPORT_FIELD => {
+                self.export_index();
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Play && self.edit_selected_field == PLAY_QUEUE_FIELD => {
+                self.play_queue_track(self.play_selected);
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Play => self.open_tree_node(),
+
+            KeyCode::Left if self.selected_tab == SelectedTab::Play => self.play_tree.set_expanded(false),
+
+            KeyCode::Right if self.selected_tab == SelectedTab::Play => self.play_tree.set_expanded(true),
+
+            KeyCode::Tab if self.selected_tab == SelectedTab::Play => {
+                self.edit_selected_field = if self.edit_selected_field == PLAY_QUEUE_FIELD { 0 } else { PLAY_QUEUE_FIELD };
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Settings && self.edit_selected_field == SETTINGS_LAST_FIELD => {
+                self.redeem_license();
+            }
+
+            KeyCode::Enter if self.selected_tab == SelectedTab::Settings => {
+                self.settings_status.clear();
+                self.settings_form.toggle(self.edit_selected_field);
+            }
     
             KeyCode::Up => {
                 if self.selected_tab == SelectedTab::Download {
-                    if self.edit_selected_field > 1 {
+                    if self.edit_selected_field > 1 && self.edit_selected_field <= DOWNLOAD_LAST_FIELD {
                         self.edit_selected_field -= 1;
                     } else {
-                        self.edit_selected_field = 4;
+                        self.edit_selected_field = DOWNLOAD_LAST_FIELD;
                     }
                 }
 
                 if self.selected_tab == SelectedTab::Scraper {
-                    if self.edit_selected_field == 1 {
-                        self.edit_selected_field = 0;
+                    if self.edit_selected_field > 0 && self.edit_selected_field <= self.scraper_last_field() {
+                        self.edit_selected_field -= 1;
                     } else {
-                        self.edit_selected_field = 1;
+                        self.edit_selected_field = self.scraper_last_field();
+                    }
+                }
+
+                if self.selected_tab == SelectedTab::Edit {
+                    if self.edit_selected_field > 0 && self.edit_selected_field <= EDIT_LAST_FIELD {
+                        self.edit_selected_field -= 1;
+                    } else {
+                        self.edit_selected_field = EDIT_LAST_FIELD;
+                    }
+                }
+
+                if self.selected_tab == SelectedTab::Settings {
+                    if self.edit_selected_field > 0 && self.edit_selected_field <= SETTINGS_LAST_FIELD {
+                        self.edit_selected_field -= 1;
+                    } else {
+                        self.edit_selected_field = SETTINGS_LAST_FIELD;
                     }
                 }
 
+                if self.selected_tab == SelectedTab::Play {
+                    self.select_in_play_tab(-1);
+                }
+
                 if self.selected_tab == SelectedTab::Home {
                     self.home_scroll = self.home_scroll.saturating_sub(1);
                 }
@@ -150,7 +364,7 @@ impl App {
 
             KeyCode::Down => {
                 if self.selected_tab == SelectedTab::Download {
-                    if self.edit_selected_field < 4 {
+                    if self.edit_selected_field < DOWNLOAD_LAST_FIELD {
                         self.edit_selected_field += 1;
                     } else {
                         self.edit_selected_field = 1;
@@ -158,17 +372,75 @@ impl App {
                 }
 
                 if self.selected_tab == SelectedTab::Scraper {
-                    if self.edit_selected_field == 0 {
-                        self.edit_selected_field = 1;
+                    if self.edit_selected_field < self.scraper_last_field() {
+                        self.edit_selected_field += 1;
+                    } else {
+                        self.edit_selected_field = 0;
+                    }
+                }
+
+                if self.selected_tab == SelectedTab::Edit {
+                    if self.edit_selected_field < EDIT_LAST_FIELD {
+                        self.edit_selected_field += 1;
+                    } else {
+                        self.edit_selected_field = 0;
+                    }
+                }
+
+                if self.selected_tab == SelectedTab::Settings {
+                    if self.edit_selected_field < SETTINGS_LAST_FIELD {
+                        self.edit_selected_field += 1;
                     } else {
                         self.edit_selected_field = 0;
                     }
                 }
 
+                if self.selected_tab == SelectedTab::Play {
+                    self.select_in_play_tab(1);
+                }
+
                 if self.selected_tab == SelectedTab::Home {
                     self.home_scroll += 1; // Increase scroll offset
                 }
             }
+
+            KeyCode::PageUp if self.selected_tab == SelectedTab::Scraper => self.scraper_log.scroll_up(LOG_PAGE),
+
+            KeyCode::PageDown if self.selected_tab == SelectedTab::Scraper => self.scraper_log.scroll_down(LOG_PAGE),
+
+            KeyCode::Tab if self.selected_tab == SelectedTab::Scraper => self.scraper_log.cycle_filter(),
+
+            KeyCode::PageUp if self.selected_tab == SelectedTab::Download && self.edit_selected_field == DOWNLOAD_LAST_FIELD => {
+                self.select_download(-1);
+            }
+
+            KeyCode::PageDown if self.selected_tab == SelectedTab::Download && self.edit_selected_field == DOWNLOAD_LAST_FIELD => {
+                self.select_download(1);
+            }
+
+            KeyCode::PageUp if self.selected_tab == SelectedTab::Download => {
+                if let Some(console) = self.selected_console() {
+                    console.scroll_up(LOG_PAGE);
+                }
+            }
+
+            KeyCode::PageDown if self.selected_tab == SelectedTab::Download => {
+                if let Some(console) = self.selected_console() {
+                    console.scroll_down(LOG_PAGE);
+                }
+            }
+
+            KeyCode::Tab if self.selected_tab == SelectedTab::Download => self.download_log.cycle_filter(),
+
+            KeyCode::Tab if self.selected_tab == SelectedTab::Edit => self.cycle_sort_order(),
+
+            KeyCode::PageUp if self.selected_tab == SelectedTab::Edit => {
+                self.select_file(self.edit_selected_row.saturating_sub(1));
+            }
+
+            KeyCode::PageDown if self.selected_tab == SelectedTab::Edit => {
+                self.select_file(self.edit_selected_row + 1);
+            }
     
             KeyCode::Backspace => {
                 if self.selected_tab == SelectedTab::Download {
@@ -180,10 +452,19 @@ impl App {
                     }
                 }
 
-                if self.selected_tab == SelectedTab::Scraper {
+                if self.selected_tab == SelectedTab::Scraper && self.edit_selected_field == 0 {
+                    self.scraper_directory.pop();
+                }
+
+                if self.selected_tab == SelectedTab::Edit {
+                    self.edit_selected_file(None);
+                }
+
+                if self.selected_tab == SelectedTab::Settings {
+                    self.settings_status.clear();
                     match self.edit_selected_field {
-                        0 => { self.scraper_directory.pop(); }
-                        _ => {}
+                        SETTINGS_LAST_FIELD => { self.settings_license.pop(); }
+                        field => self.settings_form.pop(field),
                     }
                 }
             }
@@ -195,16 +476,43 @@ impl App {
                             1 => self.download_url.push(c),
                             2 => self.download_output.push(c),
                             3 => self.download_quality.push(c),
+                            DOWNLOAD_LAST_FIELD if c == 'r' => self.retry_download(),
+                            DOWNLOAD_LAST_FIELD if c == 's' => self.skip_download(),
                             _ => {}
                         }
                     }
             
-                    SelectedTab::Scraper => {
-                        match self.edit_selected_field {
-                            0 => self.scraper_directory.push(c),
+                    SelectedTab::Scraper if self.edit_selected_field == 0 => {
+                        self.scraper_directory.push(c);
+                    }
+
+                    SelectedTab::Scraper if self.edit_selected_field == SCRAPER_EXPORT_FIELD && c == 'f' => {
+                        self.scraper_export_format = self.scraper_export_format.next();
+                    }
+
+                    SelectedTab::Edit => self.edit_selected_file(Some(c)),
+
+                    SelectedTab::Play => {
+                        let queue_focused = self.edit_selected_field == PLAY_QUEUE_FIELD;
+                        match c {
+                            's' => self.send_to_player(Command::Stop),
+                            'z' => self.play_queue.toggle_shuffle(),
+                            'r' => self.play_queue.repeat = self.play_queue.repeat.next(),
+                            'a' if !queue_focused => self.queue_tree_node(),
+                            'd' if queue_focused => self.remove_from_play_queue(),
+                            '[' if queue_focused => self.move_in_play_queue(-1),
+                            ']' if queue_focused => self.move_in_play_queue(1),
                             _ => {}
                         }
                     }
+
+                    SelectedTab::Settings => {
+                        self.settings_status.clear();
+                        match self.edit_selected_field {
+                            SETTINGS_LAST_FIELD => self.settings_license.push(c),
+                            field => self.settings_form.push(field, c),
+                        }
+                    }
             
                     _ => {}
                 }
@@ -214,6 +522,762 @@ impl App {
         }
     }
 
+    /// Control playback or open the debug overlay from any tab, returning whether `key` was bound to either.
+    fn handle_global_key(&mut self, key: KeyCode) -> bool {
+        match self.settings.keybindings.action(key) {
+            Some(Action::Pause) => self.send_to_player(Command::TogglePause),
+            Some(Action::VolumeUp) => self.change_volume(VOLUME_STEP as i8),
+            Some(Action::VolumeDown) => self.change_volume(-(VOLUME_STEP as i8)),
+            Some(Action::SeekBack) => self.seek_by(-SEEK_STEP),
+            Some(Action::SeekForward) => self.seek_by(SEEK_STEP),
+            Some(Action::NextTrack) => self.play_next(),
+            Some(Action::PreviousTrack) => self.play_previous(),
+            Some(Action::DebugOverlay) if self.settings.developer_mode => self.debug_overlay = !self.debug_overlay,
+            Some(Action::DebugOverlay | Action::NextTab | Action::PreviousTab | Action::Quit) | None => return false,
+        }
+        true
+    }
+
+    /// Whether the focused field takes text, so its keys can't double as global keys.
+    fn is_typing(&self) -> bool {
+        match self.selected_tab {
+            SelectedTab::Download => (1..=3).contains(&self.edit_selected_field),
+            SelectedTab::Scraper => self.edit_selected_field == 0,
+            // The fields `edit_selected_file` types into
+            SelectedTab::Edit => matches!(self.edit_selected_field, 2..=4 | 6..=8 | 10..=13),
+            SelectedTab::Settings => {
+                self.edit_selected_field == SETTINGS_LAST_FIELD
+                    || settings::FIELDS.get(self.edit_selected_field).is_some_and(|field| !field.is_toggle())
+            }
+            SelectedTab::Home | SelectedTab::Play => false,
+        }
+    }
+
+    /// Queue the URL from the Download tab, every entry of it for a playlist.
+    fn start_download(&mut self) {
+        if self.download_url.trim().is_empty() {
+            self.download_log.warn("Enter a URL to download");
+            return;
+        }
+        if let Err(e) = downloader::audio_quality(&self.download_quality) {
+            self.download_log.error(format!("Cannot download: {e}"));
+            return;
+        }
+
+        let output = match self.download_output.trim() {
+            "" => PathBuf::from("."),
+            output => PathBuf::from(output),
+        };
+        let request = DownloadRequest { url: self.download_url.trim().to_string(), output, quality: self.download_quality.clone() };
+        let Some(link) = spotify::Link::parse(&request.url) else {
+            self.download_log.info(format!("Listing {}", request.url));
+            Extractor::from_setting(&self.settings.extractor).expand(request, self.messages.sender());
+            return;
+        };
+        match spotify::from_setting(&self.settings.spotify_source) {
+            Ok(Some(provider)) => {
+                self.download_log.info(format!("Looking up {} on Spotify", request.url));
+                spotify::expand(provider, link, request, self.messages.sender());
+            }
+            Ok(None) => self.download_log.error("Set a Spotify Source in Settings to download Spotify links"),
+            Err(e) => self.download_log.error(format!("Invalid Spotify Source: {e}")),
+        }
+    }
+
+    /// Queue the entries listed for a URL and start downloading them.
+    fn handle_expanded(&mut self, url: String, items: Result<Vec<QueueItem>, String>) {
+        let items = match items {
+            Ok(items) if !items.is_empty() => items,
+            // The extractor can't download Spotify links itself, only the tracks they resolve to
+            result if spotify::Link::parse(&url).is_some() => {
+                match result {
+                    Err(error) => self.download_log.error(format!("Cannot look up {url}: {error}")),
+                    Ok(_) => self.download_log.warn(format!("{url} has no tracks to download")),
+                }
+                return;
+            }
+            // Not every extractor can list playlists; let it try the URL as a whole
+            result => {
+                if let Err(error) = result {
+                    self.download_log.warn(format!("Cannot list {url}, queueing it as one item: {error}"));
+                }
+                let output = match self.download_output.trim() {
+                    "" => PathBuf::from("."),
+                    output => PathBuf::from(output),
+                };
+                vec![QueueItem { url: url.clone(), output, quality: self.download_quality.clone(), ..QueueItem::default() }]
+            }
+        };
+
+        self.download_log.info(format!("Queued {} items from {url}", items.len()));
+        let session = format!("Download {}", Local::now().format("%Y-%m-%d %H:%M"));
+        self.download_queue.items.extend(items.into_iter().map(|item| QueueItem { session: session.clone(), ..item }));
+        self.save_download_queue();
+        self.pump_downloads();
+    }
+
+    /// Start queued items until as many are downloading as Settings allows.
+    fn pump_downloads(&mut self) {
+        while self.download_queue.active() < self.parallel_downloads() {
+            let Some(index) = self.download_queue.next_queued() else {
+                break;
+            };
+            self.start_item(index);
+        }
+    }
+
+    /// Start downloading a queue item in a terminal of its own, searching for
+    /// the audio of a Spotify track first.
+    fn start_item(&mut self, index: usize) {
+        let extractor = Extractor::from_setting(&self.settings.extractor);
+        let item = &mut self.download_queue.items[index];
+        if let Some(track) = item.track.clone().filter(|_| item.needs_match()) {
+            item.status = ItemStatus::Matching;
+            self.download_log.info(format!("Searching for {}", item.name()));
+            matcher::search(extractor, track, index, self.messages.sender());
+            self.save_download_queue();
+            return;
+        }
+
+        let (columns, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        let (rows, columns) = DownloadTab::console_size(columns, rows);
+        item.console = Console::new(rows, columns);
+        item.progress = DownloadProgress::default();
+        item.files.clear();
+
+        let request = DownloadRequest {
+            url: item.download_url().to_string(),
+            output: item.output.clone(),
+            quality: item.quality.clone(),
+        };
+        match DownloadJob::start(&extractor, &request, index, item.console.clone(), self.messages.sender()) {
+            Ok(job) => {
+                item.status = ItemStatus::Downloading;
+                self.download_log.info(format!("Downloading {} to {}", item.name(), item.output.display()));
+                self.download_jobs.insert(index, job);
+            }
+            Err(e) => {
+                item.status = ItemStatus::Failed;
+                self.download_log.error(format!("Cannot download {}: {e}", item.name()));
+            }
+        }
+        self.save_download_queue();
+    }
+
+    /// Number of items downloaded at once, as configured in the Settings tab.
+    pub fn parallel_downloads(&self) -> usize {
+        self.settings.parallel_downloads.max(1)
+    }
+
+    /// Fold a download job's output into its queue item and the log.
+    fn handle_download_event(&mut self, index: usize, event: DownloadEvent) {
+        let Some(item) = self.download_queue.items.get_mut(index) else {
+            return;
+        };
+        match event {
+            DownloadEvent::Log(level, text) => self.download_log.push(level, format!("{}: {text}", item.name())),
+            DownloadEvent::Progress(progress) => item.progress = progress,
+            // The item may have been skipped while it was searched for
+            DownloadEvent::Matched(_) if item.status != ItemStatus::Matching => {}
+            DownloadEvent::Matched(Ok(audio)) => {
+                self.download_log.info(format!("Matched {} to \"{}\" ({}%)", item.name(), audio.title, audio.score));
+                item.audio_url = audio.url;
+                self.start_item(index);
+            }
+            DownloadEvent::Matched(Err(error)) => {
+                item.status = ItemStatus::Failed;
+                self.download_log.error(format!("Failed {}: {error}", item.name()));
+                self.finish_download_item();
+            }
+            DownloadEvent::Saved(path) => {
+                self.download_log.info(format!("Saved {}", path.display()));
+                item.files.push(path);
+            }
+            DownloadEvent::Tagged { path, result: Ok(scraped) } => {
+                let Scraped { metadata, fingerprint, .. } = *scraped;
+                self.download_log.info(format!("Tagged {} ({}%, {})", path.display(), metadata.percentage, metadata.status));
+                if let Some(fingerprint) = fingerprint {
+                    self.library.set_fingerprint(&metadata.path, fingerprint);
+                }
+                self.library.update(metadata);
+                self.save_library();
+            }
+            DownloadEvent::Tagged { path, result: Err(error) } => {
+                self.download_log.error(format!("Cannot tag {}: {error}", path.display()));
+            }
+            DownloadEvent::Exited(result) => {
+                // A job dropped on the way out of the app is no longer tracked
+                let Some(job) = self.download_jobs.remove(&index) else {
+                    return;
+                };
+                match result {
+                    Ok(()) => {
+                        item.status = ItemStatus::Done;
+                        item.progress.percent = 100.0;
+                        self.download_log.info(format!("Downloaded {}", item.name()));
+                        self.tag_download(index);
+                    }
+                    Err(_) if job.interrupted => {
+                        item.status = ItemStatus::Skipped;
+                        self.download_log.warn(format!("Skipped {}", item.name()));
+                    }
+                    Err(error) => {
+                        item.status = ItemStatus::Failed;
+                        self.download_log.error(format!("Failed {}: {error}", item.name()));
+                    }
+                }
+                self.finish_download_item();
+            }
+        }
+    }
+
+    /// Tag the files a finished item produced and add them to the library.
+    /// Downloads come with their source's tags, so they are scraped offline
+    /// rather than spend Deep Search credits.
+    fn tag_download(&mut self, index: usize) {
+        let inference = Inference::new(&self.settings.filename_patterns).unwrap_or_default();
+        let item = &self.download_queue.items[index];
+        if item.files.is_empty() {
+            return;
+        }
+        // The tagger saves the scraped tags itself
+        let scraper = Scraper {
+            inference: inference.within(&item.output),
+            provider: None,
+            allowance: Allowance::default(),
+            save_to_file: None,
+        };
+        let request = TagRequest {
+            files: item.files.clone(),
+            metadata: item.metadata.clone().unwrap_or_default(),
+            cover_url: item.cover_url.clone(),
+            session_name: item.session.clone(),
+            options: tags::WriteOptions { id3v1: self.settings.write_id3v1 },
+        };
+        tagger::start(request, scraper, index, self.messages.sender());
+    }
+
+    /// Move on once an item is done with, summing the queue up when nothing is left to do.
+    fn finish_download_item(&mut self) {
+        self.save_download_queue();
+        self.pump_downloads();
+        if self.download_queue.active() == 0 {
+            let queue = &self.download_queue;
+            self.download_log.info(format!(
+                "Download queue finished: {} done, {} failed, {} skipped",
+                queue.count(ItemStatus::Done),
+                queue.count(ItemStatus::Failed),
+                queue.count(ItemStatus::Skipped),
+            ));
+        }
+    }
+
+    /// Save the download queue after an item changed state, logging any failure.
+    fn save_download_queue(&mut self) {
+        if let Err(e) = self.download_queue.save() {
+            self.download_log.error(format!("Failed to save the download queue: {e}"));
+        }
+    }
+
+    /// Move the queue selection by `offset` rows.
+    fn select_download(&mut self, offset: isize) {
+        let last = self.download_queue.items.len().saturating_sub(1);
+        self.download_selected = self.download_selected.saturating_add_signed(offset).min(last);
+    }
+
+    /// Cancel the selected item's download as if Ctrl-C was typed into its terminal.
+    fn interrupt_download(&mut self) {
+        let Some(job) = self.download_jobs.get_mut(&self.download_selected) else {
+            return;
+        };
+        if let Err(e) = job.interrupt() {
+            self.download_log.error(format!("Cannot cancel the download: {e}"));
+        }
+    }
+
+    /// Skip the selected item, cancelling it if it is downloading.
+    fn skip_download(&mut self) {
+        let Some(item) = self.download_queue.items.get_mut(self.download_selected) else {
+            return;
+        };
+        match item.status {
+            ItemStatus::Queued => {
+                item.status = ItemStatus::Skipped;
+                self.download_log.warn(format!("Skipped {}", item.name()));
+                self.save_download_queue();
+            }
+            // The search finishes in the background and is ignored
+            ItemStatus::Matching => {
+                item.status = ItemStatus::Skipped;
+                self.download_log.warn(format!("Skipped {}", item.name()));
+                self.finish_download_item();
+            }
+            ItemStatus::Downloading => self.interrupt_download(),
+            _ => {}
+        }
+    }
+
+    /// Queue the selected item again after it failed or was skipped.
+    fn retry_download(&mut self) {
+        let Some(item) = self.download_queue.items.get_mut(self.download_selected) else {
+            return;
+        };
+        if matches!(item.status, ItemStatus::Failed | ItemStatus::Skipped) {
+            item.status = ItemStatus::Queued;
+            item.progress = DownloadProgress::default();
+            self.download_log.info(format!("Retrying {}", item.name()));
+            self.save_download_queue();
+            self.pump_downloads();
+        }
+    }
+
+    /// The console of the selected queue item, if there is one.
+    fn selected_console(&self) -> Option<&Console> {
+        self.download_queue.items.get(self.download_selected).map(|item| &item.console)
+    }
+
+    /// Fit the download consoles to a resized terminal of `columns` x `rows`.
+    fn resize_console(&mut self, columns: u16, rows: u16) {
+        let (rows, columns) = DownloadTab::console_size(columns, rows);
+        for (index, job) in &self.download_jobs {
+            self.download_queue.items[*index].console.resize(rows, columns);
+            if let Err(e) = job.resize(rows, columns) {
+                self.download_log.debug(format!("Cannot resize the download terminal: {e}"));
+            }
+        }
+    }
+
+    /// Move the selection of the focused Play tab pane by `offset` rows.
+    fn select_in_play_tab(&mut self, offset: isize) {
+        if self.edit_selected_field == PLAY_QUEUE_FIELD {
+            let last = self.play_queue.tracks.len().saturating_sub(1);
+            self.play_selected = self.play_selected.saturating_add_signed(offset).min(last);
+        } else {
+            self.play_tree.select(offset);
+        }
+    }
+
+    /// Open or close the selected artist or album, or play the selected track.
+    fn open_tree_node(&mut self) {
+        match self.play_tree.selected_node() {
+            Some(Node::Track(path)) => {
+                let index = self.play_queue.tracks.len();
+                self.play_queue.add([path]);
+                self.play_queue_track(index);
+            }
+            Some(_) => self.play_tree.toggle_expanded(),
+            None => {}
+        }
+    }
+
+    /// Add every track under the selected row of the library tree to the play queue.
+    fn queue_tree_node(&mut self) {
+        if let Some(node) = self.play_tree.selected_node() {
+            self.play_queue.add(self.play_tree.tracks(&node));
+        }
+    }
+
+    fn remove_from_play_queue(&mut self) {
+        self.play_queue.remove(self.play_selected);
+        self.play_selected = self.play_selected.min(self.play_queue.tracks.len().saturating_sub(1));
+    }
+
+    fn move_in_play_queue(&mut self, offset: isize) {
+        self.play_selected = self.play_queue.move_track(self.play_selected, offset);
+    }
+
+    /// Play the track at `index` of the play queue.
+    fn play_queue_track(&mut self, index: usize) {
+        if let Some(path) = self.play_queue.select(index) {
+            let path = PathBuf::from(path);
+            self.play_selected = index;
+            self.send_to_player(Command::Play(path));
+        }
+    }
+
+    fn play_next(&mut self) {
+        if let Some(index) = self.play_queue.next() {
+            self.play_queue_track(index);
+        }
+    }
+
+    /// Play the previous track, or the current one from the start when it's the first.
+    fn play_previous(&mut self) {
+        match self.play_queue.previous() {
+            Some(index) => self.play_queue_track(index),
+            None if self.playback.path.is_some() => self.send_to_player(Command::Seek(Duration::ZERO)),
+            None => {}
+        }
+    }
+
+    /// Follow the player, moving down the play queue as tracks end.
+    fn handle_playback_event(&mut self, event: PlaybackEvent) {
+        let finished = matches!(event, PlaybackEvent::Finished);
+        self.playback.apply(event);
+        if finished {
+            if let Some(index) = self.play_queue.after_finished() {
+                self.play_queue_track(index);
+            }
+        }
+    }
+
+    /// Seek `seconds` forwards or, when negative, backwards from the current position.
+    fn seek_by(&mut self, seconds: i64) {
+        if self.playback.path.is_none() {
+            return;
+        }
+        let step = Duration::from_secs(seconds.unsigned_abs());
+        let mut position = match seconds {
+            0.. => self.playback.position + step,
+            _ => self.playback.position.saturating_sub(step),
+        };
+        if let Some(duration) = self.playback.duration {
+            position = position.min(duration);
+        }
+        self.send_to_player(Command::Seek(position));
+    }
+
+    fn change_volume(&mut self, change: i8) {
+        self.playback.volume = self.playback.volume.saturating_add_signed(change).min(100);
+        self.send_to_player(Command::Volume(self.playback.volume as f32 / 100.0));
+    }
+
+    fn send_to_player(&mut self, command: Command) {
+        match &self.player {
+            Some(player) => player.send(command),
+            None => self.playback.error = Some("Playback is not available".to_string()),
+        }
+    }
+
+    /// Scan the scraper directory and scrape every audio file in it in the background.
+    fn start_scrape(&mut self) {
+        if self.scraper_pool.is_some() {
+            self.scraper_log.warn("A scrape is already running");
+            return;
+        }
+
+        let root = PathBuf::from(self.scraper_directory.trim());
+        let options = ScanOptions { follow_symlinks: self.settings.follow_symlinks };
+        let threads = self.scraper_threads();
+        let scraper = self.scraper();
+        if scraper.provider.is_some() && self.credits.balance() == 0 {
+            self.scraper_log.error(
+                "No Deep Search credits left: redeem a license key in Settings, or clear the Metadata Provider to scrape offline",
+            );
+            return;
+        }
+
+        self.scraper_log.info(format!("Scanning {}", root.display()));
+        self.scraper_log.debug(format!("Scraping with {threads} threads"));
+        if let Some(provider) = &scraper.provider {
+            self.scraper_log.debug(format!("Looking files up in {}", provider.name()));
+        }
+        self.scraper_progress = ScrapeProgress::start();
+        self.scraper_pool = Some(ScrapePool::start(root, options, scraper, threads, self.messages.sender()));
+    }
+
+    /// Count in the background how many Deep Search credits scraping the
+    /// scraper directory would cost.
+    fn start_estimate(&mut self) {
+        if self.scraper_pool.is_some() {
+            self.scraper_log.warn("A scrape is already running");
+            return;
+        }
+
+        let root = PathBuf::from(self.scraper_directory.trim());
+        let options = ScanOptions { follow_symlinks: self.settings.follow_symlinks };
+        self.scraper_log.info(format!("Estimating the cost of scraping {}", root.display()));
+        let scraper = self.scraper();
+        self.scraper_pool = Some(ScrapePool::estimate(root, options, scraper, self.messages.sender()));
+    }
+
+    /// A scraper configured from the Settings tab, logging any setting it has to ignore.
+    fn scraper(&mut self) -> Scraper {
+        let inference = Inference::new(&self.settings.filename_patterns).unwrap_or_else(|e| {
+            self.scraper_log.warn(format!("Ignoring the filename patterns from Settings: {e}"));
+            Inference::default()
+        });
+        let provider = providers::from_setting(&self.settings.provider, &self.settings.acoustid_key).unwrap_or_else(|e| {
+            self.scraper_log.warn(format!("Scraping without a metadata provider: {e}"));
+            None
+        });
+        let save_to_file = self.settings.save_scraped_tags.then_some(tags::WriteOptions { id3v1: self.settings.write_id3v1 });
+        Scraper { inference, provider, allowance: self.credits.allowance(), save_to_file }
+    }
+
+    /// The Scraper tab's last field, which is Export Index only in developer mode.
+    fn scraper_last_field(&self) -> usize {
+        if self.settings.developer_mode { SCRAPER_EXPORT_FIELD } else { SCRAPER_EXPORT_FIELD - 1 }
+    }
+
+    /// Export the library index with its fingerprints and lookups, for developers.
+    fn export_index(&mut self) {
+        if !self.settings.developer_mode {
+            return;
+        }
+        let format = self.scraper_export_format;
+        let path = match export::default_path(format) {
+            Ok(path) => path,
+            Err(e) => {
+                self.scraper_log.error(format!("Cannot export the index: {e}"));
+                return;
+            }
+        };
+        match export::export(&self.library, &path, format) {
+            Ok(()) => self.scraper_log.info(format!("Exported {} entries to {}", self.library.entries.len(), path.display())),
+            Err(e) => self.scraper_log.error(format!("Failed to export the index as {format}: {e}")),
+        }
+    }
+
+    /// Add the Deep Search credits of the license key file named in Settings.
+    fn redeem_license(&mut self) {
+        let path = Path::new(self.settings_license.trim());
+        self.settings_status = match self.credits.redeem(path).and_then(|added| self.credits.save().map(|()| added)) {
+            Ok(added) => format!("Added {added} Deep Search credits, {} left", self.credits.balance()),
+            Err(e) => format!("Cannot redeem {}: {e}", path.display()),
+        };
+    }
+
+    /// Save the Settings tab's form and put it into effect, unless a field needs fixing first.
+    fn save_settings(&mut self) {
+        self.settings_form.check_files();
+        if let Some((field, error)) = self.settings_form.first_error() {
+            self.settings_status = format!("Cannot save, {}: {error}", field.label());
+            return;
+        }
+        let settings = self.settings_form.draft.clone();
+        self.settings_status = match (settings.save(), settings.path()) {
+            (Ok(()), Some(path)) => format!("Saved to {}", path.display()),
+            (Ok(()), None) => "Saved for this session".to_string(),
+            (Err(e), _) => format!("Failed to save the settings: {e}"),
+        };
+        self.apply_settings(settings);
+    }
+
+    /// Go by `settings` from now on.
+    fn apply_settings(&mut self, settings: Settings) {
+        // Download fields left at their defaults follow the new ones
+        if self.download_output == self.settings.download_directory {
+            self.download_output = settings.download_directory.clone();
+        }
+        if self.download_quality == self.settings.download_quality {
+            self.download_quality = settings.download_quality.clone();
+        }
+        let filter = if settings.developer_mode { Level::Debug } else { Level::Info };
+        self.scraper_log.filter = filter;
+        self.download_log.filter = filter;
+        self.debug_overlay &= settings.developer_mode;
+        self.settings_form = SettingsForm::new(&settings);
+        self.settings = settings;
+    }
+
+    /// Number of scraper workers configured in the Settings tab.
+    pub fn scraper_threads(&self) -> usize {
+        match self.settings.scraper_threads {
+            0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        }
+    }
+
+    /// Apply everything background jobs reported since the last tick.
+    fn handle_messages(&mut self) {
+        for message in self.messages.drain() {
+            match message {
+                Message::Scrape(event) => self.handle_scrape_event(event),
+                Message::Download { item, event } => self.handle_download_event(item, event),
+                Message::Expanded { url, items } => self.handle_expanded(url, items),
+                Message::Playback(event) => self.handle_playback_event(event),
+                Message::Refreshed(entries) => {
+                    for on_disk in entries {
+                        self.library.merge_from_disk(on_disk);
+                    }
+                    self.save_library();
+                }
+            }
+        }
+    }
+
+    /// Fold a scrape job's progress into the library, the gauge and the log.
+    fn handle_scrape_event(&mut self, event: ScrapeEvent) {
+        let progress = &mut self.scraper_progress;
+        match event {
+            ScrapeEvent::Aborted(error) => {
+                self.scraper_log.error(error);
+                self.scraper_pool = None;
+            }
+            ScrapeEvent::Skipped { path, error } => {
+                self.scraper_log.warn(format!("Skipped {}: {error}", path.display()));
+            }
+            ScrapeEvent::Found(files) => {
+                progress.found = files.len();
+                self.scraper_log.info(format!("Found {} audio files", files.len()));
+                for file in files {
+                    self.library.add_pending(file);
+                }
+                self.save_library();
+            }
+            ScrapeEvent::Note { file, level, message } => self.scraper_log.push(level, format!("{file}: {message}")),
+            ScrapeEvent::Scraped(scraped) => {
+                let Scraped { metadata, fingerprint, deep_search, lookup } = *scraped;
+                progress.processed += 1;
+                self.scraper_log.info(format!("Scraped {}", metadata.file_name));
+                if deep_search {
+                    self.credits.spend();
+                    if let Err(e) = self.credits.save() {
+                        self.scraper_log.error(format!("Failed to save the Deep Search credits: {e}"));
+                    }
+                }
+                if let Some(fingerprint) = fingerprint {
+                    self.library.set_fingerprint(&metadata.path, fingerprint);
+                }
+                if let Some(lookup) = lookup {
+                    self.library.set_lookup(&metadata.path, lookup);
+                }
+                self.library.update(metadata);
+            }
+            ScrapeEvent::Failed { mut entry, error } => {
+                progress.processed += 1;
+                progress.failed += 1;
+                self.scraper_log.error(format!("Failed {}: {error}", entry.file_name));
+                entry.status = Status::Failed.to_string();
+                self.library.update(*entry);
+            }
+            ScrapeEvent::Finished => {
+                let ScrapeProgress { processed, failed, .. } = *progress;
+                self.scraper_log.info(format!("Scrape finished: {} scraped, {failed} failed", processed - failed));
+                for group in self.library.duplicates() {
+                    self.scraper_log.warn(format!("Possible duplicates: {}", group.join(", ")));
+                }
+                self.scraper_pool = None;
+                self.save_library();
+            }
+            ScrapeEvent::Estimated { files, lookups } => {
+                let balance = self.credits.balance();
+                self.scraper_log.info(format!(
+                    "Dry run: {lookups} of {files} files would need a Deep Search, {balance} credits left"
+                ));
+                if lookups > balance as usize {
+                    self.scraper_log.warn(format!(
+                        "{} files would be scraped without a lookup; redeem a license key in Settings for more credits",
+                        lookups - balance as usize
+                    ));
+                }
+                self.scraper_pool = None;
+            }
+        }
+    }
+
+    /// Save the library index after a scrape changed it, logging any failure.
+    fn save_library(&mut self) {
+        if let Err(e) = self.library.save() {
+            self.scraper_log.error(format!("Failed to save the library index: {e}"));
+        }
+        self.reselect_file();
+        self.play_tree.rebuild(&self.library.entries);
+    }
+
+    /// Sort the library by the next order, keeping the file being edited selected.
+    fn cycle_sort_order(&mut self) {
+        self.edit_sort = self.edit_sort.next();
+        self.library.sort(self.edit_sort);
+        self.reselect_file();
+    }
+
+    /// Find the file being edited again after the library changed. Its form is
+    /// only reloaded from the library when it has no unsaved edits.
+    fn reselect_file(&mut self) {
+        let row = self
+            .library
+            .entries
+            .iter()
+            .position(|entry| entry.path == self.selected_file.path)
+            .unwrap_or(self.edit_selected_row);
+        if self.edit_changed {
+            self.edit_selected_row = row;
+        } else {
+            self.select_file(row);
+        }
+    }
+
+    /// Load the metadata row `row` into the edit form, clamped to the last row.
+    pub fn select_file(&mut self, row: usize) {
+        if let Some(last) = self.library.entries.len().checked_sub(1) {
+            self.edit_selected_row = row.min(last);
+            self.selected_file = self.library.entries[self.edit_selected_row].clone();
+            self.edit_changed = false;
+            self.edit_cover = match tags::read_cover(Path::new(&self.selected_file.path)) {
+                Ok(Some(cover)) => format!("{}, {} KB", cover.mime, cover.data.len().div_ceil(1024)),
+                Ok(None) => "None".to_string(),
+                Err(e) => format!("Unreadable: {e}"),
+            };
+        }
+    }
+
+    /// Type `c` into (or with `None`, delete the last character of) the
+    /// selected field of the edit form. Only fields stored in tags are editable.
+    fn edit_selected_file(&mut self, c: Option<char>) {
+        let file = &mut self.selected_file;
+        let text = match self.edit_selected_field {
+            2 => &mut file.title,
+            3 => &mut file.artist,
+            4 => &mut file.album,
+            7 => &mut file.genre,
+            10 => &mut file.album_artist,
+            11 => &mut file.composer,
+            13 => &mut file.comments,
+            _ => {
+                let number = match self.edit_selected_field {
+                    6 => &mut file.year,
+                    8 => &mut file.track,
+                    12 => &mut file.disc_no,
+                    _ => return,
+                };
+                *number = match c {
+                    Some(c) => match c.to_digit(10) {
+                        Some(digit) => number.saturating_mul(10).saturating_add(digit as u16),
+                        None => return,
+                    },
+                    None => *number / 10,
+                };
+                self.edit_changed = true;
+                return;
+            }
+        };
+
+        match c {
+            Some(c) => text.push(c),
+            None => {
+                text.pop();
+            }
+        }
+        self.edit_changed = true;
+    }
+
+    /// Write the edit form back to the file's tags and the library index.
+    fn save_selected_file(&mut self) {
+        if self.library.entries.is_empty() {
+            return;
+        }
+
+        let path = Path::new(&self.selected_file.path);
+        let options = tags::WriteOptions { id3v1: self.settings.write_id3v1 };
+        self.edit_status = match tags::write_metadata(path, &self.selected_file, options) {
+            Ok(()) => {
+                // Rated on the saved tags alone, as the user has now vouched for them
+                let score = score::rate(&score::Evidence { tags: &self.selected_file, sources: &[], fingerprint: None });
+                self.selected_file.percentage = score.percentage;
+                self.selected_file.status = score.status.to_string();
+                self.library.entries[self.edit_selected_row] = self.selected_file.clone();
+                self.edit_changed = false;
+                match self.library.save() {
+                    Ok(()) => format!("Saved {}", self.selected_file.file_name),
+                    Err(e) => format!("Saved {}, but failed to update the library index: {e}", self.selected_file.file_name),
+                }
+            }
+            Err(e) => format!("Failed to save {}: {e}", self.selected_file.file_name),
+        };
+    }
+
     pub fn next_tab(&mut self) {
         self.selected_tab = self.selected_tab.next();
     }
@@ -243,10 +1307,10 @@ impl SelectedTab {
     }
 
     /// Return tab's name as a styled `Line`
-    fn title(self) -> Line<'static> {
+    fn title(self, theme: Theme) -> Line<'static> {
         format!("  {self}  ")
             .fg(tailwind::SLATE.c200)
-            .bg(self.palette().c900)
+            .bg(theme.palette(self).c900)
             .into()
     }
 }
@@ -254,8 +1318,8 @@ impl SelectedTab {
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         use Constraint::{Length, Min};
-        let vertical = Layout::vertical([Length(1), Min(0), Length(1)]);
-        let [header_area, inner_area, footer_area] = vertical.areas(area);
+        let vertical = Layout::vertical([Length(1), Min(0), Length(1), Length(1)]);
+        let [header_area, inner_area, status_area, footer_area] = vertical.areas(area);
 
         let horizontal = Layout::horizontal([Min(0), Length(36)]);
         let [tabs_area, title_area] = horizontal.areas(header_area);
@@ -266,14 +1330,20 @@ impl Widget for &App {
         let mut renderer = self.selected_tab.renderer();
         renderer.render(inner_area, buf, self);
         
-        render_footer(footer_area, buf);
+        self.render_now_playing(status_area, buf);
+        self.render_footer(footer_area, buf);
+
+        if self.debug_overlay && self.settings.developer_mode {
+            self.render_debug_overlay(inner_area, buf);
+        }
     }
 }
 
 impl App {
     fn render_tabs(&self, area: Rect, buf: &mut Buffer) {
-        let titles = SelectedTab::iter().map(SelectedTab::title);
-        let highlight_style = (Color::default(), self.selected_tab.palette().c700);
+        let theme = self.settings.theme;
+        let titles = SelectedTab::iter().map(|tab| tab.title(theme));
+        let highlight_style = (Color::default(), theme.palette(self.selected_tab).c700);
         let selected_tab_index = self.selected_tab as usize;
         Tabs::new(titles)
             .highlight_style(highlight_style)
@@ -282,17 +1352,128 @@ impl App {
             .divider(" ")
             .render(area, buf);
     }
+
+    /// The state of the channel, the queues and the background jobs, for developers.
+    fn render_debug_overlay(&self, area: Rect, buf: &mut Buffer) {
+        let stats = &self.messages.stats;
+        let by_kind: Vec<String> = stats.by_kind.iter().map(|(kind, count)| format!("{kind} {count}")).collect();
+        let queue = &self.download_queue;
+        let statuses: Vec<String> = [
+            ItemStatus::Queued,
+            ItemStatus::Matching,
+            ItemStatus::Downloading,
+            ItemStatus::Done,
+            ItemStatus::Failed,
+            ItemStatus::Skipped,
+        ]
+        .into_iter()
+        .map(|status| format!("{status} {}", queue.count(status)))
+        .collect();
+        let jobs: Vec<String> = self.download_jobs.keys().map(usize::to_string).collect();
+        let playback = &self.playback;
+        let play_queue = &self.play_queue;
+
+        let lines = vec![
+            Line::from("Message channel".bold()),
+            Line::from(format!(
+                "  {} drained | {} last tick | {} busiest tick",
+                stats.total, stats.last_tick, stats.busiest_tick
+            )),
+            Line::from(format!("  By kind: {}", if by_kind.is_empty() { "none".to_string() } else { by_kind.join(" | ") })),
+            Line::from("Downloads".bold()),
+            Line::from(format!("  {} items: {}", queue.items.len(), statuses.join(" | "))),
+            Line::from(format!(
+                "  Jobs running for items [{}] | {} active of {} allowed",
+                jobs.join(", "),
+                queue.active(),
+                self.parallel_downloads()
+            )),
+            Line::from("Scraper".bold()),
+            Line::from(format!(
+                "  Pool {} | {}/{} processed | {} failed | {} threads | {} credits left",
+                if self.scraper_pool.is_some() { "running" } else { "idle" },
+                self.scraper_progress.processed,
+                self.scraper_progress.found,
+                self.scraper_progress.failed,
+                self.scraper_threads(),
+                self.credits.balance()
+            )),
+            Line::from(format!(
+                "  Library: {} entries | {} fingerprinted | {} looked up",
+                self.library.entries.len(),
+                self.library.fingerprinted(),
+                self.library.looked_up()
+            )),
+            Line::from("Player".bold()),
+            Line::from(format!(
+                "  Thread {} | {:?} at {:.1}s of {:.1}s | paused {} | volume {}%",
+                if self.player.as_ref().is_some_and(Player::is_running) { "running" } else { "stopped" },
+                playback.path,
+                playback.position.as_secs_f64(),
+                playback.duration.unwrap_or_default().as_secs_f64(),
+                playback.paused,
+                playback.volume
+            )),
+            Line::from(format!(
+                "  Play queue: {} tracks | current {:?} | shuffle {} | repeat {}",
+                play_queue.tracks.len(),
+                play_queue.current,
+                play_queue.shuffle,
+                play_queue.repeat
+            )),
+        ];
+
+        let key = self.settings.keybindings.key(Action::DebugOverlay);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let width = area.width.saturating_sub(4).min(110);
+        let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .title("Debug")
+                    .title_bottom(format!("{key} to close"))
+                    .border_style(tailwind::AMBER.c500)
+                    .padding(Padding::horizontal(1)),
+            )
+            .render(popup, buf);
+    }
+
+    fn render_footer(&self, area: Rect, buf: &mut Buffer) {
+        let quit = self.settings.keybindings.key(Action::Quit);
+        Line::raw(format!("◄ ► to change tab | Enter to edit/view | ▲ ▼ to scroll | Esc to go back | {quit} to quit"))
+            .centered()
+            .render(area, buf);
+    }
+
+    /// One line on what's playing, shown under every tab.
+    fn render_now_playing(&self, area: Rect, buf: &mut Buffer) {
+        let playback = &self.playback;
+        let line = match (&playback.path, &playback.error) {
+            (_, Some(error)) => Line::raw(error.as_str()).fg(tailwind::RED.c400),
+            (Some(path), None) => {
+                let path = path.to_string_lossy();
+                let name = play::track_name(play::library_entry(self, &path), &path);
+                let state = if playback.paused { "⏸" } else { "▶" };
+                let duration = playback.duration.unwrap_or_default();
+                Line::raw(format!(
+                    "{state} {name} | {} / {} | Volume {}%",
+                    play::clock(playback.position),
+                    play::clock(duration),
+                    playback.volume,
+                ))
+                .fg(self.settings.theme.palette(SelectedTab::Play).c400)
+            }
+            (None, None) => Line::raw("■ Nothing playing").fg(tailwind::SLATE.c500),
+        };
+        line.centered().render(area, buf);
+    }
 }
 
 fn render_title(area: Rect, buf: &mut Buffer) {
     "Automated Mp3 Tag Editor CLI - v1.0".bold().render(area, buf);
 }
 
-fn render_footer(area: Rect, buf: &mut Buffer) {
-    Line::raw("◄ ► to change tab | Enter to edit/view | ▲ ▼ to scroll | Esc to go back | q to quit")
-        .centered()
-        .render(area, buf);
-}
 
 impl Widget for SelectedTab {
     fn render(self, area: Rect, buf: &mut Buffer) {
@@ -303,11 +1484,11 @@ impl Widget for SelectedTab {
 
 impl SelectedTab {
     /// A block surrounding the tab's content
-    fn block(self) -> Block<'static> {
+    fn block(self, theme: Theme) -> Block<'static> {
         Block::bordered()
             .border_set(symbols::border::PROPORTIONAL_TALL)
             .padding(Padding::horizontal(1))
-            .border_style(self.palette().c700)
+            .border_style(theme.palette(self).c700)
     }
 
     const fn palette(self) -> tailwind::Palette {
@@ -0,0 +1,83 @@
+// src/messages.rs
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::downloader::queue::QueueItem;
+use crate::downloader::DownloadEvent;
+use crate::player::PlaybackEvent;
+use crate::scraper::pool::ScrapeEvent;
+use crate::Metadata;
+
+/// Progress reported by a background job to the UI thread.
+pub enum Message {
+    /// Progress of the scrape started from the Scraper tab.
+    Scrape(ScrapeEvent),
+    /// Progress of a download, by the index of its item in the download queue.
+    Download { item: usize, event: DownloadEvent },
+    /// The entries of a URL given to the Download tab, ready to be queued.
+    Expanded { url: String, items: Result<Vec<QueueItem>, String> },
+    /// What the player is doing.
+    Playback(PlaybackEvent),
+    /// Tags re-read from the indexed files at startup.
+    Refreshed(Vec<Metadata>),
+}
+
+impl Message {
+    /// The variant's name, for the debug overlay.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::Scrape(_) => "Scrape",
+            Message::Download { .. } => "Download",
+            Message::Expanded { .. } => "Expanded",
+            Message::Playback(_) => "Playback",
+            Message::Refreshed(_) => "Refreshed",
+        }
+    }
+}
+
+/// Counts of the messages drained so far, for the debug overlay.
+#[derive(Default)]
+pub struct ChannelStats {
+    pub total: usize,
+    /// Messages drained by the last tick.
+    pub last_tick: usize,
+    /// Most messages drained by a single tick.
+    pub busiest_tick: usize,
+    pub by_kind: BTreeMap<&'static str, usize>,
+}
+
+/// The channel background jobs report through. Every job gets its own clone
+/// of the sender; the UI drains the receiver once per tick of the event loop.
+pub struct Messages {
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    pub stats: ChannelStats,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Messages { sender, receiver, stats: ChannelStats::default() }
+    }
+}
+
+impl Messages {
+    /// A sender to hand to a new background job.
+    pub fn sender(&self) -> Sender<Message> {
+        self.sender.clone()
+    }
+
+    /// Every message that arrived since the last call, without blocking.
+    pub fn drain(&mut self) -> Vec<Message> {
+        let messages: Vec<Message> = self.receiver.try_iter().collect();
+        let stats = &mut self.stats;
+        stats.total += messages.len();
+        stats.last_tick = messages.len();
+        stats.busiest_tick = stats.busiest_tick.max(messages.len());
+        for message in &messages {
+            *stats.by_kind.entry(message.kind()).or_default() += 1;
+        }
+        messages
+    }
+}
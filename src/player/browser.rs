@@ -0,0 +1,127 @@
+// src/player/browser.rs
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Metadata;
+
+/// Shown for entries without an artist or album tag.
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+const UNKNOWN_ALBUM: &str = "Unknown Album";
+
+/// What a row of the library tree stands for.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Node {
+    Artist(String),
+    Album(String, String),
+    /// A track, by path.
+    Track(String),
+}
+
+pub struct Row {
+    pub node: Node,
+    pub depth: u16,
+    pub label: String,
+    pub expanded: bool,
+}
+
+/// An album's tracks, in disc and track order.
+type Albums = BTreeMap<String, Vec<Metadata>>;
+
+/// The library index as an artist → album → track tree, for browsing in the Play tab.
+#[derive(Default)]
+pub struct LibraryTree {
+    artists: BTreeMap<String, Albums>,
+    /// Artists and albums showing what's inside them.
+    expanded: BTreeSet<Node>,
+    /// Index of the selected row among the visible ones.
+    pub selected: usize,
+}
+
+impl LibraryTree {
+    /// Rebuild the tree from the library, keeping what was expanded.
+    pub fn rebuild(&mut self, entries: &[Metadata]) {
+        self.artists.clear();
+        for entry in entries {
+            let artist = if entry.album_artist.is_empty() { &entry.artist } else { &entry.album_artist };
+            let artist = if artist.is_empty() { UNKNOWN_ARTIST } else { artist };
+            let album = if entry.album.is_empty() { UNKNOWN_ALBUM } else { &entry.album };
+            let albums = self.artists.entry(artist.to_string()).or_default();
+            albums.entry(album.to_string()).or_default().push(entry.clone());
+        }
+        for tracks in self.artists.values_mut().flat_map(|albums| albums.values_mut()) {
+            tracks.sort_by(|a, b| (a.disc_no, a.track, &a.title).cmp(&(b.disc_no, b.track, &b.title)));
+        }
+        self.selected = self.selected.min(self.rows().len().saturating_sub(1));
+    }
+
+    /// The rows on show: every artist, and the contents of whatever is expanded.
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (artist, albums) in &self.artists {
+            let node = Node::Artist(artist.clone());
+            let expanded = self.expanded.contains(&node);
+            rows.push(Row { node, depth: 0, label: artist.clone(), expanded });
+            if !expanded {
+                continue;
+            }
+            for (album, tracks) in albums {
+                let node = Node::Album(artist.clone(), album.clone());
+                let expanded = self.expanded.contains(&node);
+                rows.push(Row { node, depth: 1, label: album.clone(), expanded });
+                if !expanded {
+                    continue;
+                }
+                for track in tracks {
+                    let title = if track.title.is_empty() { &track.file_name } else { &track.title };
+                    let label = match track.track {
+                        0 => title.clone(),
+                        number => format!("{number:02}. {title}"),
+                    };
+                    rows.push(Row { node: Node::Track(track.path.clone()), depth: 2, label, expanded: false });
+                }
+            }
+        }
+        rows
+    }
+
+    pub fn select(&mut self, offset: isize) {
+        let last = self.rows().len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    pub fn selected_node(&self) -> Option<Node> {
+        self.rows().into_iter().nth(self.selected).map(|row| row.node)
+    }
+
+    /// Show or hide what's inside the selected artist or album; tracks have nothing inside.
+    pub fn set_expanded(&mut self, expanded: bool) {
+        let Some(node) = self.selected_node() else {
+            return;
+        };
+        match node {
+            Node::Track(_) => {}
+            node if expanded => {
+                self.expanded.insert(node);
+            }
+            node => {
+                self.expanded.remove(&node);
+            }
+        }
+    }
+
+    pub fn toggle_expanded(&mut self) {
+        if let Some(node) = self.selected_node() {
+            self.set_expanded(!self.expanded.contains(&node));
+        }
+    }
+
+    /// Paths of every track under a node, in order.
+    pub fn tracks(&self, node: &Node) -> Vec<String> {
+        let paths = |tracks: &Vec<Metadata>| tracks.iter().map(|track| track.path.clone()).collect::<Vec<_>>();
+        match node {
+            Node::Artist(artist) => self.artists.get(artist).into_iter().flat_map(|albums| albums.values()).flat_map(paths).collect(),
+            Node::Album(artist, album) => self.artists.get(artist).and_then(|albums| albums.get(album)).map(paths).unwrap_or_default(),
+            Node::Track(path) => vec![path.clone()],
+        }
+    }
+}
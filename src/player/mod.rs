@@ -0,0 +1,276 @@
+// src/player/mod.rs
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::decode::Decoder;
+use crate::messages::Message;
+use sink::Sink;
+
+pub mod browser;
+pub mod queue;
+pub mod sink;
+
+/// How often the position is reported while playing.
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What the UI asks the playback thread to do.
+pub enum Command {
+    /// Play a file from the start, replacing whatever is playing.
+    Play(PathBuf),
+    /// Pause, or resume when paused.
+    TogglePause,
+    /// Move to a position in the current track.
+    Seek(Duration),
+    Stop,
+    /// Scale the samples by this factor, 0.0-1.0.
+    Volume(f32),
+}
+
+/// What the playback thread reports, as `Message::Playback`.
+pub enum PlaybackEvent {
+    Started { path: PathBuf, duration: Option<Duration> },
+    /// How far into the current track playback has got.
+    Position(Duration),
+    Paused(bool),
+    /// The track played to its end.
+    Finished,
+    /// Playback was stopped before the end of the track.
+    Stopped,
+    Error(String),
+}
+
+/// What the UI knows of the player, kept up to date from its events.
+pub struct PlaybackState {
+    /// The file playing or paused; `None` when stopped.
+    pub path: Option<PathBuf>,
+    pub duration: Option<Duration>,
+    pub position: Duration,
+    pub paused: bool,
+    /// 0-100.
+    pub volume: u8,
+    /// The last thing that went wrong.
+    pub error: Option<String>,
+}
+
+impl Default for PlaybackState {
+    fn default() -> Self {
+        PlaybackState { path: None, duration: None, position: Duration::ZERO, paused: false, volume: 100, error: None }
+    }
+}
+
+impl PlaybackState {
+    pub fn apply(&mut self, event: PlaybackEvent) {
+        match event {
+            PlaybackEvent::Started { path, duration } => {
+                *self = PlaybackState { path: Some(path), duration, volume: self.volume, ..PlaybackState::default() };
+            }
+            PlaybackEvent::Position(position) => self.position = position,
+            PlaybackEvent::Paused(paused) => self.paused = paused,
+            PlaybackEvent::Finished | PlaybackEvent::Stopped => {
+                *self = PlaybackState { volume: self.volume, ..PlaybackState::default() };
+            }
+            PlaybackEvent::Error(error) => self.error = Some(error),
+        }
+    }
+}
+
+/// Opens the device the playback thread writes to. Some audio backends can't
+/// move their streams between threads, so the sink is opened on the playback thread.
+pub type OpenSink = fn() -> io::Result<Box<dyn Sink>>;
+
+/// Decodes and plays one track at a time on a background thread.
+pub struct Player {
+    /// `None` once the player is being dropped.
+    commands: Option<Sender<Command>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Player {
+    /// Start the playback thread, writing to the sink `open_sink` opens.
+    pub fn start(open_sink: OpenSink, messages: Sender<Message>) -> Player {
+        let (commands, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let send = |event| {
+                let _ = messages.send(Message::Playback(event));
+            };
+            match open_sink() {
+                Ok(sink) => Engine { sink, send: &send, volume: 1.0, paused: false, track: None }.run(&receiver),
+                Err(e) => send(PlaybackEvent::Error(format!("Cannot open the audio output: {e}"))),
+            }
+        });
+        Player { commands: Some(commands), thread: Some(thread) }
+    }
+
+    /// Whether the playback thread is still there to take commands.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|thread| !thread.is_finished())
+    }
+
+    pub fn send(&self, command: Command) {
+        // The thread only goes away when its sink couldn't be opened, which was reported then
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(command);
+        }
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        // Hanging the channel up ends the playback thread
+        self.commands = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The track being played.
+struct Track {
+    decoder: Decoder,
+    /// Where decoding last started, at the beginning or after a seek.
+    start: Duration,
+    /// Frames written to the sink since `start`.
+    frames: u64,
+    /// The sample rate and channel count the sink was configured for.
+    format: Option<(u32, u16)>,
+    last_report: Instant,
+}
+
+impl Track {
+    fn position(&self, latency: Duration) -> Duration {
+        let played = Duration::from_secs_f64(self.frames as f64 / self.decoder.sample_rate as f64);
+        (self.start + played).saturating_sub(latency)
+    }
+}
+
+/// The state of the playback thread.
+struct Engine<'a> {
+    sink: Box<dyn Sink>,
+    send: &'a dyn Fn(PlaybackEvent),
+    volume: f32,
+    paused: bool,
+    track: Option<Track>,
+}
+
+impl Engine<'_> {
+    fn run(mut self, commands: &Receiver<Command>) {
+        loop {
+            // Only wait for a command when there's nothing to play
+            let command = if self.track.is_some() && !self.paused {
+                match commands.try_recv() {
+                    Ok(command) => Some(command),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            } else {
+                match commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => return,
+                }
+            };
+            match command {
+                Some(command) => self.handle(command),
+                None => self.play_block(),
+            }
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Play(path) => {
+                self.sink.clear();
+                if self.paused {
+                    self.paused = false;
+                    self.sink.pause(false);
+                }
+                match Decoder::open(&path) {
+                    Ok(decoder) => {
+                        (self.send)(PlaybackEvent::Started { path, duration: decoder.duration });
+                        let last_report = Instant::now();
+                        self.track = Some(Track { decoder, start: Duration::ZERO, frames: 0, format: None, last_report });
+                    }
+                    Err(e) => {
+                        self.track = None;
+                        (self.send)(PlaybackEvent::Error(format!("Cannot play {}: {e}", path.display())));
+                    }
+                }
+            }
+            Command::TogglePause if self.track.is_some() => {
+                self.paused = !self.paused;
+                self.sink.pause(self.paused);
+                (self.send)(PlaybackEvent::Paused(self.paused));
+            }
+            Command::TogglePause => {}
+            Command::Seek(position) => {
+                let Some(track) = &mut self.track else {
+                    return;
+                };
+                match track.decoder.seek(position) {
+                    Ok(start) => {
+                        self.sink.clear();
+                        track.start = start;
+                        track.frames = 0;
+                        (self.send)(PlaybackEvent::Position(start));
+                    }
+                    Err(e) => (self.send)(PlaybackEvent::Error(format!("Cannot seek: {e}"))),
+                }
+            }
+            Command::Stop => {
+                self.sink.clear();
+                if self.paused {
+                    self.paused = false;
+                    self.sink.pause(false);
+                }
+                if self.track.take().is_some() {
+                    (self.send)(PlaybackEvent::Stopped);
+                }
+            }
+            Command::Volume(volume) => self.volume = volume.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Decode the next block of the track and write it to the sink.
+    fn play_block(&mut self) {
+        let Some(track) = &mut self.track else {
+            return;
+        };
+        let block = track.decoder.decode_next().and_then(|decoded| {
+            if !decoded {
+                return Ok(false);
+            }
+            let format = (track.decoder.sample_rate, track.decoder.channels);
+            if track.format != Some(format) {
+                self.sink.configure(format.0, format.1)?;
+                track.format = Some(format);
+            }
+            let samples = track.decoder.samples();
+            let scaled: Vec<i16> = samples.iter().map(|&sample| (sample as f32 * self.volume) as i16).collect();
+            self.sink.write(&scaled)?;
+            track.frames += (samples.len() / format.1.max(1) as usize) as u64;
+            Ok(true)
+        });
+
+        match block {
+            Ok(true) => {
+                if track.last_report.elapsed() >= POSITION_INTERVAL {
+                    track.last_report = Instant::now();
+                    (self.send)(PlaybackEvent::Position(track.position(self.sink.latency())));
+                }
+            }
+            Ok(false) => {
+                self.sink.drain();
+                self.track = None;
+                (self.send)(PlaybackEvent::Finished);
+            }
+            Err(e) => {
+                self.sink.clear();
+                self.track = None;
+                (self.send)(PlaybackEvent::Error(format!("Playback failed: {e}")));
+            }
+        }
+    }
+}
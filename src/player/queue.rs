@@ -0,0 +1,256 @@
+// src/player/queue.rs
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use strum::Display;
+
+/// What happens when a track ends.
+#[derive(Clone, Copy, Default, Display, PartialEq, Eq)]
+pub enum Repeat {
+    #[default]
+    #[strum(to_string = "Off")]
+    Off,
+    #[strum(to_string = "One")]
+    One,
+    #[strum(to_string = "All")]
+    All,
+}
+
+impl Repeat {
+    pub fn next(self) -> Repeat {
+        match self {
+            Repeat::Off => Repeat::All,
+            Repeat::All => Repeat::One,
+            Repeat::One => Repeat::Off,
+        }
+    }
+}
+
+/// The tracks lined up in the Play tab, by path.
+#[derive(Default)]
+pub struct PlayQueue {
+    pub tracks: Vec<String>,
+    /// The track playing, or last played.
+    pub current: Option<usize>,
+    pub shuffle: bool,
+    pub repeat: Repeat,
+    /// Tracks played in this round of shuffling, oldest first, so a round
+    /// plays every track once and "previous" can retrace it.
+    played: Vec<usize>,
+    /// State of the shuffle's random number generator.
+    seed: u64,
+}
+
+impl PlayQueue {
+    pub fn add(&mut self, paths: impl IntoIterator<Item = String>) {
+        self.tracks.extend(paths);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.tracks.len() {
+            return;
+        }
+        self.tracks.remove(index);
+        let shift = |track: usize| if track > index { track - 1 } else { track };
+        self.current = self.current.filter(|&current| current != index).map(shift);
+        self.played = self.played.iter().filter(|&&track| track != index).map(|&track| shift(track)).collect();
+    }
+
+    /// Swap the track at `index` with its neighbour `offset` places away,
+    /// returning where it ended up.
+    pub fn move_track(&mut self, index: usize, offset: isize) -> usize {
+        let Some(target) = index.checked_add_signed(offset).filter(|&target| target < self.tracks.len()) else {
+            return index;
+        };
+        self.tracks.swap(index, target);
+        let swap = |track: usize| match track {
+            track if track == index => target,
+            track if track == target => index,
+            track => track,
+        };
+        self.current = self.current.map(swap);
+        self.played.iter_mut().for_each(|track| *track = swap(*track));
+        target
+    }
+
+    /// Make `index` the current track.
+    pub fn select(&mut self, index: usize) -> Option<&str> {
+        let path = self.tracks.get(index)?;
+        self.current = Some(index);
+        if self.shuffle && !self.played.contains(&index) {
+            self.played.push(index);
+        }
+        Some(path)
+    }
+
+    pub fn toggle_shuffle(&mut self) {
+        self.shuffle = !self.shuffle;
+        // A new round starts from the track playing now
+        self.played = self.current.into_iter().collect();
+    }
+
+    /// The track to play when the current one ends by itself, honouring repeat.
+    pub fn after_finished(&mut self) -> Option<usize> {
+        match (self.repeat, self.current) {
+            (Repeat::One, Some(current)) => Some(current),
+            _ => self.next(),
+        }
+    }
+
+    /// The track after the current one, wrapping around only when repeating
+    /// the whole queue.
+    pub fn next(&mut self) -> Option<usize> {
+        if self.tracks.is_empty() {
+            return None;
+        }
+        if self.shuffle {
+            return self.random_unplayed();
+        }
+        match self.current {
+            None => Some(0),
+            Some(current) if current + 1 < self.tracks.len() => Some(current + 1),
+            Some(_) if self.repeat == Repeat::All => Some(0),
+            Some(_) => None,
+        }
+    }
+
+    /// The track before the current one: the one played before it when shuffling.
+    pub fn previous(&mut self) -> Option<usize> {
+        if self.shuffle {
+            self.played.pop();
+            return self.played.last().copied();
+        }
+        self.current.and_then(|current| current.checked_sub(1))
+    }
+
+    fn random_unplayed(&mut self) -> Option<usize> {
+        let mut unplayed: Vec<usize> = (0..self.tracks.len()).filter(|track| !self.played.contains(track)).collect();
+        if unplayed.is_empty() {
+            if self.repeat != Repeat::All {
+                return None;
+            }
+            // Start a new round, without playing the last track twice in a row
+            self.played.clear();
+            unplayed = (0..self.tracks.len()).filter(|&track| Some(track) != self.current || self.tracks.len() == 1).collect();
+        }
+        Some(unplayed[self.random() as usize % unplayed.len()])
+    }
+
+    /// xorshift64*, seeded from the clock; good enough to shuffle a queue.
+    fn random(&mut self) -> u64 {
+        if self.seed == 0 {
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+            self.seed = nanos | 1;
+        }
+        self.seed ^= self.seed >> 12;
+        self.seed ^= self.seed << 25;
+        self.seed ^= self.seed >> 27;
+        self.seed.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(len: usize) -> PlayQueue {
+        let mut queue = PlayQueue { seed: 42, ..PlayQueue::default() };
+        queue.add((0..len).map(|track| format!("/music/{track}.mp3")));
+        queue
+    }
+
+    /// Play from the current track until the queue runs out, at most `limit` tracks.
+    fn play(queue: &mut PlayQueue, limit: usize) -> Vec<usize> {
+        let mut order = Vec::new();
+        while let Some(track) = queue.after_finished().filter(|_| order.len() < limit) {
+            queue.select(track);
+            order.push(track);
+        }
+        order
+    }
+
+    #[test]
+    fn plays_in_order_and_wraps_only_when_repeating_all() {
+        let mut queue = queue(3);
+        assert_eq!(play(&mut queue, 10), [0, 1, 2]);
+        assert_eq!(queue.previous(), Some(1));
+
+        queue.repeat = Repeat::All;
+        assert_eq!(play(&mut queue, 4), [0, 1, 2, 0]);
+
+        queue.repeat = Repeat::One;
+        assert_eq!(play(&mut queue, 3), [0, 0, 0]);
+        assert_eq!(queue.next(), Some(1));
+    }
+
+    #[test]
+    fn shuffle_plays_every_track_once_per_round() {
+        let mut queue = queue(5);
+        queue.select(2);
+        queue.toggle_shuffle();
+
+        let mut order = play(&mut queue, 10);
+        assert_eq!(order.len(), 4);
+        order.push(2);
+        order.sort();
+        assert_eq!(order, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn shuffle_repeats_rounds_without_playing_a_track_twice_in_a_row() {
+        let mut queue = queue(4);
+        queue.toggle_shuffle();
+        queue.repeat = Repeat::All;
+
+        let order = play(&mut queue, 40);
+        assert_eq!(order.len(), 40);
+        for round in order.chunks(4) {
+            let mut round = round.to_vec();
+            round.sort();
+            assert_eq!(round, [0, 1, 2, 3]);
+        }
+        assert!(order.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn previous_retraces_the_shuffle() {
+        let mut queue = queue(6);
+        queue.toggle_shuffle();
+        let order = play(&mut queue, 4);
+
+        for expected in order.iter().rev().skip(1) {
+            let track = queue.previous().unwrap();
+            assert_eq!(track, *expected);
+            queue.select(track);
+        }
+        assert_eq!(queue.previous(), None);
+    }
+
+    #[test]
+    fn editing_the_queue_keeps_the_current_track() {
+        let mut queue = queue(4);
+        queue.select(2);
+
+        queue.remove(0);
+        assert_eq!(queue.current, Some(1));
+        assert_eq!(queue.tracks[1], "/music/2.mp3");
+        assert_eq!(queue.move_track(1, -1), 0);
+        assert_eq!(queue.current, Some(0));
+        assert_eq!(queue.move_track(0, -1), 0);
+
+        queue.remove(0);
+        assert_eq!(queue.current, None);
+        assert_eq!(queue.tracks, ["/music/1.mp3", "/music/3.mp3"]);
+    }
+
+    #[test]
+    fn repeat_cycles_through_its_modes() {
+        let mut repeat = Repeat::default();
+        let mut modes = Vec::new();
+        for _ in 0..4 {
+            modes.push(repeat.to_string());
+            repeat = repeat.next();
+        }
+        assert_eq!(modes, ["Off", "All", "One", "Off"]);
+    }
+}
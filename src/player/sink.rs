@@ -0,0 +1,387 @@
+// src/player/sink.rs
+
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where the playback thread writes decoded audio.
+pub trait Sink {
+    /// Prepare for interleaved samples at `sample_rate` with `channels` channels.
+    /// Called before the first block of a track and whenever the format changes.
+    fn configure(&mut self, sample_rate: u32, channels: u16) -> io::Result<()>;
+
+    /// Queue samples for playing, blocking while the queue is full.
+    fn write(&mut self, samples: &[i16]) -> io::Result<()>;
+
+    /// Drop whatever is queued but not played yet, e.g. after a seek.
+    fn clear(&mut self);
+
+    /// Wait until whatever is queued has played.
+    fn drain(&mut self) {}
+
+    fn pause(&mut self, _paused: bool) {}
+
+    /// How long queued samples take to be heard.
+    fn latency(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Open the sound card when built with the `audio` feature. Without the
+/// feature, or without a sound card, playback goes to a sink that plays silently.
+pub fn open_default() -> io::Result<Box<dyn Sink>> {
+    #[cfg(feature = "audio")]
+    match device::DeviceSink::open() {
+        Ok(sink) => return Ok(Box::new(sink)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok(Box::new(NullSink::default()))
+}
+
+/// Throws samples away at the pace a sound card would play them, so playback
+/// behaves the same on machines without one.
+#[derive(Default)]
+pub struct NullSink {
+    sample_rate: u32,
+    channels: u16,
+    /// When playing resumed, and the audio written since then.
+    clock: Option<(Instant, Duration)>,
+}
+
+impl Sink for NullSink {
+    fn configure(&mut self, sample_rate: u32, channels: u16) -> io::Result<()> {
+        if sample_rate == 0 || channels == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no samples to play"));
+        }
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let frames = samples.len() as f64 / self.channels.max(1) as f64;
+        let (started, written) = self.clock.get_or_insert_with(|| (Instant::now(), Duration::ZERO));
+        *written += Duration::from_secs_f64(frames / self.sample_rate.max(1) as f64);
+        // Sleep until the audio before this block would have played
+        if let Some(ahead) = written.checked_sub(started.elapsed()) {
+            thread::sleep(ahead);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.clock = None;
+    }
+
+    fn pause(&mut self, _paused: bool) {
+        // Whether pausing or resuming, pacing starts over
+        self.clock = None;
+    }
+}
+
+#[cfg(feature = "audio")]
+mod device {
+    use std::collections::VecDeque;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{FromSample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig, SupportedStreamConfigRange};
+
+    use super::Sink;
+
+    /// Audio queued ahead of the device, enough to ride out a slow decode.
+    const QUEUE_LENGTH: Duration = Duration::from_millis(500);
+
+    /// Plays through the default output device with cpal. Audio the device
+    /// can't take as it is gets converted to a format it supports: samples to
+    /// its sample type, channels mixed or copied, and the rate resampled.
+    pub struct DeviceSink {
+        device: cpal::Device,
+        stream: Option<Stream>,
+        /// Samples in the device's rate and channels.
+        queue: Arc<Mutex<VecDeque<i16>>>,
+        /// The rate and channels of the audio being written.
+        sample_rate: u32,
+        channels: u16,
+        /// What the device plays.
+        config: StreamConfig,
+        resampler: Resampler,
+    }
+
+    impl DeviceSink {
+        pub fn open() -> io::Result<DeviceSink> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no audio output device"))?;
+            Ok(DeviceSink {
+                device,
+                stream: None,
+                queue: Arc::default(),
+                sample_rate: 0,
+                channels: 0,
+                config: StreamConfig { channels: 0, sample_rate: SampleRate(0), buffer_size: cpal::BufferSize::Default },
+                resampler: Resampler::default(),
+            })
+        }
+
+        fn queue_limit(&self) -> usize {
+            (QUEUE_LENGTH.as_secs_f64() * self.config.sample_rate.0 as f64) as usize * self.config.channels as usize
+        }
+
+        /// The device's closest match to `sample_rate` and `channels`: the
+        /// channel count matters most, then the rate, then a sample type that
+        /// needs no conversion.
+        fn choose_config(&self, sample_rate: u32, channels: u16) -> io::Result<(StreamConfig, SampleFormat)> {
+            let supported = self.device.supported_output_configs().map_err(io::Error::other)?;
+            let format_rank = |format: SampleFormat| match format {
+                SampleFormat::I16 => 3,
+                SampleFormat::F32 => 2,
+                SampleFormat::U16 => 1,
+                _ => 0,
+            };
+            let range = supported
+                .filter(|range| format_rank(range.sample_format()) > 0)
+                .max_by_key(|range: &SupportedStreamConfigRange| {
+                    let has_rate = (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&sample_rate);
+                    (range.channels() == channels, range.channels() >= channels.min(2), has_rate, format_rank(range.sample_format()))
+                })
+                .ok_or_else(|| io::Error::other("the audio device takes no sample type we can convert to"))?;
+            let rate = sample_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+            let format = range.sample_format();
+            Ok((range.with_sample_rate(SampleRate(rate)).config(), format))
+        }
+
+        fn build_stream<T: SizedSample + FromSample<i16>>(&self, config: &StreamConfig) -> Result<Stream, cpal::BuildStreamError> {
+            let queue = Arc::clone(&self.queue);
+            self.device.build_output_stream(
+                config,
+                move |data: &mut [T], _| {
+                    let mut queue = queue.lock().unwrap();
+                    // Running dry plays silence rather than stale samples
+                    for sample in data.iter_mut() {
+                        *sample = T::from_sample(queue.pop_front().unwrap_or(0));
+                    }
+                },
+                |_| {},
+                None,
+            )
+        }
+    }
+
+    impl Sink for DeviceSink {
+        fn configure(&mut self, sample_rate: u32, channels: u16) -> io::Result<()> {
+            if self.stream.is_some() && (sample_rate, channels) == (self.sample_rate, self.channels) {
+                return Ok(());
+            }
+            self.stream = None;
+            self.queue.lock().unwrap().clear();
+
+            let (config, format) = self.choose_config(sample_rate, channels)?;
+            let stream = match format {
+                SampleFormat::I16 => self.build_stream::<i16>(&config),
+                SampleFormat::F32 => self.build_stream::<f32>(&config),
+                _ => self.build_stream::<u16>(&config),
+            }
+            .map_err(|e| {
+                io::Error::other(format!(
+                    "cannot play {sample_rate} Hz audio with {channels} channels as {} Hz with {}: {e}",
+                    config.sample_rate.0, config.channels
+                ))
+            })?;
+            stream.play().map_err(io::Error::other)?;
+            self.stream = Some(stream);
+            self.resampler = Resampler::new(sample_rate, channels, &config);
+            self.config = config;
+            self.sample_rate = sample_rate;
+            self.channels = channels;
+            Ok(())
+        }
+
+        fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+            while self.queue.lock().unwrap().len() > self.queue_limit() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let converted = self.resampler.convert(samples);
+            self.queue.lock().unwrap().extend(converted);
+            Ok(())
+        }
+
+        fn clear(&mut self) {
+            self.queue.lock().unwrap().clear();
+            self.resampler.reset();
+        }
+
+        fn drain(&mut self) {
+            while !self.queue.lock().unwrap().is_empty() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        fn pause(&mut self, paused: bool) {
+            if let Some(stream) = &self.stream {
+                // A stream that can't pause keeps playing what's queued, which runs out soon enough
+                if paused {
+                    let _ = stream.pause();
+                } else {
+                    let _ = stream.play();
+                }
+            }
+        }
+
+        fn latency(&self) -> Duration {
+            let queued = self.queue.lock().unwrap().len() as f64 / self.config.channels.max(1) as f64;
+            Duration::from_secs_f64(queued / self.config.sample_rate.0.max(1) as f64)
+        }
+    }
+
+    /// Converts interleaved audio to the device's channels and rate, resampling
+    /// linearly between the frames on either side of each output frame.
+    #[derive(Default)]
+    struct Resampler {
+        channels: usize,
+        output_channels: usize,
+        /// Input frames per output frame.
+        step: f64,
+        /// Where the next output frame falls, in input frames after `previous`.
+        position: f64,
+        /// The last input frame of the previous block, already in the output channels.
+        previous: Option<Vec<f32>>,
+    }
+
+    impl Resampler {
+        fn new(sample_rate: u32, channels: u16, config: &StreamConfig) -> Resampler {
+            Resampler {
+                channels: channels.max(1) as usize,
+                output_channels: config.channels.max(1) as usize,
+                step: sample_rate as f64 / config.sample_rate.0.max(1) as f64,
+                position: 0.0,
+                previous: None,
+            }
+        }
+
+        fn reset(&mut self) {
+            self.position = 0.0;
+            self.previous = None;
+        }
+
+        /// One input frame in the output channels: mono is copied to every
+        /// channel, anything going down to mono is mixed, and otherwise the
+        /// first channels are kept.
+        fn map_channels(&self, frame: &[i16]) -> Vec<f32> {
+            let output_channels = self.output_channels;
+            if output_channels == 1 && frame.len() > 1 {
+                return vec![frame.iter().map(|&sample| sample as f32).sum::<f32>() / frame.len() as f32];
+            }
+            (0..output_channels).map(|channel| frame[channel % frame.len()] as f32).collect()
+        }
+
+        fn convert(&mut self, samples: &[i16]) -> Vec<i16> {
+            let frames: Vec<Vec<f32>> = samples.chunks_exact(self.channels).map(|frame| self.map_channels(frame)).collect();
+            let Some(first) = frames.first() else {
+                return Vec::new();
+            };
+            if self.step == 1.0 {
+                return frames.iter().flatten().map(|&sample| sample as i16).collect();
+            }
+
+            // Frame 0 is the last one of the previous block, so output can fall between blocks
+            let previous = self.previous.take().unwrap_or_else(|| first.clone());
+            let frame = |index: usize| if index == 0 { &previous } else { &frames[index - 1] };
+            let mut output = Vec::with_capacity((frames.len() as f64 / self.step) as usize * self.output_channels + 1);
+            while self.position < frames.len() as f64 {
+                let index = self.position as usize;
+                let weight = (self.position - index as f64) as f32;
+                let (before, after) = (frame(index), frame((index + 1).min(frames.len())));
+                output.extend(before.iter().zip(after).map(|(&a, &b)| (a + (b - a) * weight) as i16));
+                self.position += self.step;
+            }
+            self.position -= frames.len() as f64;
+            self.previous = frames.last().cloned();
+            output
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn config(sample_rate: u32, channels: u16) -> StreamConfig {
+            StreamConfig { channels, sample_rate: SampleRate(sample_rate), buffer_size: cpal::BufferSize::Default }
+        }
+
+        #[test]
+        fn resampling_keeps_the_length_and_joins_blocks_smoothly() {
+            let mut resampler = Resampler::new(44_100, 1, &config(48_000, 1));
+            // One second of a slow ramp, written in blocks
+            let input = (0..44_100).map(|i| (i / 2) as i16).collect::<Vec<_>>();
+            let mut output = Vec::new();
+            for block in input.chunks(1_000) {
+                output.extend(resampler.convert(block));
+            }
+
+            assert!(output.len().abs_diff(48_000) <= 1, "{}", output.len());
+            // No steps back or jumps ahead, at the block boundaries either
+            assert!(output.windows(2).all(|pair| (0..=1).contains(&(pair[1] - pair[0]))));
+        }
+
+        #[test]
+        fn channels_are_copied_up_and_mixed_down() {
+            let mut resampler = Resampler::new(48_000, 1, &config(48_000, 2));
+            assert_eq!(resampler.convert(&[100, -50]), [100, 100, -50, -50]);
+
+            let mut resampler = Resampler::new(48_000, 2, &config(48_000, 1));
+            assert_eq!(resampler.convert(&[100, 300, -50, 50]), [200, 0]);
+
+            let mut resampler = Resampler::new(48_000, 6, &config(48_000, 2));
+            assert_eq!(resampler.convert(&[1, 2, 3, 4, 5, 6]), [1, 2]);
+        }
+
+        #[test]
+        fn reset_forgets_the_previous_block() {
+            let mut resampler = Resampler::new(24_000, 1, &config(48_000, 1));
+            assert_eq!(resampler.convert(&[1_000, 1_000]), [1_000, 1_000, 1_000, 1_000]);
+            resampler.reset();
+            assert_eq!(resampler.convert(&[0, 0]), [0, 0, 0, 0]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn null_sink_plays_at_the_sample_rate() {
+        let mut sink = NullSink::default();
+        assert!(sink.configure(0, 2).is_err());
+        sink.configure(8_000, 2).unwrap();
+
+        // 50 ms of stereo audio in five blocks
+        let started = Instant::now();
+        for _ in 0..5 {
+            sink.write(&[0; 160]).unwrap();
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(40), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+    }
+
+    #[test]
+    fn null_sink_does_not_catch_up_after_a_pause() {
+        let mut sink = NullSink::default();
+        sink.configure(8_000, 1).unwrap();
+        sink.write(&[0; 80]).unwrap();
+        sink.pause(true);
+        thread::sleep(Duration::from_millis(30));
+        sink.pause(false);
+
+        // Without starting over, the first block after the pause would not wait at all
+        let started = Instant::now();
+        sink.write(&[0; 160]).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(15));
+    }
+}
@@ -0,0 +1,175 @@
+// src/providers/fixture.rs
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::fingerprint::Fingerprint;
+use crate::providers::{normalize, Candidate, MetadataProvider};
+use crate::Metadata;
+
+/// An offline provider answering from a JSON file, so scraping can be tried
+/// out without network access. The file holds a list of recordings:
+///
+/// ```json
+/// { "recordings": [
+///     { "id": "1", "title": "One More Time", "artist": "Daft Punk", "album": "Discovery",
+///       "year": 2001, "track": 1, "fingerprint": "AQADtE..." }
+/// ] }
+/// ```
+pub struct Fixture {
+    path: PathBuf,
+    recordings: Vec<Recording>,
+}
+
+#[derive(Deserialize)]
+struct FixtureFile {
+    recordings: Vec<Recording>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Recording {
+    id: String,
+    title: String,
+    artist: String,
+    album: String,
+    album_artist: String,
+    genre: String,
+    year: u16,
+    track: u16,
+    disc: u16,
+    /// The encoded fingerprint, as `fpcalc` prints it.
+    fingerprint: String,
+}
+
+impl Recording {
+    fn candidate(&self, score: u16) -> Candidate {
+        Candidate {
+            id: self.id.clone(),
+            metadata: Metadata {
+                title: self.title.clone(),
+                artist: self.artist.clone(),
+                album: self.album.clone(),
+                album_artist: self.album_artist.clone(),
+                genre: self.genre.clone(),
+                year: self.year,
+                track: self.track,
+                disc_no: self.disc,
+                ..Metadata::default()
+            },
+            score,
+        }
+    }
+}
+
+impl Fixture {
+    pub fn open(path: PathBuf) -> io::Result<Fixture> {
+        let file: FixtureFile = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(Fixture { path, recordings: file.recordings })
+    }
+}
+
+impl MetadataProvider for Fixture {
+    fn name(&self) -> &str {
+        self.path.to_str().unwrap_or("fixture")
+    }
+
+    /// Exact title matches, scored 100 when the artist matches too.
+    fn search(&self, artist: &str, title: &str) -> io::Result<Vec<Candidate>> {
+        let (artist, title) = (normalize(artist), normalize(title));
+        let mut candidates = self
+            .recordings
+            .iter()
+            .filter(|recording| normalize(&recording.title) == title)
+            .map(|recording| recording.candidate(if normalize(&recording.artist) == artist { 100 } else { 50 }))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|candidate| Reverse(candidate.score));
+        Ok(candidates)
+    }
+
+    fn by_fingerprint(&self, fingerprint: &Fingerprint) -> io::Result<Vec<Candidate>> {
+        Ok(self
+            .recordings
+            .iter()
+            .filter(|recording| !recording.fingerprint.is_empty() && recording.fingerprint == fingerprint.encoded)
+            .map(|recording| recording.candidate(100))
+            .collect())
+    }
+
+    fn album(&self, artist: &str, album: &str) -> io::Result<Vec<Candidate>> {
+        let (artist, album) = (normalize(artist), normalize(album));
+        let mut tracks = self
+            .recordings
+            .iter()
+            .filter(|recording| normalize(&recording.artist) == artist && normalize(&recording.album) == album)
+            .collect::<Vec<_>>();
+        tracks.sort_by_key(|recording| (recording.disc, recording.track));
+        Ok(tracks.into_iter().map(|recording| recording.candidate(100)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::TempFile;
+
+    fn fixture(name: &str) -> (TempFile, Fixture) {
+        let recordings = json!({ "recordings": [
+            { "id": "1", "title": "One More Time", "artist": "Daft Punk", "album": "Discovery", "track": 1,
+              "fingerprint": "AQAA" },
+            { "id": "2", "title": "Aerodynamic", "artist": "Daft Punk", "album": "Discovery", "track": 2 },
+            { "id": "3", "title": "One more time!", "artist": "Someone Else", "album": "Covers", "track": 4 },
+            { "id": "4", "title": "Intro", "artist": "Daft Punk", "album": "Discovery", "track": 1, "disc": 2 }
+        ] });
+        let file = TempFile::new(name, recordings.to_string().as_bytes());
+        let fixture = Fixture::open(file.path().to_path_buf()).unwrap();
+        (file, fixture)
+    }
+
+    fn ids(candidates: &[Candidate]) -> Vec<&str> {
+        candidates.iter().map(|candidate| candidate.id.as_str()).collect()
+    }
+
+    #[test]
+    fn search_matches_normalized_titles_and_prefers_the_artist() {
+        let (_file, fixture) = fixture("fixture-search.json");
+        let candidates = fixture.search("someone else", "ONE MORE TIME").unwrap();
+        assert_eq!(ids(&candidates), ["3", "1"]);
+        assert_eq!(candidates[0].score, 100);
+        assert_eq!(candidates[1].score, 50);
+        assert_eq!(candidates[1].metadata.album, "Discovery");
+        assert!(fixture.search("Daft Punk", "Digital Love").unwrap().is_empty());
+    }
+
+    #[test]
+    fn fingerprints_must_match_exactly() {
+        let (_file, fixture) = fixture("fixture-fingerprint.json");
+        let mut fingerprint = Fingerprint { duration: 320, encoded: "AQAA".to_string(), hash: 0 };
+        assert_eq!(ids(&fixture.by_fingerprint(&fingerprint).unwrap()), ["1"]);
+
+        // Recordings without a fingerprint never match an empty one
+        fingerprint.encoded.clear();
+        assert!(fixture.by_fingerprint(&fingerprint).unwrap().is_empty());
+    }
+
+    #[test]
+    fn albums_are_listed_in_disc_and_track_order() {
+        let (_file, fixture) = fixture("fixture-album.json");
+        let tracks = fixture.album("daft punk", "discovery").unwrap();
+        assert_eq!(ids(&tracks), ["1", "2", "4"]);
+        assert_eq!(tracks[2].metadata.disc_no, 2);
+    }
+
+    #[test]
+    fn open_rejects_files_without_recordings() {
+        let file = TempFile::new("fixture-invalid.json", b"{}");
+        assert!(Fixture::open(file.path().to_path_buf()).is_err());
+        assert!(Fixture::open(file.path().with_extension("missing")).is_err());
+    }
+}
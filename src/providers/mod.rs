@@ -0,0 +1,103 @@
+// src/providers/mod.rs
+
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fingerprint::Fingerprint;
+use crate::Metadata;
+
+pub mod fixture;
+pub mod musicbrainz;
+
+/// A recording a provider thinks a file might be.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    /// The provider's id for the recording, e.g. a MusicBrainz recording MBID.
+    pub id: String,
+    /// The tags the provider has for the recording; unknown fields are left empty.
+    pub metadata: Metadata,
+    /// How well the recording matches the query, 0-100.
+    pub score: u16,
+}
+
+/// A remote or local catalogue of recordings the scraper can look files up in.
+/// Looking a file up is a "Deep Search", paid for with a credit from `credits::Ledger`.
+pub trait MetadataProvider: Send + Sync {
+    /// Shown in the Scraper tab's log.
+    fn name(&self) -> &str;
+
+    /// Recordings matching an artist and a title, best first.
+    fn search(&self, artist: &str, title: &str) -> io::Result<Vec<Candidate>>;
+
+    /// Recordings matching an acoustic fingerprint, best first.
+    fn by_fingerprint(&self, fingerprint: &Fingerprint) -> io::Result<Vec<Candidate>>;
+
+    /// The tracks of the best matching release of an album, in order.
+    fn album(&self, artist: &str, album: &str) -> io::Result<Vec<Candidate>>;
+}
+
+/// Build the provider named in the Settings tab: empty for none,
+/// `musicbrainz` (optionally `musicbrainz:<base url>`) or `fixture:<path>`.
+/// MusicBrainz resolves fingerprints through AcoustID when given a key.
+pub fn from_setting(setting: &str, acoustid_key: &str) -> io::Result<Option<Box<dyn MetadataProvider>>> {
+    let setting = setting.trim();
+    let (kind, argument) = setting.split_once(':').unwrap_or((setting, ""));
+    match kind {
+        "" | "none" => Ok(None),
+        "musicbrainz" => {
+            let mut provider = match argument {
+                "" => musicbrainz::MusicBrainz::default(),
+                base_url => musicbrainz::MusicBrainz::new(base_url),
+            };
+            if !acoustid_key.trim().is_empty() {
+                provider = provider.with_acoustid_key(acoustid_key.trim());
+            }
+            Ok(Some(Box::new(provider)))
+        }
+        "fixture" => Ok(Some(Box::new(fixture::Fixture::open(PathBuf::from(argument))?))),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown metadata provider \"{kind}\""))),
+    }
+}
+
+/// Compare names ignoring case, spacing and punctuation.
+pub fn normalize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    #[test]
+    fn settings_name_the_provider() {
+        assert!(from_setting("", "").unwrap().is_none());
+        assert!(from_setting(" none ", "").unwrap().is_none());
+
+        let provider = from_setting("musicbrainz:http://localhost:5000/ws/2/", "key").unwrap().unwrap();
+        assert_eq!(provider.name(), "http://localhost:5000/ws/2");
+
+        let file = TempFile::new("provider-setting.json", br#"{ "recordings": [] }"#);
+        let provider = from_setting(&format!("fixture:{}", file.path().display()), "").unwrap().unwrap();
+        assert_eq!(provider.name(), file.path().to_str().unwrap());
+    }
+
+    #[test]
+    fn bad_settings_are_errors() {
+        let error = from_setting("discogs", "").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(from_setting("fixture:", "").is_err());
+    }
+
+    #[test]
+    fn normalize_drops_case_spacing_and_punctuation() {
+        assert_eq!(normalize("  Don't Stop Me Now! "), "dontstopmenow");
+        assert_eq!(normalize("Beyoncé"), "beyoncé");
+    }
+}
@@ -0,0 +1,300 @@
+// src/providers/musicbrainz.rs
+
+use std::cmp::Reverse;
+use std::io;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::fingerprint::Fingerprint;
+use crate::providers::{Candidate, MetadataProvider};
+use crate::tags::leading_number;
+use crate::Metadata;
+
+const DEFAULT_BASE_URL: &str = "https://musicbrainz.org/ws/2";
+const ACOUSTID_URL: &str = "https://api.acoustid.org/v2/lookup";
+
+/// MusicBrainz asks clients to identify themselves and to stay under one request a second.
+const USER_AGENT: &str = concat!(
+    "mp3-cli/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/Mp3-Automated-Tag-Editor/Automated-Mp3-CLI )"
+);
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Candidates asked for per search.
+const SEARCH_LIMIT: &str = "5";
+
+/// Looks recordings up in the MusicBrainz web service, or any server speaking
+/// the same JSON API. Fingerprints are resolved through AcoustID, which needs
+/// an application key.
+pub struct MusicBrainz {
+    base_url: String,
+    acoustid_key: Option<String>,
+    /// When the last request was sent, shared by every scraper worker.
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl Default for MusicBrainz {
+    fn default() -> Self {
+        MusicBrainz::new(DEFAULT_BASE_URL)
+    }
+}
+
+impl MusicBrainz {
+    pub fn new(base_url: &str) -> MusicBrainz {
+        MusicBrainz {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            acoustid_key: None,
+            last_request: Mutex::new(None),
+        }
+    }
+
+    /// Enable fingerprint lookups with an AcoustID application key.
+    pub fn with_acoustid_key(self, key: &str) -> MusicBrainz {
+        MusicBrainz { acoustid_key: Some(key.to_string()), ..self }
+    }
+
+    fn get(&self, url: &str, query: &[(&str, &str)]) -> io::Result<Value> {
+        // Holding the lock while sleeping queues the workers up behind each other
+        let mut last_request = self.last_request.lock().unwrap();
+        if let Some(wait) = last_request.and_then(|last| REQUEST_INTERVAL.checked_sub(last.elapsed())) {
+            thread::sleep(wait);
+        }
+        *last_request = Some(Instant::now());
+
+        let mut request = ureq::get(url).set("User-Agent", USER_AGENT).set("Accept", "application/json");
+        for (name, value) in query {
+            request = request.query(name, value);
+        }
+        let response = request.call().map_err(io::Error::other)?;
+        response.into_json()
+    }
+}
+
+impl MetadataProvider for MusicBrainz {
+    fn name(&self) -> &str {
+        &self.base_url
+    }
+
+    fn search(&self, artist: &str, title: &str) -> io::Result<Vec<Candidate>> {
+        let url = format!("{}/recording", self.base_url);
+        let query = search_query(artist, title);
+        let response = self.get(&url, &[("query", &query), ("fmt", "json"), ("limit", SEARCH_LIMIT)])?;
+
+        Ok(array(&response, "recordings").iter().map(recording_candidate).collect())
+    }
+
+    fn by_fingerprint(&self, fingerprint: &Fingerprint) -> io::Result<Vec<Candidate>> {
+        let Some(key) = &self.acoustid_key else {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "fingerprint lookups need an AcoustID key"));
+        };
+        let duration = fingerprint.duration.to_string();
+        let response = self.get(
+            ACOUSTID_URL,
+            &[
+                ("client", key),
+                ("meta", "recordings releasegroups"),
+                ("duration", &duration),
+                ("fingerprint", &fingerprint.encoded),
+            ],
+        )?;
+
+        let mut candidates = Vec::new();
+        for result in array(&response, "results") {
+            let score = (result["score"].as_f64().unwrap_or(0.0) * 100.0).round() as u16;
+            for recording in array(result, "recordings") {
+                let mut candidate = recording_candidate(recording);
+                candidate.score = score;
+                if let Some(group) = array(recording, "releasegroups").first() {
+                    candidate.metadata.album = text(group, "title");
+                }
+                candidates.push(candidate);
+            }
+        }
+        candidates.sort_by_key(|candidate| Reverse(candidate.score));
+        Ok(candidates)
+    }
+
+    fn album(&self, artist: &str, album: &str) -> io::Result<Vec<Candidate>> {
+        let url = format!("{}/release", self.base_url);
+        let query = format!("release:\"{}\" AND artist:\"{}\"", escape(album), escape(artist));
+        let response = self.get(&url, &[("query", &query), ("fmt", "json"), ("limit", "1")])?;
+        let Some(release) = array(&response, "releases").first() else {
+            return Ok(Vec::new());
+        };
+
+        let url = format!("{}/release/{}", self.base_url, text(release, "id"));
+        let release = self.get(&url, &[("inc", "recordings artist-credits"), ("fmt", "json")])?;
+        let score = release_score(&response);
+
+        let mut tracks = Vec::new();
+        for medium in array(&release, "media") {
+            for track in array(medium, "tracks") {
+                let recording = &track["recording"];
+                tracks.push(Candidate {
+                    id: text(recording, "id"),
+                    metadata: Metadata {
+                        title: text(track, "title"),
+                        artist: artist_credit(track).unwrap_or_else(|| artist_credit(&release).unwrap_or_default()),
+                        album: text(&release, "title"),
+                        album_artist: artist_credit(&release).unwrap_or_default(),
+                        year: leading_number(&text(&release, "date")),
+                        track: track["position"].as_u64().unwrap_or(0) as u16,
+                        disc_no: medium["position"].as_u64().unwrap_or(0) as u16,
+                        ..Metadata::default()
+                    },
+                    score,
+                });
+            }
+        }
+        Ok(tracks)
+    }
+}
+
+/// The Lucene query for a recording search, leaving the artist out when it's unknown.
+fn search_query(artist: &str, title: &str) -> String {
+    let mut query = format!("recording:\"{}\"", escape(title));
+    if !artist.is_empty() {
+        query.push_str(&format!(" AND artist:\"{}\"", escape(artist)));
+    }
+    query
+}
+
+/// A recording from a search or an AcoustID lookup, taking album, year and
+/// track from its first release.
+fn recording_candidate(recording: &Value) -> Candidate {
+    let release = array(recording, "releases").first();
+    let medium = release.and_then(|release| array(release, "media").first());
+    let position = medium
+        .and_then(|medium| array(medium, "track").first())
+        .map(|track| leading_number(&text(track, "number")))
+        .unwrap_or(0);
+    let genre = array(recording, "tags")
+        .iter()
+        .max_by_key(|tag| tag["count"].as_i64().unwrap_or(0))
+        .map(|tag| text(tag, "name"))
+        .unwrap_or_default();
+
+    Candidate {
+        id: text(recording, "id"),
+        metadata: Metadata {
+            title: text(recording, "title"),
+            artist: artist_credit(recording).unwrap_or_default(),
+            album: release.map(|release| text(release, "title")).unwrap_or_default(),
+            year: release.map_or(0, |release| leading_number(&text(release, "date"))),
+            track: position,
+            disc_no: medium.map_or(0, |medium| medium["position"].as_u64().unwrap_or(0) as u16),
+            genre,
+            ..Metadata::default()
+        },
+        score: recording["score"].as_u64().unwrap_or(0).min(100) as u16,
+    }
+}
+
+fn release_score(search: &Value) -> u16 {
+    array(search, "releases")
+        .first()
+        .and_then(|release| release["score"].as_u64())
+        .unwrap_or(0)
+        .min(100) as u16
+}
+
+/// Join an "artist-credit" list into a display name, e.g. "Daft Punk feat. Romanthony".
+fn artist_credit(value: &Value) -> Option<String> {
+    let credits = value.get("artist-credit").or_else(|| value.get("artists"))?.as_array()?;
+    let name = credits
+        .iter()
+        .map(|credit| format!("{}{}", text(credit, "name"), text(credit, "joinphrase")))
+        .collect::<String>();
+    (!name.is_empty()).then_some(name)
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value[key].as_str().unwrap_or_default().to_string()
+}
+
+/// Escape Lucene syntax inside a quoted query term.
+fn escape(term: &str) -> String {
+    term.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn search_queries_escape_their_terms() {
+        assert_eq!(search_query("", "One More Time"), r#"recording:"One More Time""#);
+        assert_eq!(
+            search_query(r"AC\DC", r#"Say "Hi""#),
+            r#"recording:"Say \"Hi\"" AND artist:"AC\\DC""#
+        );
+    }
+
+    #[test]
+    fn recordings_take_their_tags_from_the_first_release() {
+        let recording = json!({
+            "id": "rec-1",
+            "score": 97,
+            "title": "One More Time",
+            "artist-credit": [{ "name": "Daft Punk", "joinphrase": " feat. " }, { "name": "Romanthony" }],
+            "releases": [
+                { "title": "Discovery", "date": "2001-03-12",
+                  "media": [{ "position": 2, "track": [{ "number": "7" }] }] },
+                { "title": "Alive 2007", "date": "2007" }
+            ],
+            "tags": [{ "name": "house", "count": 3 }, { "name": "electronic", "count": 8 }]
+        });
+        let candidate = recording_candidate(&recording);
+        assert_eq!(candidate.id, "rec-1");
+        assert_eq!(candidate.score, 97);
+        let metadata = candidate.metadata;
+        assert_eq!(metadata.title, "One More Time");
+        assert_eq!(metadata.artist, "Daft Punk feat. Romanthony");
+        assert_eq!(metadata.album, "Discovery");
+        assert_eq!((metadata.year, metadata.track, metadata.disc_no), (2001, 7, 2));
+        assert_eq!(metadata.genre, "electronic");
+    }
+
+    #[test]
+    fn missing_fields_are_left_empty() {
+        let candidate = recording_candidate(&json!({ "id": "rec-2", "score": 250 }));
+        assert_eq!(candidate.score, 100);
+        assert!(candidate.metadata == Metadata::default());
+    }
+
+    #[test]
+    fn artist_credits_fall_back_to_acoustid_artists() {
+        assert_eq!(artist_credit(&json!({ "artists": [{ "name": "Air" }] })).as_deref(), Some("Air"));
+        assert_eq!(artist_credit(&json!({ "artist-credit": [] })), None);
+        assert_eq!(artist_credit(&json!({})), None);
+    }
+
+    #[test]
+    fn release_scores_come_from_the_best_search_result() {
+        assert_eq!(release_score(&json!({ "releases": [{ "score": 88 }, { "score": 100 }] })), 88);
+        assert_eq!(release_score(&json!({ "releases": [] })), 0);
+    }
+
+    #[test]
+    fn base_urls_lose_their_trailing_slash() {
+        assert_eq!(MusicBrainz::new("http://localhost:5000/ws/2/").name(), "http://localhost:5000/ws/2");
+        assert_eq!(MusicBrainz::default().name(), DEFAULT_BASE_URL);
+    }
+
+    #[test]
+    fn fingerprint_lookups_need_a_key() {
+        let fingerprint = Fingerprint { duration: 60, encoded: "AQAA".to_string(), hash: 0 };
+        let error = MusicBrainz::new("http://localhost:1").by_fingerprint(&fingerprint).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+}
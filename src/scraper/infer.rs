@@ -0,0 +1,401 @@
+// src/scraper/infer.rs
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Metadata;
+
+/// Templates tried after the user's own, most specific first. Path components
+/// are separated by `/`; the last component is matched against the file name
+/// without its extension.
+const BUILT_IN_TEMPLATES: [(&str, u16); 9] = [
+    ("{artist}/{album} ({year})/{track} - {title}", 90),
+    ("{artist}/{album} ({year})/{track}. {title}", 90),
+    ("{artist} - {album}/{track} - {title}", 85),
+    ("{artist}/{album}/{track} - {title}", 85),
+    ("{artist}/{album}/{track}. {title}", 85),
+    ("{artist}/{album}/{track} {title}", 75),
+    ("{track} - {artist} - {title}", 75),
+    ("{artist} - {title}", 70),
+    ("{track} - {title}", 60),
+];
+
+/// Reliability of a template the user wrote for their own library.
+const USER_TEMPLATE_RELIABILITY: u16 = 95;
+
+/// Words that mark a bracketed part of a file name as video-site noise,
+/// e.g. "[Official Video]" or "(Lyrics)".
+const NOISE_WORDS: [&str; 13] = [
+    "official", "video", "audio", "lyrics", "lyric", "visualizer", "visualiser", "hd", "hq", "4k", "mv", "clip",
+    "explicit",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Artist,
+    AlbumArtist,
+    Album,
+    Title,
+    Track,
+    Disc,
+    Year,
+    Genre,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        Some(match name {
+            "artist" => Field::Artist,
+            "albumartist" => Field::AlbumArtist,
+            "album" => Field::Album,
+            "title" => Field::Title,
+            "track" => Field::Track,
+            "disc" => Field::Disc,
+            "year" => Field::Year,
+            "genre" => Field::Genre,
+            _ => return None,
+        })
+    }
+
+    /// How much finding this field adds to a guess's confidence; the weights sum to 100.
+    fn weight(self) -> u16 {
+        match self {
+            Field::Title => 35,
+            Field::Artist => 25,
+            Field::Album => 15,
+            Field::Track | Field::Year => 10,
+            Field::Disc => 5,
+            Field::AlbumArtist | Field::Genre => 0,
+        }
+    }
+
+    fn accepts(self, value: &str) -> bool {
+        let digits = |max: usize| value.len() <= max && value.bytes().all(|b| b.is_ascii_digit());
+        match self {
+            Field::Track => digits(3),
+            Field::Disc => digits(2),
+            Field::Year => value.len() == 4 && digits(4) && matches!(&value[..2], "19" | "20"),
+            _ => true,
+        }
+    }
+
+    fn apply(self, value: &str, metadata: &mut Metadata) {
+        let value = value.to_string();
+        match self {
+            Field::Artist => metadata.artist = value,
+            Field::AlbumArtist => metadata.album_artist = value,
+            Field::Album => metadata.album = value,
+            Field::Title => metadata.title = value,
+            Field::Genre => metadata.genre = value,
+            Field::Track => metadata.track = value.parse().unwrap_or(0),
+            Field::Disc => metadata.disc_no = value.parse().unwrap_or(0),
+            Field::Year => metadata.year = value.parse().unwrap_or(0),
+        }
+    }
+}
+
+enum Token {
+    Text(String),
+    Field(Field),
+}
+
+/// A file name pattern such as `{artist}/{album} ({year})/{track} - {title}`.
+pub struct Template {
+    components: Vec<Vec<Token>>,
+    /// How far a match of this template can be trusted, in percent.
+    reliability: u16,
+}
+
+impl Template {
+    /// Parse a template. Fields are written in braces and must be separated by
+    /// some text, as `{track}{title}` could be split anywhere.
+    pub fn parse(source: &str, reliability: u16) -> io::Result<Template> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, format!("pattern \"{source}\": {msg}"));
+
+        let mut components = Vec::new();
+        for component in source.trim().trim_matches('/').split('/') {
+            let mut tokens = Vec::new();
+            let mut rest = component;
+            while !rest.is_empty() {
+                match rest.find('{') {
+                    Some(0) => {
+                        let end = rest.find('}').ok_or_else(|| invalid("unclosed {".to_string()))?;
+                        let field = Field::parse(&rest[1..end])
+                            .ok_or_else(|| invalid(format!("unknown field {{{}}}", &rest[1..end])))?;
+                        if matches!(tokens.last(), Some(Token::Field(_))) {
+                            return Err(invalid("fields must be separated by text".to_string()));
+                        }
+                        tokens.push(Token::Field(field));
+                        rest = &rest[end + 1..];
+                    }
+                    Some(start) => {
+                        tokens.push(Token::Text(rest[..start].to_string()));
+                        rest = &rest[start..];
+                    }
+                    None => {
+                        tokens.push(Token::Text(rest.to_string()));
+                        rest = "";
+                    }
+                }
+            }
+            components.push(tokens);
+        }
+
+        if !components.iter().flatten().any(|token| matches!(token, Token::Field(_))) {
+            return Err(invalid("no fields".to_string()));
+        }
+        Ok(Template { components, reliability })
+    }
+
+    /// Match the template against the last path components, returning the fields found.
+    fn capture<'a>(&self, names: &[&'a str]) -> Option<Vec<(Field, &'a str)>> {
+        let names = names.get(names.len().checked_sub(self.components.len())?..)?;
+        let mut captures = Vec::new();
+        for (tokens, name) in self.components.iter().zip(names) {
+            if !match_tokens(tokens, name, &mut captures) {
+                return None;
+            }
+        }
+        Some(captures)
+    }
+}
+
+/// Match `text` against `tokens` in full. Fields take as little text as they
+/// can, so the first " - " in "Artist - Title - Remix" splits the artist off.
+fn match_tokens<'a>(tokens: &[Token], text: &'a str, captures: &mut Vec<(Field, &'a str)>) -> bool {
+    match tokens.split_first() {
+        None => text.trim().is_empty(),
+        Some((Token::Text(literal), rest)) => {
+            strip_literal(text, literal).is_some_and(|text| match_tokens(rest, text, captures))
+        }
+        Some((Token::Field(field), [])) => {
+            let value = text.trim();
+            let accepted = !value.is_empty() && field.accepts(value);
+            if accepted {
+                captures.push((*field, value));
+            }
+            accepted
+        }
+        Some((Token::Field(field), rest)) => {
+            for (end, _) in text.char_indices().skip(1) {
+                let value = text[..end].trim();
+                if value.is_empty() || !field.accepts(value) {
+                    continue;
+                }
+                let mark = captures.len();
+                if match_tokens(rest, &text[end..], captures) {
+                    captures.insert(mark, (*field, value));
+                    return true;
+                }
+                captures.truncate(mark);
+            }
+            false
+        }
+    }
+}
+
+/// Strip `literal` from the start of `text`, ignoring ASCII case. A space in
+/// the literal matches any run of at least one space.
+fn strip_literal<'a>(text: &'a str, literal: &str) -> Option<&'a str> {
+    let mut rest = text;
+    for expected in literal.chars() {
+        if expected == ' ' {
+            let trimmed = rest.trim_start_matches(' ');
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            rest = trimmed;
+        } else {
+            let mut chars = rest.chars();
+            if !chars.next()?.eq_ignore_ascii_case(&expected) {
+                return None;
+            }
+            rest = chars.as_str();
+        }
+    }
+    Some(rest)
+}
+
+/// Tidy a path component before matching: underscores become spaces, runs of
+/// spaces collapse, and bracketed noise like "[Official Video]" is dropped.
+fn clean(name: &str) -> String {
+    let mut cleaned = String::new();
+    let mut rest = name;
+    while let Some(open) = rest.find(['(', '[']) {
+        let close = if rest[open..].starts_with('(') { ')' } else { ']' };
+        let Some(length) = rest[open..].find(close) else {
+            break;
+        };
+        let inner = rest[open + 1..open + length].to_lowercase();
+        let is_noise = inner
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| NOISE_WORDS.contains(&word));
+        cleaned.push_str(&rest[..open]);
+        if !is_noise {
+            cleaned.push_str(&rest[open..=open + length]);
+        }
+        rest = &rest[open + length + 1..];
+    }
+    cleaned.push_str(rest);
+
+    cleaned.replace('_', " ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Metadata inferred from a file's path, with how far it can be trusted.
+pub struct Guess {
+    /// Only the inferred fields are set.
+    pub metadata: Metadata,
+    /// 0-100: the share of fields found, scaled by the template's reliability.
+    pub confidence: u16,
+}
+
+/// Infers metadata from file and folder names using the user's templates
+/// followed by the built-in ones.
+pub struct Inference {
+    templates: Vec<Template>,
+    /// Folders above this one are never read as artist or album names.
+    root: Option<PathBuf>,
+}
+
+impl Inference {
+    /// Build an engine from the user's templates, separated by `;`.
+    pub fn new(patterns: &str) -> io::Result<Inference> {
+        let mut templates = patterns
+            .split(';')
+            .filter(|pattern| !pattern.trim().is_empty())
+            .map(|pattern| Template::parse(pattern, USER_TEMPLATE_RELIABILITY))
+            .collect::<io::Result<Vec<_>>>()?;
+        for (pattern, reliability) in BUILT_IN_TEMPLATES {
+            templates.push(Template::parse(pattern, reliability)?);
+        }
+        Ok(Inference { templates, root: None })
+    }
+
+    /// Only read folder names from `root` down, so the folders a library
+    /// happens to live in (`/home/me/Music`) aren't taken for an artist or album.
+    pub fn within(self, root: &Path) -> Inference {
+        Inference { root: Some(root.to_path_buf()), ..self }
+    }
+
+    /// The most confident guess any template makes for `path`. Ties go to the
+    /// template listed first.
+    pub fn infer(&self, path: &Path) -> Option<Guess> {
+        let stem = clean(&path.file_stem()?.to_string_lossy());
+        let folders = path.parent()?;
+        // The root's own name is kept, as a library rooted at an artist's folder is common
+        let folders = self
+            .root
+            .as_ref()
+            .and_then(|root| folders.strip_prefix(root.parent()?).ok())
+            .unwrap_or(folders);
+        let mut names = folders
+            .components()
+            .map(|component| clean(&component.as_os_str().to_string_lossy()))
+            .collect::<Vec<_>>();
+        names.push(stem);
+        let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let mut best: Option<Guess> = None;
+        for template in &self.templates {
+            let Some(captures) = template.capture(&names) else {
+                continue;
+            };
+            let mut metadata = Metadata::default();
+            let mut weight = 0;
+            for (field, value) in captures {
+                field.apply(value, &mut metadata);
+                weight += field.weight();
+            }
+            let confidence = weight * template.reliability / 100;
+            if best.as_ref().is_none_or(|best| confidence > best.confidence) {
+                best = Some(Guess { metadata, confidence });
+            }
+        }
+        best
+    }
+}
+
+impl Default for Inference {
+    fn default() -> Self {
+        Inference::new("").expect("built-in templates are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(inference: &Inference, path: &str) -> Guess {
+        inference.infer(Path::new(path)).unwrap()
+    }
+
+    #[test]
+    fn built_in_templates_read_artist_album_folders() {
+        let guess = infer(&Inference::default(), "/music/Daft Punk/Discovery (2001)/03 - Digital Love.flac");
+        assert_eq!(guess.metadata.artist, "Daft Punk");
+        assert_eq!(guess.metadata.album, "Discovery");
+        assert_eq!(guess.metadata.year, 2001);
+        assert_eq!(guess.metadata.track, 3);
+        assert_eq!(guess.metadata.title, "Digital Love");
+        assert_eq!(guess.confidence, 95 * 90 / 100);
+    }
+
+    #[test]
+    fn file_names_are_cleaned_before_matching() {
+        let guess = infer(&Inference::default(), "/downloads/Daft_Punk - One More Time (Official Video) [HD].mp3");
+        assert_eq!(guess.metadata.artist, "Daft Punk");
+        assert_eq!(guess.metadata.title, "One More Time");
+        assert_eq!(guess.confidence, 60 * 70 / 100);
+
+        // Brackets that aren't noise stay part of the title
+        let guess = infer(&Inference::default(), "/downloads/Artist - Song (Live at Wembley).mp3");
+        assert_eq!(guess.metadata.title, "Song (Live at Wembley)");
+    }
+
+    #[test]
+    fn fields_take_as_little_text_as_they_can() {
+        let guess = infer(&Inference::default(), "/downloads/Artist - Title - Remix.mp3");
+        assert_eq!(guess.metadata.artist, "Artist");
+        assert_eq!(guess.metadata.title, "Title - Remix");
+    }
+
+    #[test]
+    fn user_templates_win_over_built_in_ones() {
+        let inference = Inference::new("{genre}/{albumartist}/{album}/{disc}-{track} {title}; ").unwrap();
+        let guess = infer(&inference, "/music/Jazz/Various Artists/Blue Note/1-07 Moanin'.mp3");
+        assert_eq!(guess.metadata.genre, "Jazz");
+        assert_eq!(guess.metadata.album_artist, "Various Artists");
+        assert_eq!(guess.metadata.album, "Blue Note");
+        assert_eq!((guess.metadata.disc_no, guess.metadata.track), (1, 7));
+        assert_eq!(guess.metadata.title, "Moanin'");
+        assert_eq!(guess.confidence, 65 * USER_TEMPLATE_RELIABILITY / 100);
+    }
+
+    #[test]
+    fn folders_above_the_root_are_ignored() {
+        let inference = Inference::default().within(Path::new("/home/me/Music"));
+        let guess = infer(&inference, "/home/me/Music/01 - Artist - Title.mp3");
+        assert_eq!((guess.metadata.artist.as_str(), guess.metadata.album.as_str()), ("Artist", ""));
+        assert_eq!((guess.metadata.track, guess.metadata.title.as_str()), (1, "Title"));
+
+        // Without the root, "me" and "Music" would be taken for the artist and album
+        let guess = infer(&Inference::default(), "/home/me/Music/01 - Artist - Title.mp3");
+        assert_eq!((guess.metadata.artist.as_str(), guess.metadata.album.as_str()), ("me", "Music"));
+    }
+
+    #[test]
+    fn numeric_fields_must_look_like_numbers() {
+        assert!(Field::Track.accepts("012"));
+        assert!(!Field::Track.accepts("1234"));
+        assert!(!Field::Year.accepts("1850"));
+        assert!(Field::Year.accepts("2024"));
+        assert!(Inference::default().infer(Path::new("/music/Intro.mp3")).is_none());
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for pattern in ["{artist}{title}", "{artist} - {name}", "{artist - {title}", "just text"] {
+            assert!(Inference::new(pattern).is_err(), "{pattern}");
+        }
+    }
+}
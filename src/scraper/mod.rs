@@ -0,0 +1,258 @@
+// src/scraper/mod.rs
+
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::credits::Allowance;
+use crate::fingerprint::Fingerprint;
+use crate::log::Level;
+use crate::providers::{normalize, Candidate, MetadataProvider};
+use crate::{tags, Metadata};
+
+use infer::Inference;
+use score::{Evidence, Source, Status};
+
+pub mod infer;
+pub mod pool;
+pub mod scanner;
+pub mod score;
+
+/// Provider matches scoring below this are ignored.
+const MIN_LOOKUP_SCORE: u16 = 80;
+
+/// The outcome of scraping a file.
+pub struct Scraped {
+    pub metadata: Metadata,
+    /// `None` when the audio couldn't be decoded.
+    pub fingerprint: Option<Fingerprint>,
+    /// Whether the file was looked up, spending a Deep Search credit.
+    pub deep_search: bool,
+    /// What the provider answered when the file was looked up.
+    pub lookup: Option<Lookup>,
+}
+
+/// Every answer a provider gave while looking a file up, kept in the library
+/// index for developers to export.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Lookup {
+    pub provider: String,
+    pub responses: Vec<Response>,
+    /// Id of the recording the file was matched to; empty for none.
+    pub matched: String,
+}
+
+/// What a provider answered to one query.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Response {
+    /// e.g. `search "Artist" "Title"`.
+    pub query: String,
+    pub candidates: Vec<Candidate>,
+    /// Why the query failed; empty when it didn't.
+    pub error: String,
+}
+
+impl Lookup {
+    fn record(&mut self, query: String, result: &io::Result<Vec<Candidate>>) {
+        let (candidates, error) = match result {
+            Ok(candidates) => (candidates.clone(), String::new()),
+            Err(e) => (Vec::new(), e.to_string()),
+        };
+        self.responses.push(Response { query, candidates, error });
+    }
+}
+
+/// Everything the workers need to scrape a file.
+pub struct Scraper {
+    pub inference: Inference,
+    /// Where files the tags and file name can't settle are looked up, if anywhere.
+    pub provider: Option<Box<dyn MetadataProvider>>,
+    /// Credits the provider lookups may spend.
+    pub allowance: Allowance,
+    /// How to save the filled in tags back to the file; `None` keeps them in the index only.
+    pub save_to_file: Option<tags::WriteOptions>,
+}
+
+impl Scraper {
+    /// Scrape a single pending entry: read whatever tags the file already
+    /// carries, fingerprint its audio, look it up if the tags and its path aren't
+    /// conclusive, fill the gaps and rate the result. Anything worth logging that
+    /// doesn't stop the file from being scraped is added to `notes`. The entry's
+    /// session name is kept.
+    pub fn scrape(&self, entry: &Metadata, notes: &mut Vec<(Level, String)>) -> io::Result<Scraped> {
+        let path = Path::new(&entry.path);
+        let mut scraped = Metadata { session_name: entry.session_name.clone(), ..tags::read_metadata(path)? };
+        let fingerprint = match Fingerprint::compute(path) {
+            Ok(fingerprint) => Some(fingerprint),
+            Err(e) => {
+                notes.push((Level::Debug, format!("Not fingerprinted: {e}")));
+                None
+            }
+        };
+
+        let guess = self.inference.infer(path);
+        let mut sources = guess
+            .iter()
+            .map(|guess| Source { metadata: &guess.metadata, confidence: guess.confidence })
+            .collect::<Vec<_>>();
+
+        let offline = score::rate(&Evidence { tags: &scraped, sources: &sources, fingerprint: None });
+        let mut deep_search = false;
+        let mut responses = None;
+        let lookup = if offline.status == Status::Complete || self.provider.is_none() {
+            None
+        } else if self.allowance.take() {
+            deep_search = true;
+            let responses = responses.insert(Lookup::default());
+            self.look_up(&scraped, guess.as_ref().map(|guess| &guess.metadata), fingerprint.as_ref(), responses, notes)
+        } else {
+            notes.push((Level::Warn, "Not looked up: no Deep Search credits left".to_string()));
+            None
+        };
+        // Looked up values take precedence over guessed ones when filling gaps
+        let mut fingerprint_score = None;
+        if let Some((candidate, by_fingerprint)) = &lookup {
+            sources.insert(0, Source { metadata: &candidate.metadata, confidence: candidate.score });
+            fingerprint_score = by_fingerprint.then_some(candidate.score);
+        }
+
+        let score = score::rate(&Evidence { tags: &scraped, sources: &sources, fingerprint: fingerprint_score });
+        let tagged = scraped.clone();
+        for source in &sources {
+            fill_missing(&mut scraped, source.metadata);
+        }
+        if let Some(options) = self.save_to_file.filter(|_| scraped != tagged) {
+            if let Err(e) = tags::write_metadata(path, &scraped, options) {
+                notes.push((Level::Warn, format!("Filled in tags not saved to the file: {e}")));
+            }
+        }
+        scraped.percentage = score.percentage;
+        scraped.status = score.status.to_string();
+        Ok(Scraped { metadata: scraped, fingerprint, deep_search, lookup: responses })
+    }
+
+    /// Whether scraping the entry would need a provider lookup, judging by its
+    /// tags and path alone. Nothing is decoded or looked up.
+    pub fn needs_lookup(&self, entry: &Metadata) -> io::Result<bool> {
+        let path = Path::new(&entry.path);
+        let tags = tags::read_metadata(path)?;
+        let guess = self.inference.infer(path);
+        let sources = guess
+            .iter()
+            .map(|guess| Source { metadata: &guess.metadata, confidence: guess.confidence })
+            .collect::<Vec<_>>();
+        let offline = score::rate(&Evidence { tags: &tags, sources: &sources, fingerprint: None });
+        Ok(offline.status != Status::Complete)
+    }
+
+    /// Look the file up by its fingerprint, or else search the provider by artist
+    /// and title, taken from the tags or else the guess. When that finds nothing,
+    /// look for the title among the tracks of the album. Returns the match and
+    /// whether it was found by fingerprint; every answer is added to `lookup`.
+    fn look_up(
+        &self,
+        tags: &Metadata,
+        guess: Option<&Metadata>,
+        fingerprint: Option<&Fingerprint>,
+        lookup: &mut Lookup,
+        notes: &mut Vec<(Level, String)>,
+    ) -> Option<(Candidate, bool)> {
+        let provider = self.provider.as_ref()?;
+        lookup.provider = provider.name().to_string();
+
+        if let Some(fingerprint) = fingerprint {
+            let result = provider.by_fingerprint(fingerprint);
+            if !matches!(&result, Err(e) if e.kind() == io::ErrorKind::Unsupported) {
+                lookup.record(format!("fingerprint {}", fingerprint.hash), &result);
+            }
+            match result {
+                Ok(candidates) => {
+                    if let Some(candidate) = best_match(candidates) {
+                        notes.push((Level::Debug, format!("Fingerprint matched recording {} ({}%)", candidate.id, candidate.score)));
+                        lookup.matched.clone_from(&candidate.id);
+                        return Some((candidate, true));
+                    }
+                }
+                // Providers without fingerprint support fall through to searching
+                Err(e) if e.kind() == io::ErrorKind::Unsupported => {}
+                Err(e) => notes.push((Level::Warn, format!("{} fingerprint lookup failed: {e}", provider.name()))),
+            }
+        }
+
+        let field = |value: fn(&Metadata) -> &String| {
+            let tagged = value(tags);
+            match guess {
+                Some(guess) if tagged.is_empty() => value(guess).clone(),
+                _ => tagged.clone(),
+            }
+        };
+        let (artist, title, album) = (field(|m| &m.artist), field(|m| &m.title), field(|m| &m.album));
+        if title.is_empty() {
+            return None;
+        }
+
+        let result = provider.search(&artist, &title);
+        lookup.record(format!("search {artist:?} {title:?}"), &result);
+        let mut best = match result {
+            Ok(candidates) => best_match(candidates),
+            Err(e) => {
+                notes.push((Level::Warn, format!("{} search failed: {e}", provider.name())));
+                return None;
+            }
+        };
+        if best.is_none() && !artist.is_empty() && !album.is_empty() {
+            let result = provider.album(&artist, &album);
+            lookup.record(format!("album {artist:?} {album:?}"), &result);
+            best = match result {
+                Ok(tracks) => best_match(
+                    tracks
+                        .into_iter()
+                        .filter(|track| normalize(&track.metadata.title) == normalize(&title))
+                        .collect(),
+                ),
+                Err(e) => {
+                    notes.push((Level::Warn, format!("{} album lookup failed: {e}", provider.name())));
+                    None
+                }
+            };
+        }
+
+        if let Some(candidate) = &best {
+            notes.push((Level::Debug, format!("Matched recording {} ({}%)", candidate.id, candidate.score)));
+            lookup.matched.clone_from(&candidate.id);
+        }
+        best.map(|candidate| (candidate, false))
+    }
+}
+
+/// The highest scoring candidate good enough to use; ties go to the first.
+fn best_match(candidates: Vec<Candidate>) -> Option<Candidate> {
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.score >= MIN_LOOKUP_SCORE)
+        .reduce(|best, candidate| if candidate.score > best.score { candidate } else { best })
+}
+
+/// Copy the fields of `from` into the fields of `metadata` that are still empty.
+fn fill_missing(metadata: &mut Metadata, from: &Metadata) {
+    let fill_text = |field: &mut String, value: &String| {
+        if field.is_empty() {
+            field.clone_from(value);
+        }
+    };
+    fill_text(&mut metadata.title, &from.title);
+    fill_text(&mut metadata.artist, &from.artist);
+    fill_text(&mut metadata.album, &from.album);
+    fill_text(&mut metadata.album_artist, &from.album_artist);
+    fill_text(&mut metadata.genre, &from.genre);
+
+    let fill_number = |field: &mut u16, value: u16| {
+        if *field == 0 {
+            *field = value;
+        }
+    };
+    fill_number(&mut metadata.track, from.track);
+    fill_number(&mut metadata.year, from.year);
+    fill_number(&mut metadata.disc_no, from.disc_no);
+}
@@ -0,0 +1,192 @@
+// src/scraper/pool.rs
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::log::Level;
+use crate::messages::Message;
+use crate::scraper::scanner::{self, ScanOptions};
+use crate::scraper::{Scraped, Scraper};
+use crate::Metadata;
+
+/// Progress of a scrape job, reported as `Message::Scrape`.
+pub enum ScrapeEvent {
+    /// The directory could not be scanned at all; the job is over.
+    Aborted(String),
+    /// A path inside the directory could not be read while scanning.
+    Skipped { path: PathBuf, error: String },
+    /// The scan finished and these files are queued for the workers.
+    Found(Vec<Metadata>),
+    Scraped(Box<Scraped>),
+    /// Something worth logging about a file that was still scraped, e.g. a failed lookup.
+    Note { file: String, level: Level, message: String },
+    Failed { entry: Box<Metadata>, error: String },
+    /// Every queued file has been scraped or has failed.
+    Finished,
+    /// A dry run counted how many of the files found would need a Deep Search; the job is over.
+    Estimated { files: usize, lookups: usize },
+}
+
+/// Counters for the job in progress, built up from its `ScrapeEvent`s.
+#[derive(Clone, Copy, Default)]
+pub struct ScrapeProgress {
+    pub found: usize,
+    /// Files done so far, failures included.
+    pub processed: usize,
+    pub failed: usize,
+    started: Option<Instant>,
+}
+
+impl ScrapeProgress {
+    pub fn start() -> ScrapeProgress {
+        ScrapeProgress { started: Some(Instant::now()), ..ScrapeProgress::default() }
+    }
+
+    pub fn percent(&self) -> u16 {
+        match self.found {
+            0 => 0,
+            found => (self.processed * 100 / found) as u16,
+        }
+    }
+
+    /// Time left at the average rate so far; `None` until the first file is done.
+    pub fn eta(&self) -> Option<Duration> {
+        let started = self.started?;
+        if self.processed == 0 {
+            return None;
+        }
+        let remaining = self.found.saturating_sub(self.processed) as u32;
+        Some(started.elapsed() / self.processed as u32 * remaining)
+    }
+}
+
+/// A background scrape: one thread scans the directory, then a fixed set of
+/// worker threads scrapes the files found in parallel.
+pub struct ScrapePool {
+    cancelled: Arc<AtomicBool>,
+    coordinator: Option<JoinHandle<()>>,
+}
+
+impl ScrapePool {
+    /// Scan `root` and scrape what it holds on `threads` workers (at least one).
+    pub fn start(
+        root: PathBuf,
+        options: ScanOptions,
+        scraper: Scraper,
+        threads: usize,
+        messages: Sender<Message>,
+    ) -> ScrapePool {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        // Found files get absolute paths, so folder names are read from the absolute root
+        let root = fs::canonicalize(&root).unwrap_or(root);
+        let coordinator = {
+            let cancelled = Arc::clone(&cancelled);
+            let scraper = Scraper { inference: scraper.inference.within(&root), ..scraper };
+            thread::spawn(move || run(root, options, &scraper, threads, &cancelled, &messages))
+        };
+        ScrapePool { cancelled, coordinator: Some(coordinator) }
+    }
+
+    /// Scan `root` and count the files a scrape would look up, without looking
+    /// anything up or touching the library.
+    pub fn estimate(root: PathBuf, options: ScanOptions, scraper: Scraper, messages: Sender<Message>) -> ScrapePool {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        // Found files get absolute paths, so folder names are read from the absolute root
+        let root = fs::canonicalize(&root).unwrap_or(root);
+        let coordinator = {
+            let cancelled = Arc::clone(&cancelled);
+            let scraper = Scraper { inference: scraper.inference.within(&root), ..scraper };
+            thread::spawn(move || estimate(root, options, &scraper, &cancelled, &messages))
+        };
+        ScrapePool { cancelled, coordinator: Some(coordinator) }
+    }
+}
+
+impl Drop for ScrapePool {
+    fn drop(&mut self) {
+        // Workers finish the file they are on and then stop
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(coordinator) = self.coordinator.take() {
+            let _ = coordinator.join();
+        }
+    }
+}
+
+fn run(
+    root: PathBuf,
+    options: ScanOptions,
+    scraper: &Scraper,
+    threads: usize,
+    cancelled: &AtomicBool,
+    messages: &Sender<Message>,
+) {
+    let send = |event| {
+        // The UI is gone; nobody is waiting for the rest
+        let _ = messages.send(Message::Scrape(event));
+    };
+
+    let scan = match scanner::scan(&root, options) {
+        Ok(scan) => scan,
+        Err(e) => return send(ScrapeEvent::Aborted(format!("Cannot scan {}: {e}", root.display()))),
+    };
+    for (path, e) in scan.errors {
+        send(ScrapeEvent::Skipped { path, error: e.to_string() });
+    }
+    send(ScrapeEvent::Found(scan.files.clone()));
+
+    let total = scan.files.len();
+    let queue = Mutex::new(VecDeque::from(scan.files));
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, total.max(1)) {
+            scope.spawn(|| {
+                while !cancelled.load(Ordering::Relaxed) {
+                    let Some(entry) = queue.lock().unwrap().pop_front() else {
+                        return;
+                    };
+                    let mut notes = Vec::new();
+                    let result = scraper.scrape(&entry, &mut notes);
+                    for (level, message) in notes {
+                        send(ScrapeEvent::Note { file: entry.file_name.clone(), level, message });
+                    }
+                    send(match result {
+                        Ok(scraped) => ScrapeEvent::Scraped(Box::new(scraped)),
+                        Err(e) => ScrapeEvent::Failed { entry: Box::new(entry), error: e.to_string() },
+                    });
+                }
+            });
+        }
+    });
+    send(ScrapeEvent::Finished);
+}
+
+fn estimate(root: PathBuf, options: ScanOptions, scraper: &Scraper, cancelled: &AtomicBool, messages: &Sender<Message>) {
+    let send = |event| {
+        let _ = messages.send(Message::Scrape(event));
+    };
+
+    let scan = match scanner::scan(&root, options) {
+        Ok(scan) => scan,
+        Err(e) => return send(ScrapeEvent::Aborted(format!("Cannot scan {}: {e}", root.display()))),
+    };
+    for (path, e) in scan.errors {
+        send(ScrapeEvent::Skipped { path, error: e.to_string() });
+    }
+
+    let mut lookups = 0;
+    for entry in &scan.files {
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        // Files whose tags can't be read would fail before any lookup
+        if let Ok(true) = scraper.needs_lookup(entry) {
+            lookups += 1;
+        }
+    }
+    send(ScrapeEvent::Estimated { files: scan.files.len(), lookups });
+}
@@ -0,0 +1,151 @@
+// src/scraper/scanner.rs
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::scraper::score::Status;
+use crate::tags::Format;
+use crate::Metadata;
+
+/// Folders that only ever hold operating system or tool bookkeeping.
+const SYSTEM_FOLDERS: [&str; 4] = ["$RECYCLE.BIN", "System Volume Information", "__MACOSX", "lost+found"];
+
+#[derive(Clone, Copy, Default)]
+pub struct ScanOptions {
+    /// Descend into symlinked folders and index symlinked files. Off by default,
+    /// as links commonly point back up the tree or at another copy of the library.
+    pub follow_symlinks: bool,
+}
+
+/// The result of walking a directory.
+#[derive(Default)]
+pub struct Scan {
+    /// One pending entry per audio file found.
+    pub files: Vec<Metadata>,
+    /// Paths that could not be read, with the reason.
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+/// Walk `root` recursively and collect every audio file in it. Files are
+/// recognised by their content rather than their extension. Their paths are
+/// absolute, as they outlive the working directory in the library index.
+pub fn scan(root: &Path, options: ScanOptions) -> io::Result<Scan> {
+    let root = fs::canonicalize(root)?;
+    if !fs::metadata(&root)?.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a directory", root.display())));
+    }
+
+    let mut scan = Scan::default();
+    let mut visited = HashSet::new();
+    walk(&root, options, &mut visited, &mut scan);
+    scan.files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(scan)
+}
+
+fn walk(dir: &Path, options: ScanOptions, visited: &mut HashSet<PathBuf>, scan: &mut Scan) {
+    // Guards against symlink cycles when following links
+    match fs::canonicalize(dir) {
+        Ok(canonical) => {
+            if !visited.insert(canonical) {
+                return;
+            }
+        }
+        Err(e) => {
+            scan.errors.push((dir.to_path_buf(), e));
+            return;
+        }
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            scan.errors.push((dir.to_path_buf(), e));
+            return;
+        }
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                scan.errors.push((dir.to_path_buf(), e));
+                continue;
+            }
+        };
+        let path = entry.path();
+        if is_hidden(&path) {
+            continue;
+        }
+
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                scan.errors.push((path, e));
+                continue;
+            }
+        };
+
+        let is_dir = if file_type.is_symlink() {
+            if !options.follow_symlinks {
+                continue;
+            }
+            // Dangling links are skipped silently
+            match fs::metadata(&path) {
+                Ok(target) => target.is_dir(),
+                Err(_) => continue,
+            }
+        } else {
+            file_type.is_dir()
+        };
+
+        if is_dir {
+            let is_system = path
+                .file_name()
+                .is_some_and(|name| SYSTEM_FOLDERS.iter().any(|system| name.eq_ignore_ascii_case(system)));
+            if !is_system {
+                walk(&path, options, visited, scan);
+            }
+            continue;
+        }
+
+        match Format::detect(&path) {
+            Ok(Some(_)) => scan.files.push(pending_entry(&path)),
+            Ok(None) => {}
+            Err(e) => scan.errors.push((path, e)),
+        }
+    }
+}
+
+fn pending_entry(path: &Path) -> Metadata {
+    Metadata {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().into_owned(),
+        status: Status::Pending.to_string(),
+        ..Metadata::default()
+    }
+}
+
+#[cfg(not(windows))]
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(windows)]
+fn is_hidden(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+    let dotted = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    let attributes = fs::symlink_metadata(path).map_or(0, |metadata| metadata.file_attributes());
+    dotted || attributes & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
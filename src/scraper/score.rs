@@ -0,0 +1,196 @@
+// src/scraper/score.rs
+
+use strum::{Display, EnumString};
+
+use crate::providers::normalize;
+use crate::tags::number_or_empty;
+use crate::Metadata;
+
+/// Scores at or above this are `Complete`, unless sources disagree.
+const COMPLETE_SCORE: u16 = 85;
+/// Scores below this need a human to look at the file.
+const REVIEW_SCORE: u16 = 50;
+
+/// How far tags found in the file are trusted when nothing else confirms them.
+const TAG_TRUST: u16 = 90;
+/// How far a tag is trusted when other sources say something else.
+const CONFLICT_TRUST: u16 = 60;
+
+type FieldValue = fn(&Metadata) -> String;
+
+/// The fields rated, as (weight, value, whether a mismatch is worth a review).
+/// The weights sum to 100.
+const FIELDS: [(u16, FieldValue, bool); 7] = [
+    (30, |m| m.title.clone(), true),
+    (25, |m| m.artist.clone(), true),
+    (15, |m| m.album.clone(), false),
+    (10, |m| number_or_empty(m.year), false),
+    (10, |m| number_or_empty(m.track), false),
+    (5, |m| m.genre.clone(), false),
+    (5, |m| m.album_artist.clone(), false),
+];
+
+/// Triage state of an indexed file, stored in `Metadata::status`. Listed from
+/// most to least in need of attention.
+#[derive(Clone, Copy, Display, EnumString, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    #[strum(to_string = "Failed")]
+    Failed,
+    #[strum(to_string = "Needs Review")]
+    NeedsReview,
+    #[strum(to_string = "Partial")]
+    Partial,
+    #[strum(to_string = "Pending")]
+    Pending,
+    #[strum(to_string = "Complete")]
+    Complete,
+}
+
+impl Status {
+    /// The status stored in `metadata`, treating anything unknown as pending.
+    pub fn of(metadata: &Metadata) -> Status {
+        metadata.status.parse().unwrap_or(Status::Pending)
+    }
+}
+
+/// Metadata for a file from somewhere other than its tags, such as its path
+/// or a provider lookup.
+pub struct Source<'a> {
+    pub metadata: &'a Metadata,
+    /// How far the source can be trusted, 0-100.
+    pub confidence: u16,
+}
+
+/// What a file's tags are rated on.
+pub struct Evidence<'a> {
+    /// The tags read from the file itself.
+    pub tags: &'a Metadata,
+    /// Other sources, which confirm or contradict the tags and may fill their gaps.
+    pub sources: &'a [Source<'a>],
+    /// How well the audio matched a known recording (0-100), once looked up.
+    pub fingerprint: Option<u16>,
+}
+
+pub struct Score {
+    /// 0-100, stored in `Metadata::percentage`.
+    pub percentage: u16,
+    pub status: Status,
+}
+
+/// Rate how complete and trustworthy the tags in `evidence` are, counting
+/// fields the sources would fill in at the most trusted source's confidence.
+pub fn rate(evidence: &Evidence) -> Score {
+    let mut total = 0;
+    let mut conflict = false;
+    for (weight, field, important) in FIELDS {
+        let tagged = field(evidence.tags);
+        let others = evidence
+            .sources
+            .iter()
+            .map(|source| (normalize(&field(source.metadata)), source.confidence))
+            .filter(|(value, _)| !value.is_empty())
+            .collect::<Vec<_>>();
+
+        let trust = if tagged.is_empty() {
+            others.iter().map(|(_, confidence)| *confidence).max().unwrap_or(0)
+        } else if others.is_empty() {
+            TAG_TRUST
+        } else if others.iter().any(|(value, _)| *value == normalize(&tagged)) {
+            100
+        } else {
+            conflict |= important;
+            CONFLICT_TRUST
+        };
+        total += weight * trust.min(100);
+    }
+    let mut percentage = total / 100;
+
+    // An acoustic match is the strongest evidence there is, so it counts for a third
+    if let Some(fingerprint) = evidence.fingerprint {
+        percentage = (percentage * 2 + fingerprint.min(100)) / 3;
+    }
+
+    let status = if conflict || percentage < REVIEW_SCORE {
+        Status::NeedsReview
+    } else if percentage >= COMPLETE_SCORE {
+        Status::Complete
+    } else {
+        Status::Partial
+    };
+    Score { percentage, status }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged() -> Metadata {
+        Metadata {
+            title: "Digital Love".to_string(),
+            artist: "Daft Punk".to_string(),
+            album: "Discovery".to_string(),
+            year: 2001,
+            track: 3,
+            genre: "House".to_string(),
+            album_artist: "Daft Punk".to_string(),
+            ..Metadata::default()
+        }
+    }
+
+    fn rate_with(tags: &Metadata, sources: &[Source], fingerprint: Option<u16>) -> (u16, String) {
+        let score = rate(&Evidence { tags, sources, fingerprint });
+        (score.percentage, score.status.to_string())
+    }
+
+    #[test]
+    fn unconfirmed_tags_are_mostly_trusted() {
+        assert_eq!(rate_with(&tagged(), &[], None), (TAG_TRUST, "Complete".to_string()));
+        assert_eq!(rate_with(&Metadata::default(), &[], None), (0, "Needs Review".to_string()));
+    }
+
+    #[test]
+    fn sources_confirm_tags_ignoring_case_and_punctuation() {
+        let mut other = tagged();
+        other.title = "digital love!".to_string();
+        other.artist = "DAFT PUNK".to_string();
+        let sources = [Source { metadata: &other, confidence: 40 }];
+        assert_eq!(rate_with(&tagged(), &sources, None), (100, "Complete".to_string()));
+    }
+
+    #[test]
+    fn sources_fill_gaps_at_their_confidence() {
+        let tags = Metadata { title: "Title".to_string(), artist: "Artist".to_string(), ..Metadata::default() };
+        let guess = Metadata { album: "Album".to_string(), year: 1999, track: 1, ..Metadata::default() };
+        let lookup = Metadata { album: "Album".to_string(), ..Metadata::default() };
+        let sources = [Source { metadata: &guess, confidence: 50 }, Source { metadata: &lookup, confidence: 80 }];
+
+        // 55 points of tags at 90%, the album at 80% and year and track at 50%
+        let expected = (55 * 90 + 15 * 80 + 20 * 50) / 100;
+        assert_eq!(rate_with(&tags, &sources, None), (expected, "Partial".to_string()));
+    }
+
+    #[test]
+    fn conflicting_titles_need_review_but_albums_do_not() {
+        let album = Metadata { album: "Homework".to_string(), ..Metadata::default() };
+        let sources = [Source { metadata: &album, confidence: 90 }];
+        assert_eq!(rate_with(&tagged(), &sources, None), ((85 * 90 + 15 * 60) / 100, "Complete".to_string()));
+
+        let title = Metadata { title: "Something Else".to_string(), ..Metadata::default() };
+        let sources = [Source { metadata: &title, confidence: 90 }];
+        assert_eq!(rate_with(&tagged(), &sources, None), ((70 * 90 + 30 * 60) / 100, "Needs Review".to_string()));
+    }
+
+    #[test]
+    fn fingerprints_count_for_a_third() {
+        assert_eq!(rate_with(&tagged(), &[], Some(100)).0, (TAG_TRUST * 2 + 100) / 3);
+        assert_eq!(rate_with(&tagged(), &[], Some(0)), (TAG_TRUST * 2 / 3, "Partial".to_string()));
+    }
+
+    #[test]
+    fn unknown_statuses_are_pending() {
+        let status = |status: &str| Status::of(&Metadata { status: status.to_string(), ..Metadata::default() });
+        assert!(status("Needs Review") == Status::NeedsReview);
+        assert!(status("Scanning") == Status::Pending);
+        assert!(Status::Failed < Status::NeedsReview && Status::Pending < Status::Complete);
+    }
+}
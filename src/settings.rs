@@ -0,0 +1,764 @@
+// src/settings.rs
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::KeyCode;
+use ratatui::style::palette::tailwind::{self, Palette};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::downloader::queue::DEFAULT_PARALLEL_DOWNLOADS;
+use crate::downloader::{self, spotify};
+use crate::providers;
+use crate::scraper::infer::Inference;
+use crate::tabs::SelectedTab;
+
+/// More scraper workers than this only wait on each other.
+const MAX_THREADS: usize = 256;
+
+/// More parallel downloads than this only get throttled by the sites.
+const MAX_PARALLEL_DOWNLOADS: usize = 16;
+
+/// Everything the Settings tab configures, saved as TOML in the config directory.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Where the settings are saved; `None` for ones that only live in memory.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Scraper workers; 0 picks one per CPU.
+    pub scraper_threads: usize,
+    /// Filename templates tried before the built-in ones, `;`-separated.
+    pub filename_patterns: String,
+    /// Metadata provider for lookups, see `providers::from_setting`.
+    pub provider: String,
+    /// AcoustID application key, enabling fingerprint lookups.
+    pub acoustid_key: String,
+    /// Scan into the folders symlinks point at.
+    pub follow_symlinks: bool,
+    /// Add ID3v1 trailers when saving, for old players.
+    pub write_id3v1: bool,
+    /// Save the tags the scraper fills in back to the files, not just the index.
+    pub save_scraped_tags: bool,
+    /// yt-dlp compatible downloader; empty runs yt-dlp from PATH.
+    pub extractor: String,
+    /// Where the Download tab saves to unless told otherwise; empty for the working directory.
+    pub download_directory: String,
+    /// The Download tab's bitrate unless told otherwise; empty for the best available.
+    pub download_quality: String,
+    pub parallel_downloads: usize,
+    /// Where Spotify links are resolved, see `downloader::spotify::from_setting`.
+    pub spotify_source: String,
+    pub theme: Theme,
+    /// Show debug lines in the Logs panes.
+    pub developer_mode: bool,
+    pub keybindings: Keybindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            path: None,
+            scraper_threads: 0,
+            filename_patterns: String::new(),
+            provider: String::new(),
+            acoustid_key: String::new(),
+            follow_symlinks: false,
+            write_id3v1: false,
+            save_scraped_tags: false,
+            extractor: String::new(),
+            download_directory: String::new(),
+            download_quality: String::new(),
+            parallel_downloads: DEFAULT_PARALLEL_DOWNLOADS,
+            spotify_source: String::new(),
+            theme: Theme::default(),
+            developer_mode: false,
+            keybindings: Keybindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// The settings location, e.g. `~/.config/mp3-cli/settings.toml` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("mp3-cli").join("settings.toml"))
+    }
+
+    /// Open the settings at the default location.
+    pub fn open_default() -> io::Result<Settings> {
+        let path = Self::default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory for this user"))?;
+        Self::open(path)
+    }
+
+    /// Open the settings at `path`. A missing file, or a setting missing from
+    /// it, takes the default.
+    pub fn open(path: PathBuf) -> io::Result<Settings> {
+        match fs::read_to_string(&path) {
+            Ok(data) => {
+                let settings: Settings = toml::from_str(&data).map_err(|e| {
+                    let line = e.span().map_or(1, |span| data[..span.start].matches('\n').count() + 1);
+                    io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {}", e.message()))
+                })?;
+                Ok(Settings { path: Some(path), ..settings })
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings { path: Some(path), ..Settings::default() }),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write the settings back to disk, through a temporary file like the library index.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let data = toml::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let temp_path = path.with_extension("toml.tmp");
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, path)
+    }
+}
+
+/// Colours of the tab bar and tab borders.
+#[derive(Clone, Copy, Default, Display, EnumIter, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Theme {
+    /// A colour of its own for every tab.
+    #[default]
+    Colorful,
+    Monochrome,
+}
+
+impl Theme {
+    pub fn palette(self, tab: SelectedTab) -> Palette {
+        match self {
+            Theme::Colorful => tab.palette(),
+            Theme::Monochrome => tailwind::SLATE,
+        }
+    }
+
+    fn next(self) -> Theme {
+        let themes: Vec<Theme> = Theme::iter().collect();
+        let index = themes.iter().position(|theme| *theme == self).unwrap_or_default();
+        themes[(index + 1) % themes.len()]
+    }
+}
+
+/// What a key can be bound to. The arrow keys, Enter and Esc keep their
+/// meanings whatever is bound.
+#[derive(Clone, Copy, Display, EnumIter, PartialEq, Eq)]
+pub enum Action {
+    #[strum(to_string = "Next Tab")]
+    NextTab,
+    #[strum(to_string = "Previous Tab")]
+    PreviousTab,
+    Quit,
+    Pause,
+    #[strum(to_string = "Next Track")]
+    NextTrack,
+    #[strum(to_string = "Previous Track")]
+    PreviousTrack,
+    #[strum(to_string = "Volume Up")]
+    VolumeUp,
+    #[strum(to_string = "Volume Down")]
+    VolumeDown,
+    #[strum(to_string = "Seek Back")]
+    SeekBack,
+    #[strum(to_string = "Seek Forward")]
+    SeekForward,
+    /// Only in developer mode.
+    #[strum(to_string = "Debug Overlay")]
+    DebugOverlay,
+}
+
+/// The key of every action, by name, e.g. "q", "space" or "f5".
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    pub next_tab: String,
+    pub previous_tab: String,
+    pub quit: String,
+    pub pause: String,
+    pub next_track: String,
+    pub previous_track: String,
+    pub volume_up: String,
+    pub volume_down: String,
+    pub seek_back: String,
+    pub seek_forward: String,
+    pub debug_overlay: String,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings {
+            next_tab: "l".to_string(),
+            previous_tab: "h".to_string(),
+            quit: "q".to_string(),
+            pause: "space".to_string(),
+            next_track: "n".to_string(),
+            previous_track: "p".to_string(),
+            volume_up: "+".to_string(),
+            volume_down: "-".to_string(),
+            seek_back: ",".to_string(),
+            seek_forward: ".".to_string(),
+            debug_overlay: "f12".to_string(),
+        }
+    }
+}
+
+impl Keybindings {
+    pub fn key(&self, action: Action) -> &str {
+        match action {
+            Action::NextTab => &self.next_tab,
+            Action::PreviousTab => &self.previous_tab,
+            Action::Quit => &self.quit,
+            Action::Pause => &self.pause,
+            Action::NextTrack => &self.next_track,
+            Action::PreviousTrack => &self.previous_track,
+            Action::VolumeUp => &self.volume_up,
+            Action::VolumeDown => &self.volume_down,
+            Action::SeekBack => &self.seek_back,
+            Action::SeekForward => &self.seek_forward,
+            Action::DebugOverlay => &self.debug_overlay,
+        }
+    }
+
+    fn key_mut(&mut self, action: Action) -> &mut String {
+        match action {
+            Action::NextTab => &mut self.next_tab,
+            Action::PreviousTab => &mut self.previous_tab,
+            Action::Quit => &mut self.quit,
+            Action::Pause => &mut self.pause,
+            Action::NextTrack => &mut self.next_track,
+            Action::PreviousTrack => &mut self.previous_track,
+            Action::VolumeUp => &mut self.volume_up,
+            Action::VolumeDown => &mut self.volume_down,
+            Action::SeekBack => &mut self.seek_back,
+            Action::SeekForward => &mut self.seek_forward,
+            Action::DebugOverlay => &mut self.debug_overlay,
+        }
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        Action::iter().find(|action| parse_key(self.key(*action)).is_ok_and(|bound| bound == key))
+    }
+}
+
+/// A key by name: a single character, "space" or "f1" to "f12".
+pub fn parse_key(name: &str) -> Result<KeyCode, String> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() => return Ok(KeyCode::Char(c)),
+        (None, _) => return Err("Enter a key".to_string()),
+        _ => {}
+    }
+    let lowercase = name.to_lowercase();
+    if lowercase == "space" {
+        return Ok(KeyCode::Char(' '));
+    }
+    match lowercase.strip_prefix('f').and_then(|number| number.parse::<u8>().ok()) {
+        Some(number @ 1..=12) => Ok(KeyCode::F(number)),
+        _ => Err(format!("\"{name}\" is not a key; use a single character, space or f1 to f12")),
+    }
+}
+
+/// One row of the Settings tab's form.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    ScraperThreads,
+    FilenamePatterns,
+    Provider,
+    AcoustidKey,
+    FollowSymlinks,
+    WriteId3v1,
+    SaveScrapedTags,
+    Extractor,
+    DownloadDirectory,
+    DownloadQuality,
+    ParallelDownloads,
+    SpotifySource,
+    Theme,
+    DeveloperMode,
+    Key(Action),
+}
+
+/// The form's rows, top to bottom.
+pub const FIELDS: [Field; 25] = [
+    Field::ScraperThreads,
+    Field::FilenamePatterns,
+    Field::Provider,
+    Field::AcoustidKey,
+    Field::FollowSymlinks,
+    Field::WriteId3v1,
+    Field::SaveScrapedTags,
+    Field::Extractor,
+    Field::DownloadDirectory,
+    Field::DownloadQuality,
+    Field::ParallelDownloads,
+    Field::SpotifySource,
+    Field::Theme,
+    Field::DeveloperMode,
+    Field::Key(Action::NextTab),
+    Field::Key(Action::PreviousTab),
+    Field::Key(Action::Quit),
+    Field::Key(Action::Pause),
+    Field::Key(Action::NextTrack),
+    Field::Key(Action::PreviousTrack),
+    Field::Key(Action::VolumeUp),
+    Field::Key(Action::VolumeDown),
+    Field::Key(Action::SeekBack),
+    Field::Key(Action::SeekForward),
+    Field::Key(Action::DebugOverlay),
+];
+
+impl Field {
+    pub fn label(self) -> String {
+        match self {
+            Field::ScraperThreads => "Scraper Threads".to_string(),
+            Field::FilenamePatterns => "Filename Patterns".to_string(),
+            Field::Provider => "Metadata Provider".to_string(),
+            Field::AcoustidKey => "AcoustID API Key".to_string(),
+            Field::FollowSymlinks => "Follow Symlinks".to_string(),
+            Field::WriteId3v1 => "Write ID3v1 Tags".to_string(),
+            Field::SaveScrapedTags => "Save Scraped Tags".to_string(),
+            Field::Extractor => "Extractor Command".to_string(),
+            Field::DownloadDirectory => "Download Directory".to_string(),
+            Field::DownloadQuality => "Download Quality".to_string(),
+            Field::ParallelDownloads => "Parallel Downloads".to_string(),
+            Field::SpotifySource => "Spotify Source".to_string(),
+            Field::Theme => "Theme".to_string(),
+            Field::DeveloperMode => "Developer Mode".to_string(),
+            Field::Key(action) => format!("Key: {action}"),
+        }
+    }
+
+    pub fn help(self) -> &'static str {
+        match self {
+            Field::ScraperThreads => "Files scraped at once; empty for one per CPU.",
+            Field::FilenamePatterns => "Tried before the built-in patterns, e.g. {artist}/{album}/{track} - {title}; separate with ;",
+            Field::Provider => "Where files are looked up: empty, musicbrainz[:<url>] or fixture:<file.json>.",
+            Field::AcoustidKey => "Identifies files by their audio when looking them up on MusicBrainz.",
+            Field::FollowSymlinks => "Scan the folders symlinks point at. Space to toggle.",
+            Field::WriteId3v1 => "Add ID3v1 tags when saving, for old players. Space to toggle.",
+            Field::SaveScrapedTags => "Write the tags guessed from file names or looked up to the files, not just the index. Space to toggle.",
+            Field::Extractor => "A yt-dlp compatible downloader; empty for yt-dlp.",
+            Field::DownloadDirectory => "Where the Download tab saves to; empty for the directory the app started in.",
+            Field::DownloadQuality => "The Download tab's bitrate, e.g. 320 or 192k; empty for the best available.",
+            Field::ParallelDownloads => "Items downloaded at once.",
+            Field::SpotifySource => "Where Spotify links are looked up: empty, api:<client id>:<client secret> or fixture:<file.json>.",
+            Field::Theme => "Colours of the tabs: colorful or monochrome. Space to change.",
+            Field::DeveloperMode => "Show debug lines in the Logs panes, export the index from the Scraper tab and open the debug overlay. Space to toggle.",
+            Field::Key(_) => "A single character, space or f1 to f12; used from any tab.",
+        }
+    }
+
+    /// Whether the field is changed with Space rather than typed into.
+    pub fn is_toggle(self) -> bool {
+        matches!(self, Field::FollowSymlinks | Field::WriteId3v1 | Field::SaveScrapedTags | Field::Theme | Field::DeveloperMode)
+    }
+
+    /// Whether checking the field may open a file it names, e.g. a fixture.
+    fn reads_files(self) -> bool {
+        matches!(self, Field::Provider | Field::SpotifySource)
+    }
+
+    /// Fields checked against this one's value.
+    fn dependents(self) -> &'static [Field] {
+        match self {
+            Field::AcoustidKey => &[Field::Provider],
+            _ => &[],
+        }
+    }
+
+    /// The field's text for `settings`.
+    pub fn value(self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" }.to_string();
+        match self {
+            Field::ScraperThreads => match settings.scraper_threads {
+                0 => String::new(),
+                threads => threads.to_string(),
+            },
+            Field::FilenamePatterns => settings.filename_patterns.clone(),
+            Field::Provider => settings.provider.clone(),
+            Field::AcoustidKey => settings.acoustid_key.clone(),
+            Field::FollowSymlinks => on_off(settings.follow_symlinks),
+            Field::WriteId3v1 => on_off(settings.write_id3v1),
+            Field::SaveScrapedTags => on_off(settings.save_scraped_tags),
+            Field::Extractor => settings.extractor.clone(),
+            Field::DownloadDirectory => settings.download_directory.clone(),
+            Field::DownloadQuality => settings.download_quality.clone(),
+            Field::ParallelDownloads => settings.parallel_downloads.to_string(),
+            Field::SpotifySource => settings.spotify_source.clone(),
+            Field::Theme => settings.theme.to_string(),
+            Field::DeveloperMode => on_off(settings.developer_mode),
+            Field::Key(action) => settings.keybindings.key(action).to_string(),
+        }
+    }
+
+    /// Check `text` and, if it's valid, store it in `settings`.
+    pub fn set(self, settings: &mut Settings, text: &str) -> Result<(), String> {
+        let trimmed = text.trim();
+        match self {
+            Field::ScraperThreads => {
+                settings.scraper_threads = match trimmed {
+                    "" => 0,
+                    threads => parse_count(threads, MAX_THREADS)?,
+                }
+            }
+            Field::FilenamePatterns => {
+                Inference::new(trimmed).map_err(|e| e.to_string())?;
+                settings.filename_patterns = trimmed.to_string();
+            }
+            Field::Provider => {
+                providers::from_setting(trimmed, &settings.acoustid_key).map_err(|e| e.to_string())?;
+                settings.provider = trimmed.to_string();
+            }
+            Field::AcoustidKey => settings.acoustid_key = trimmed.to_string(),
+            Field::FollowSymlinks => settings.follow_symlinks = parse_on_off(trimmed)?,
+            Field::WriteId3v1 => settings.write_id3v1 = parse_on_off(trimmed)?,
+            Field::SaveScrapedTags => settings.save_scraped_tags = parse_on_off(trimmed)?,
+            Field::Extractor => {
+                // A bare name is looked up on PATH when it runs; a path has to be there now
+                if trimmed.contains(std::path::MAIN_SEPARATOR) && !Path::new(trimmed).is_file() {
+                    return Err(format!("{trimmed} is not a file"));
+                }
+                settings.extractor = trimmed.to_string();
+            }
+            Field::DownloadDirectory => {
+                if Path::new(trimmed).exists() && !Path::new(trimmed).is_dir() {
+                    return Err(format!("{trimmed} is not a directory"));
+                }
+                settings.download_directory = trimmed.to_string();
+            }
+            Field::DownloadQuality => {
+                downloader::audio_quality(trimmed).map_err(|e| e.to_string())?;
+                settings.download_quality = trimmed.to_string();
+            }
+            Field::ParallelDownloads => settings.parallel_downloads = parse_count(trimmed, MAX_PARALLEL_DOWNLOADS)?,
+            Field::SpotifySource => {
+                spotify::from_setting(trimmed).map_err(|e| e.to_string())?;
+                settings.spotify_source = trimmed.to_string();
+            }
+            Field::Theme => {
+                settings.theme = Theme::iter()
+                    .find(|theme| theme.to_string().eq_ignore_ascii_case(trimmed))
+                    .ok_or_else(|| format!("\"{trimmed}\" is not a theme; use colorful or monochrome"))?;
+            }
+            Field::DeveloperMode => settings.developer_mode = parse_on_off(trimmed)?,
+            Field::Key(action) => {
+                parse_key(text)?;
+                *settings.keybindings.key_mut(action) = text.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// The next value of a toggle field.
+    fn toggle(self, settings: &mut Settings) {
+        match self {
+            Field::FollowSymlinks => settings.follow_symlinks = !settings.follow_symlinks,
+            Field::WriteId3v1 => settings.write_id3v1 = !settings.write_id3v1,
+            Field::SaveScrapedTags => settings.save_scraped_tags = !settings.save_scraped_tags,
+            Field::Theme => settings.theme = settings.theme.next(),
+            Field::DeveloperMode => settings.developer_mode = !settings.developer_mode,
+            _ => {}
+        }
+    }
+}
+
+fn parse_count(text: &str, max: usize) -> Result<usize, String> {
+    match text.parse() {
+        Ok(count) if (1..=max).contains(&count) => Ok(count),
+        _ => Err(format!("Enter a number from 1 to {max}")),
+    }
+}
+
+fn parse_on_off(text: &str) -> Result<bool, String> {
+    match text.to_lowercase().as_str() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err("Use On or Off".to_string()),
+    }
+}
+
+/// The Settings tab's form: the text of every field as typed, checked as it
+/// changes (or on saving, for fields naming files), and the settings it makes
+/// up. Nothing takes effect until it's saved.
+pub struct SettingsForm {
+    /// The settings the form makes up; a field keeps its last valid value while its text isn't.
+    pub draft: Settings,
+    /// The text of every field, in `FIELDS` order.
+    pub text: Vec<String>,
+    /// What's wrong with every field, in `FIELDS` order.
+    pub errors: Vec<Option<String>>,
+    /// Fields edited since they were last checked, as they open files; see `check_files`.
+    unchecked: Vec<bool>,
+}
+
+impl Default for SettingsForm {
+    fn default() -> Self {
+        SettingsForm::new(&Settings::default())
+    }
+}
+
+impl SettingsForm {
+    pub fn new(settings: &Settings) -> SettingsForm {
+        let text = FIELDS.iter().map(|field| field.value(settings)).collect();
+        let mut form = SettingsForm {
+            draft: settings.clone(),
+            text,
+            errors: vec![None; FIELDS.len()],
+            unchecked: vec![true; FIELDS.len()],
+        };
+        for index in 0..FIELDS.len() {
+            form.check_field(index);
+        }
+        form.check_files();
+        form.check_keys();
+        form
+    }
+
+    /// Type `c` into the field at `index`, or change it for a toggle when `c` is a space.
+    pub fn push(&mut self, index: usize, c: char) {
+        match FIELDS.get(index) {
+            Some(field) if field.is_toggle() && c == ' ' => self.toggle(index),
+            Some(field) if !field.is_toggle() => {
+                self.text[index].push(c);
+                self.edited(index);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn pop(&mut self, index: usize) {
+        if FIELDS.get(index).is_some_and(|field| !field.is_toggle()) {
+            self.text[index].pop();
+            self.edited(index);
+        }
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(field) = FIELDS.get(index).filter(|field| field.is_toggle()) {
+            field.toggle(&mut self.draft);
+            self.text[index] = field.value(&self.draft);
+            self.edited(index);
+        }
+    }
+
+    /// The first field that can't be saved as it is.
+    pub fn first_error(&self) -> Option<(Field, &str)> {
+        FIELDS.iter().zip(&self.errors).find_map(|(field, error)| Some((*field, error.as_deref()?)))
+    }
+
+    /// Whether the form differs from `settings`.
+    pub fn is_changed(&self, settings: &Settings) -> bool {
+        FIELDS.iter().zip(&self.text).any(|(field, text)| field.value(settings) != *text)
+    }
+
+    /// Check fields edited since they were last checked that open files, such
+    /// as a fixture named by the provider. Typing into them leaves that until
+    /// the form is saved, so a key press never waits on the disk.
+    pub fn check_files(&mut self) {
+        for (index, field) in FIELDS.iter().enumerate() {
+            if std::mem::take(&mut self.unchecked[index]) {
+                self.errors[index] = field.set(&mut self.draft, &self.text[index]).err();
+            }
+        }
+    }
+
+    /// Check the field at `index` after it changed, and the fields checked against it.
+    fn edited(&mut self, index: usize) {
+        let field = FIELDS[index];
+        self.check_field(index);
+        for dependent in field.dependents() {
+            if let Some(index) = FIELDS.iter().position(|field| field == dependent) {
+                self.check_field(index);
+            }
+        }
+        if let Field::Key(_) = field {
+            self.check_keys();
+        }
+    }
+
+    /// Check the field at `index` and store its value if it's valid, unless
+    /// that would open a file.
+    fn check_field(&mut self, index: usize) {
+        if FIELDS[index].reads_files() {
+            self.errors[index] = None;
+            self.unchecked[index] = true;
+        } else {
+            self.errors[index] = FIELDS[index].set(&mut self.draft, &self.text[index]).err();
+        }
+    }
+
+    /// Check every key, as changing one can clash with or free up another.
+    fn check_keys(&mut self) {
+        // A key can only do one thing
+        for (index, field) in FIELDS.iter().enumerate() {
+            let Field::Key(action) = field else {
+                continue;
+            };
+            self.errors[index] = parse_key(&self.text[index]).err();
+            if self.errors[index].is_some() {
+                continue;
+            }
+            let key = parse_key(&self.text[index]);
+            let other = FIELDS.iter().zip(&self.text).find_map(|(other, text)| match other {
+                Field::Key(other) if other != action && parse_key(text) == key => Some(*other),
+                _ => None,
+            });
+            if let Some(other) = other {
+                self.errors[index] = Some(format!("Also bound to {other}"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    fn index(field: Field) -> usize {
+        FIELDS.iter().position(|other| *other == field).unwrap()
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(parse_key("q"), Ok(KeyCode::Char('q')));
+        assert_eq!(parse_key("+"), Ok(KeyCode::Char('+')));
+        assert_eq!(parse_key("Space"), Ok(KeyCode::Char(' ')));
+        assert_eq!(parse_key("F5"), Ok(KeyCode::F(5)));
+        assert_eq!(parse_key("f12"), Ok(KeyCode::F(12)));
+        for name in ["", " ", "f0", "f13", "ctrl", "qq"] {
+            assert!(parse_key(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn set_checks_counts_and_toggles() {
+        let mut settings = Settings::default();
+        Field::ScraperThreads.set(&mut settings, " 8 ").unwrap();
+        assert_eq!(settings.scraper_threads, 8);
+        Field::ScraperThreads.set(&mut settings, "").unwrap();
+        assert_eq!(settings.scraper_threads, 0);
+        assert!(Field::ScraperThreads.set(&mut settings, "300").is_err());
+        assert!(Field::ParallelDownloads.set(&mut settings, "0").is_err());
+        assert_eq!(settings.parallel_downloads, DEFAULT_PARALLEL_DOWNLOADS);
+
+        Field::WriteId3v1.set(&mut settings, "on").unwrap();
+        assert!(settings.write_id3v1);
+        assert!(Field::WriteId3v1.set(&mut settings, "yes").is_err());
+        Field::Theme.set(&mut settings, "Monochrome").unwrap();
+        assert!(settings.theme == Theme::Monochrome);
+    }
+
+    #[test]
+    fn set_validates_text_fields() {
+        let mut settings = Settings::default();
+        Field::FilenamePatterns.set(&mut settings, "{artist}/{title}").unwrap();
+        assert_eq!(settings.filename_patterns, "{artist}/{title}");
+        assert!(Field::FilenamePatterns.set(&mut settings, "{artist}{title}").is_err());
+        assert!(Field::Provider.set(&mut settings, "discogs").is_err());
+        Field::Provider.set(&mut settings, "musicbrainz").unwrap();
+        Field::DownloadQuality.set(&mut settings, "192k").unwrap();
+        assert!(Field::DownloadQuality.set(&mut settings, "best").is_err());
+        assert_eq!(settings.download_quality, "192k");
+
+        Field::Key(Action::Quit).set(&mut settings, "x").unwrap();
+        let action = settings.keybindings.action(KeyCode::Char('x'));
+        assert_eq!(action.map(|action| action.to_string()).as_deref(), Some("Quit"));
+        assert!(Field::Key(Action::Quit).set(&mut settings, "escape").is_err());
+    }
+
+    #[test]
+    fn form_keeps_the_last_valid_value_and_flags_shared_keys() {
+        let mut form = SettingsForm::default();
+        let threads = index(Field::ScraperThreads);
+        form.push(threads, '4');
+        form.push(threads, 'x');
+        assert!(form.errors[threads].is_some());
+        assert_eq!(form.draft.scraper_threads, 4);
+        form.pop(threads);
+        form.pop(threads);
+        assert_eq!((form.errors[threads].as_deref(), form.draft.scraper_threads), (None, 0));
+
+        let pause = index(Field::Key(Action::Pause));
+        while !form.text[pause].is_empty() {
+            form.pop(pause);
+        }
+        form.push(pause, 'q');
+        assert_eq!(form.errors[pause].as_deref(), Some("Also bound to Quit"));
+        assert_eq!(form.errors[index(Field::Key(Action::Quit))].as_deref(), Some("Also bound to Pause"));
+        assert!(form.first_error().is_some_and(|(field, _)| field == Field::Key(Action::Quit)));
+
+        let developer_mode = index(Field::DeveloperMode);
+        form.push(developer_mode, ' ');
+        assert!(form.draft.developer_mode);
+        assert!(form.is_changed(&Settings::default()));
+    }
+
+    #[test]
+    fn form_opens_named_files_only_when_checking_them() {
+        let mut form = SettingsForm::default();
+        let provider = index(Field::Provider);
+        let missing = std::env::temp_dir().join(format!("mp3-cli-test-{}-missing-fixture.json", std::process::id()));
+        for c in format!("fixture:{}", missing.display()).chars() {
+            form.push(provider, c);
+        }
+        assert!(form.errors[provider].is_none());
+        assert_eq!(form.draft.provider, "");
+        form.check_files();
+        assert!(form.errors[provider].is_some());
+
+        // Editing the AcoustID key checks the provider against it again
+        let file = TempFile::new("form-fixture.json", br#"{ "recordings": [] }"#);
+        form.text[provider] = format!("fixture:{}", file.path().display());
+        form.push(index(Field::AcoustidKey), 'k');
+        form.check_files();
+        assert!(form.errors[provider].is_none());
+        assert_eq!(form.draft.provider, form.text[provider]);
+        assert!(form.first_error().is_none());
+    }
+
+    #[test]
+    fn form_clears_a_clash_once_one_key_moves() {
+        let mut form = SettingsForm::default();
+        let (pause, quit) = (index(Field::Key(Action::Pause)), index(Field::Key(Action::Quit)));
+        form.text[pause].clear();
+        form.push(pause, 'q');
+        assert!(form.errors[quit].is_some());
+        form.pop(pause);
+        form.push(pause, 'x');
+        assert!(form.errors[pause].is_none() && form.errors[quit].is_none());
+    }
+
+    #[test]
+    fn open_and_save_round_trip() {
+        let file = TempFile::new("settings.toml", b"scraper_threads = 3\n[keybindings]\nquit = \"x\"\n");
+        let mut settings = Settings::open(file.path().to_path_buf()).unwrap();
+        assert_eq!((settings.scraper_threads, settings.keybindings.quit.as_str()), (3, "x"));
+        assert_eq!(settings.keybindings.pause, "space");
+
+        settings.theme = Theme::Monochrome;
+        settings.save().unwrap();
+        let reopened = Settings::open(file.path().to_path_buf()).unwrap();
+        assert!(reopened == settings);
+
+        let broken = TempFile::new("broken-settings.toml", b"theme = \"monochrome\"\nscraper_threads = \"many\"\n");
+        let error = Settings::open(broken.path().to_path_buf()).err().unwrap();
+        assert!(error.to_string().starts_with("line 2:"), "{error}");
+    }
+}
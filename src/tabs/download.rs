@@ -1,12 +1,16 @@
+use std::rc::Rc;
+
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Rect},
     style::{Color, Style},
     text::Line,
-    widgets::{Block, Borders, Padding, Paragraph, Widget, Gauge},
+    widgets::{Block, Borders, Padding, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Gauge},
 };
-use crate::{App, tabs::tab_renderer::TabRenderer, AppMode};
+use crate::downloader::{console::Console, queue::ItemStatus};
+use crate::{App, tabs::{log_pane::LogPane, tab_renderer::TabRenderer}, AppMode, DOWNLOAD_LAST_FIELD};
 use ratatui::style::palette::tailwind::{RED, GRAY};
+use tui_term::widget::{Cursor, PseudoTerminal};
 
 pub struct DownloadTab;
 
@@ -26,7 +30,7 @@ impl DownloadTab {
 
         // Compute inner layout: Adding space for "Points to Note"
         let inner_chunks = Layout::vertical([
-            Constraint::Length(12), // Space for "Points to Note"
+            Constraint::Length(16), // Space for "Points to Note"
             Constraint::Length(3),  // URL input
             Constraint::Length(3),  // Output Path input
             Constraint::Length(3),  // Quality input
@@ -44,6 +48,10 @@ impl DownloadTab {
             Line::from(""),
             Line::from("2. Set a specific bitrate. In case of constraints, it will default to the available bitrate."),
             Line::from(""),
+            Line::from("3. Downloads run yt-dlp, which must be installed; another compatible tool can be set in Settings."),
+            Line::from(""),
+            Line::from("4. Spotify tracks are searched for by title, duration and ISRC; set a Spotify Source in Settings."),
+            Line::from(""),
         ];
 
         Paragraph::new(points_to_note)
@@ -69,7 +77,7 @@ impl DownloadTab {
                 Color::White
             };
 
-            Paragraph::new(format!("{}", value))
+            Paragraph::new(value.as_str())
                 .block(
                     Block::bordered()
                         .title(title)
@@ -95,24 +103,102 @@ impl DownloadTab {
             )
             .render(inner_chunks[4], buf);
 
-        // Right Column: Split into Logs and Progress Gauge
-        let right_column_chunks = Layout::vertical([
-            Constraint::Percentage(90),  // Space for Logs
-            Constraint::Percentage(10),  // Space for Progress Gauge
-        ])
-        .split(chunks[1]);
+        // Right Column: Split into Queue, Console, Logs and Progress Gauge
+        let right_column_chunks = Self::right_column(area);
+
+        let queue_highlight = if app.edit_selected_field == DOWNLOAD_LAST_FIELD && is_editing {
+            Color::Yellow
+        } else {
+            Color::White
+        };
+        let rows = app.download_queue.items.iter().map(|item| {
+            let status_color = match item.status {
+                ItemStatus::Queued | ItemStatus::Skipped => Color::DarkGray,
+                ItemStatus::Matching => Color::Yellow,
+                ItemStatus::Downloading => Color::Cyan,
+                ItemStatus::Done => Color::Green,
+                ItemStatus::Failed => Color::Red,
+            };
+            Row::new(vec![
+                item.name().to_string(),
+                item.status.to_string(),
+                item.progress.size.clone(),
+                format!("{:.1}%", item.progress.percent),
+            ])
+            .style(Style::default().fg(status_color))
+        });
+        let widths = [
+            Constraint::Percentage(55),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+        ];
+        let table = Table::new(rows, widths)
+            .header(Row::new(vec!["Title", "Status", "Size", "Progress"]))
+            .block(
+                Block::bordered()
+                    .title("Queue")
+                    .title_bottom("PgUp/PgDn to select | r to retry | s to skip")
+                    .border_style(queue_highlight),
+            )
+            .highlight_style(Style::default().fg(RED.c500).bg(GRAY.c800));
+        let mut table_state = TableState::default().with_selected(Some(app.download_selected));
+        StatefulWidget::render(table, right_column_chunks[0], buf, &mut table_state);
+
+        // Render the selected item's terminal, hiding the cursor once it's done
+        let selected = app.download_queue.items.get(app.download_selected);
+        let idle = Console::default();
+        let parser = selected.map_or(&idle, |item| &item.console).lock();
+        let screen = parser.screen();
+        let mut cursor = Cursor::default();
+        if !app.download_jobs.contains_key(&app.download_selected) || screen.scrollback() > 0 {
+            cursor.hide();
+        }
+        let mut title = match selected {
+            Some(item) => format!("Console: {}", item.name()),
+            None => "Console".to_string(),
+        };
+        if screen.scrollback() > 0 {
+            title.push_str(&format!(" ({} lines back)", screen.scrollback()));
+        }
+        PseudoTerminal::new(screen)
+            .cursor(cursor)
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .title_bottom("PgUp/PgDn to scroll | Ctrl-C to cancel"),
+            )
+            .render(right_column_chunks[1], buf);
 
         // Render Logs section
-        Paragraph::new(format!(
-            "URL: {}\nOutput Path: {}\nQuality: {}",
-            app.download_url, app.download_output, app.download_quality
-        ))
-        .block(
-            Block::bordered()
-                .title("Logs")
-                .padding(Padding::horizontal(1)),
-        )
-        .render(right_column_chunks[0], buf);
+        LogPane {
+            log: &app.download_log,
+            header: vec![
+                Line::from(format!("URL: {}", app.download_url)),
+                Line::from(format!("Output Path: {}", app.download_output)),
+                Line::from(format!("Quality: {}", app.download_quality)),
+            ],
+            help: "Tab to filter",
+        }
+        .render(right_column_chunks[2], buf);
+
+        // Overall progress, with the speed and time left of the selected download
+        let queue = &app.download_queue;
+        let percent = queue.percent();
+        let mut label = format!(
+            "{:.1}% | {}/{} done | {} failed",
+            percent,
+            queue.count(ItemStatus::Done),
+            queue.items.len() - queue.count(ItemStatus::Skipped),
+            queue.count(ItemStatus::Failed),
+        );
+        if let Some(item) = selected.filter(|item| item.status == ItemStatus::Downloading) {
+            for (name, value) in [("at", &item.progress.speed), ("ETA", &item.progress.eta)] {
+                if !value.is_empty() {
+                    label.push_str(&format!(" {name} {value}"));
+                }
+            }
+        }
 
         // Right Column: Progress Gauge (bottom section)
         let progress_gauge = Gauge::default()
@@ -122,7 +208,8 @@ impl DownloadTab {
                     .fg(RED.c500)
                     .bg(GRAY.c700),
             )
-            .percent(50); // Replace with app.download_progress if applicable
+            .percent(percent as u16)
+            .label(label);
 
         // Render empty paragraph for space before the gauge
         Paragraph::new("")
@@ -132,10 +219,32 @@ impl DownloadTab {
                     .borders(Borders::ALL)
                     .padding(Padding::horizontal(0)),
             )
-            .render(right_column_chunks[1], buf); // Render empty space before the gauge
+            .render(right_column_chunks[3], buf); // Render empty space before the gauge
 
         // Render the progress gauge
-        progress_gauge.render(right_column_chunks[1], buf);
+        progress_gauge.render(right_column_chunks[3], buf);
+    }
+}
+
+impl DownloadTab {
+    /// The right column of the tab: the queue, the console, the logs and the progress gauge.
+    fn right_column(area: Rect) -> Rc<[Rect]> {
+        let chunks = Layout::horizontal([Constraint::Percentage(25), Constraint::Percentage(75)]).split(area);
+        Layout::vertical([
+            Constraint::Percentage(25), // Space for the Queue
+            Constraint::Percentage(40), // Space for the Console
+            Constraint::Percentage(25), // Space for Logs
+            Constraint::Percentage(10), // Space for Progress Gauge
+        ])
+        .split(chunks[1])
+    }
+
+    /// Rows and columns inside the console when the whole terminal is `columns` x `rows`.
+    pub fn console_size(columns: u16, rows: u16) -> (u16, u16) {
+        // The tab sits between the header line and the now playing and footer lines
+        let area = Rect::new(0, 0, columns, rows.saturating_sub(3));
+        let console = Block::bordered().inner(Self::right_column(area)[1]);
+        (console.height.max(1), console.width.max(1))
     }
 }
 
@@ -2,29 +2,11 @@ use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Rect},
     style::{palette::material::WHITE, Color, Style},
-    widgets::{Block, Borders, Padding, Paragraph, Row, Table, TableState, Widget},
+    widgets::{Block, Borders, Padding, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
 };
 use crate::tabs::tab_renderer::TabRenderer;
 use crate::{App, AppMode};
-use ratatui::style::palette::tailwind::{ORANGE, BLUE, GRAY};
-use serde::{Deserialize};
-use serde_json::Result;
-use std::fs;
-use std::path::Path;
-
-#[derive(Deserialize, Clone)]
-struct Metadata {
-    file_name: String,
-    percentage: u16,
-    title: String,
-    artist: String,
-    album: String,
-    path: String,
-    year: u16,
-    genre: String,
-    track: u16,
-    status: String,
-}
+use ratatui::style::palette::tailwind::ORANGE;
 
 pub struct EditTab;
 
@@ -38,13 +20,11 @@ impl EditTab {
 
         // Left Column Block (Table)
         let left_column_block = Block::bordered()
-            .title("Metadata Table")
+            .title(format!("Metadata Table by {} (PgUp/PgDn to select a file, Tab to sort)", app.edit_sort))
+            .title_bottom(app.edit_status.as_str())
             .borders(Borders::ALL)
             .border_style(highlight_color);
 
-        // Load the metadata from the JSON file
-        let metadata = self.load_metadata_from_file().unwrap();
-
         // Define the column headers
         let headers = vec![
             "File Name", "Percentage", "Title", "Artist", "Album", "Path", 
@@ -52,7 +32,7 @@ impl EditTab {
         ];
 
         // Render Table with headers
-        let rows = metadata.iter().map(|item| {
+        let rows = app.library.entries.iter().map(|item| {
             Row::new(vec![
                 item.file_name.clone(),
                 item.percentage.to_string(),
@@ -86,14 +66,15 @@ impl EditTab {
             .block(left_column_block)
             .style(Style::default().fg(WHITE))
             .highlight_style(Style::default().fg(ORANGE.c500))
-            .widths(&widths);
+            .widths(widths);
 
-        // Render the table in the left column
-        table.render(chunks[0], buf);
+        // Render the table in the left column, highlighting the file being edited
+        let mut table_state = TableState::default().with_selected(Some(app.edit_selected_row));
+        StatefulWidget::render(table, chunks[0], buf, &mut table_state);
 
         // Right Column Block: Editable fields for selected row
         let right_column_block = Block::bordered()
-            .title("Edit Metadata")
+            .title("Edit Metadata (Ctrl+S to save)")
             .borders(Borders::ALL)
             .border_style(highlight_color);
 
@@ -145,7 +126,7 @@ impl EditTab {
                 Color::White
             };
 
-            Paragraph::new(format!("{}", value))
+            Paragraph::new(value.as_str())
                 .block(
                     Block::bordered()
                         .title(title)
@@ -155,31 +136,22 @@ impl EditTab {
                 .render(right_column_chunks[index], buf);
         }
 
-        // Render image placeholder (can be updated later with actual image rendering)
+        // The cover art, described rather than drawn
         let field_highlight = if app.edit_selected_field == 15 && is_editing {
             Color::Yellow
         } else {
             Color::White
         };
 
-        Paragraph::new("Image Placeholder")
+        Paragraph::new(app.edit_cover.as_str())
             .block(
                 Block::bordered()
-                    .title("Image")
+                    .title("Cover Art")
                     .border_style(field_highlight)
                     .padding(Padding::horizontal(1)),
             )
             .render(right_column_chunks[15], buf);
     }
-
-    fn load_metadata_from_file(&self) -> Result<Vec<Metadata>> {
-        let current_dir = std::env::current_dir().expect("Failed to get current directory");
-        let file_path = current_dir.join(r".\src\data.json"); // Adjust the file path
-
-        let data = fs::read_to_string(file_path).expect("Unable to read file");
-        let metadata: Vec<Metadata> = serde_json::from_str(&data)?;
-        Ok(metadata)
-    }
 }
 
 impl TabRenderer for EditTab {
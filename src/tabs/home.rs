@@ -59,7 +59,10 @@ impl HomeTab {
             Line::from("How to use this app:"),
             Line::from("- Use the ◄ and ► arrow keys to navigate between tabs."),
             Line::from("- Press 'q' or 'Esc' to quit the application."),
-            Line::from("- Press 'Space' to start/stop music playback."),
+            Line::from("- Press 'Space' to pause, 'n'/'p' to skip, '+'/'-' for volume and ','/'.' to seek from any tab."),
+            Line::from("- Change these keys, the theme and the download defaults in the Settings tab; press Ctrl-S to save them."),
+            Line::from("- In the Play tab, press 'a' to queue an artist, album or track and 'Enter' to play."),
+            Line::from("- Turn on Developer Mode in Settings to export the library index from the Scraper tab and press 'F12' for a debug overlay."),
             Line::from("- Follow on-screen instructions for each tab to perform actions."),
         ];
     
@@ -110,7 +113,7 @@ impl HomeTab {
     
         // Render the styled paragraph with wrapping
         Paragraph::new(styled_text)
-            .block(SelectedTab::Home.block().border_style(highlight_color))
+            .block(SelectedTab::Home.block(app.settings.theme).border_style(highlight_color))
             .wrap(Wrap { trim: true }) // Enable text wrapping
             .scroll((app.home_scroll, 0))
             .render(area, buf);
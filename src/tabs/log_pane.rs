@@ -0,0 +1,45 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Padding, Paragraph, Widget},
+};
+
+use crate::log::{Level, Log};
+
+/// The Logs pane shared by the tabs that run background jobs.
+pub struct LogPane<'a> {
+    pub log: &'a Log,
+    /// Lines shown above the log entries, e.g. the job's input.
+    pub header: Vec<Line<'a>>,
+    /// Keys the tab handles for the pane, shown under it.
+    pub help: &'a str,
+}
+
+impl Widget for LogPane<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title(format!("Logs [{}+]", self.log.filter.label()))
+            .title_bottom(self.help)
+            .padding(Padding::horizontal(1));
+
+        let height = (block.inner(area).height as usize).saturating_sub(self.header.len());
+        let mut lines = self.header;
+        lines.extend(self.log.visible(height).into_iter().map(|entry| {
+            let color = match entry.level {
+                Level::Debug => Color::DarkGray,
+                Level::Info => Color::White,
+                Level::Warn => Color::Yellow,
+                Level::Error => Color::Red,
+            };
+            Line::from(vec![
+                Span::raw(format!("{} ", entry.time)).dark_gray(),
+                Span::raw(format!("{:<5} ", entry.level.label())).fg(color),
+                Span::raw(entry.text.as_str()).fg(color),
+            ])
+        }));
+
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
@@ -3,6 +3,7 @@
 use strum::{Display, EnumIter, FromRepr};
 
 pub mod home;
+pub mod log_pane;
 pub mod scraper;
 pub mod download;
 pub mod edit;
@@ -1,30 +1,138 @@
+use std::time::Duration;
+
 use ratatui::layout::{Constraint, Layout};
-use ratatui::widgets::{Block, Borders, Widget};
+use ratatui::style::palette::tailwind::{GRAY, RED};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, ListState, Padding, StatefulWidget, Widget};
 use ratatui::{buffer::Buffer, layout::Rect, widgets::Paragraph};
+use crate::player::browser::Node;
 use crate::tabs::tab_renderer::TabRenderer;
-use crate::App;
+use crate::{App, AppMode, Metadata, PLAY_QUEUE_FIELD};
 
 pub struct PlayTab;
 
 impl TabRenderer for PlayTab {
     fn render(&mut self, area: Rect, buf: &mut Buffer, app: &App) {
-        // Split area into two equal columns
+        let is_editing = app.mode == AppMode::InsideTab;
+        let queue_focused = is_editing && app.edit_selected_field == PLAY_QUEUE_FIELD;
+        let tree_focused = is_editing && !queue_focused;
+        let border = |focused: bool| if focused { Color::Yellow } else { Color::White };
+
+        // Split area into two columns
         let chunks: [Rect; 2] = Layout::horizontal([
             Constraint::Percentage(30),
             Constraint::Percentage(70),
         ])
-        .areas(area)
-        .try_into()
-        .unwrap();
-
-        // Left column
-        Paragraph::new("Left Column Content")
-            .block(Block::default().borders(Borders::ALL).title("Left"))
-            .render(chunks[0], buf);
-
-        // Right column
-        Paragraph::new("Right Column Content")
-            .block(Block::default().borders(Borders::ALL).title("Right"))
-            .render(chunks[1], buf);
+        .areas(area);
+
+        // Left column: the library by artist, album and track
+        let rows = app.play_tree.rows();
+        let items = rows.iter().map(|row| {
+            let marker = match row.node {
+                Node::Track(_) => " ",
+                _ if row.expanded => "▾",
+                _ => "▸",
+            };
+            format!("{}{marker} {}", "  ".repeat(row.depth as usize), row.label)
+        });
+        let tree = List::new(items)
+            .block(
+                Block::bordered()
+                    .title("Library")
+                    .title_bottom("Enter to open | a to queue | ◄/► to fold")
+                    .border_style(border(tree_focused)),
+            )
+            .highlight_style(Style::default().fg(RED.c500).bg(GRAY.c800));
+        let mut tree_state = ListState::default().with_selected((!rows.is_empty()).then_some(app.play_tree.selected));
+        StatefulWidget::render(tree, chunks[0], buf, &mut tree_state);
+
+        // Right column: the play queue above what's playing
+        let [queue_area, now_playing_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(11)]).areas(chunks[1]);
+
+        let queue = &app.play_queue;
+        let items = queue.tracks.iter().enumerate().map(|(index, path)| {
+            let marker = if queue.current == Some(index) { "▶" } else { " " };
+            format!("{marker} {}", track_name(library_entry(app, path), path))
+        });
+        let queue_list = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(format!("Queue ({} tracks)", queue.tracks.len()))
+                    .title_bottom("Tab to switch | Enter to play | d to remove | [/] to move | n/p to skip")
+                    .border_style(border(queue_focused)),
+            )
+            .highlight_style(Style::default().fg(RED.c500).bg(GRAY.c800));
+        let mut queue_state = ListState::default().with_selected((!queue.tracks.is_empty()).then_some(app.play_selected));
+        StatefulWidget::render(queue_list, queue_area, buf, &mut queue_state);
+
+        let now_playing_block = Block::bordered()
+            .title("Now Playing")
+            .title_bottom("Space to pause | ,/. to seek | +/- volume | s to stop | z shuffle | r repeat")
+            .padding(Padding::horizontal(1));
+        let [details_area, gauge_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(now_playing_block.inner(now_playing_area));
+        now_playing_block.render(now_playing_area, buf);
+
+        let playback = &app.playback;
+        let mut details = match &playback.path {
+            Some(path) => {
+                let path = path.to_string_lossy();
+                // Files in the library are shown by their tags
+                let entry = library_entry(app, &path);
+                let state = if playback.paused { "Paused" } else { "Playing" };
+                vec![
+                    Line::from(format!("{state}: {}", track_name(entry, &path))),
+                    Line::from(format!(
+                        "Album: {}",
+                        entry.map_or("", |entry| entry.album.as_str())
+                    )),
+                ]
+            }
+            None => vec![Line::from("Stopped"), Line::from("")],
+        };
+        details.push(Line::from(format!(
+            "Volume: {}% | Shuffle: {} | Repeat: {}",
+            playback.volume,
+            if queue.shuffle { "On" } else { "Off" },
+            queue.repeat,
+        )));
+        if let Some(error) = &playback.error {
+            details.push(Line::from(error.as_str()).style(Style::default().fg(RED.c500)));
+        }
+        Paragraph::new(details).render(details_area, buf);
+
+        let duration = playback.duration.unwrap_or_default();
+        let ratio = if duration.is_zero() {
+            0.0
+        } else {
+            (playback.position.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0)
+        };
+        Gauge::default()
+            .block(Block::bordered())
+            .gauge_style(Style::default().fg(RED.c500).bg(GRAY.c700))
+            .ratio(ratio)
+            .label(format!("{} / {}", clock(playback.position), clock(duration)))
+            .render(gauge_area, buf);
+    }
+}
+
+pub fn library_entry<'a>(app: &'a App, path: &str) -> Option<&'a Metadata> {
+    app.library.entries.iter().find(|entry| entry.path == path)
+}
+
+/// "Artist - Title" from the tags, or the path when there are none.
+pub fn track_name(entry: Option<&Metadata>, path: &str) -> String {
+    match entry {
+        Some(entry) if !entry.title.is_empty() && !entry.artist.is_empty() => format!("{} - {}", entry.artist, entry.title),
+        Some(entry) if !entry.title.is_empty() => entry.title.clone(),
+        _ => path.to_string(),
     }
-}
\ No newline at end of file
+}
+
+/// A duration as minutes and seconds, e.g. "3:07".
+pub fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
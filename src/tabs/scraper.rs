@@ -5,7 +5,7 @@ use ratatui::{
     text::Line,
     widgets::{Block, Borders, Padding, Paragraph, Widget, Gauge},
 };
-use crate::{App, tabs::tab_renderer::TabRenderer, AppMode};
+use crate::{App, credits::TRIAL_CREDITS, tabs::{log_pane::LogPane, tab_renderer::TabRenderer}, AppMode, SCRAPER_EXPORT_FIELD};
 use ratatui::style::palette::tailwind::PURPLE;
 
 pub struct ScraperTab;
@@ -29,6 +29,8 @@ impl ScraperTab {
             Constraint::Length(15),  // Space for the new text
             Constraint::Length(3),   // Download Directory field
             Constraint::Length(3),   // Start Process button
+            Constraint::Length(3),   // Estimate Cost button
+            Constraint::Length(3),   // Export Index button, in developer mode
         ])
         .split(left_column_block.inner(chunks[0]));
 
@@ -37,11 +39,13 @@ impl ScraperTab {
 
         // Text before the Download Directory input
         let points_to_note = vec![
-            Line::from("1. Make sure to select a directory which contains Mp3 files only."),
+            Line::from("1. Make sure to select a directory which contains Mp3, M4a, Flac, Ogg or Opus files only."),
             Line::from("2. Mp3 files that contain incomplete Metadata will also be searched and indexed."),
-            Line::from("3. To download indexed database, kindly turn on Developer Settings in Settings, as this is turned off by default."),
+            Line::from("3. To export the indexed database as JSON, CSV or SQLite, turn on Developer Mode in Settings, as this is turned off by default."),
             Line::from("4. Make sure to configure the application, including number of threads to be used to hasten the indexing process."),
-            Line::from("5. Remember, the trial allows 100 Deep Searches only, kindly buy more credits to index more songs."),
+            Line::from(format!(
+                "5. Every file looked up online costs one Deep Search credit; the trial allows {TRIAL_CREDITS}. Estimate Cost shows what a directory needs, and license keys in Settings add more.",
+            )),
         ];
 
         Paragraph::new(points_to_note)
@@ -63,7 +67,7 @@ impl ScraperTab {
                 Color::White
             };
 
-            Paragraph::new(format!("{}", value))
+            Paragraph::new(value.as_str())
                 .block(
                     Block::bordered()
                         .title(title)
@@ -89,6 +93,41 @@ impl ScraperTab {
             )
             .render(inner_chunks[2], buf);
 
+        // Dry run button, counting the credits a scrape would cost
+        let estimate_highlight = if app.edit_selected_field == 2 && is_editing {
+            Color::Cyan
+        } else {
+            Color::White
+        };
+
+        Paragraph::new("Estimate Cost")
+            .block(
+                Block::bordered()
+                    .title("=")
+                    .border_style(estimate_highlight)
+                    .padding(Padding::horizontal(1)),
+            )
+            .render(inner_chunks[3], buf);
+
+        // Export of the whole index, for developers
+        if app.settings.developer_mode {
+            let export_highlight = if app.edit_selected_field == SCRAPER_EXPORT_FIELD && is_editing {
+                Color::Cyan
+            } else {
+                Color::White
+            };
+
+            Paragraph::new(format!("Export Index as {}", app.scraper_export_format))
+                .block(
+                    Block::bordered()
+                        .title("=")
+                        .title_bottom("f to change format")
+                        .border_style(export_highlight)
+                        .padding(Padding::horizontal(1)),
+                )
+                .render(inner_chunks[4], buf);
+        }
+
         // Right Column: Logs (top section)
         let right_column_chunks = Layout::vertical([
             Constraint::Percentage(90),  // Space for Logs
@@ -96,17 +135,26 @@ impl ScraperTab {
         ])
         .split(chunks[1]);
 
-        Paragraph::new(format!(
-            "Directory: {}\n",
-            app.scraper_directory
-        ))
-        .block(
-            Block::bordered()
-                .title("Logs")
-                .padding(Padding::horizontal(1)),
-        )
+        LogPane {
+            log: &app.scraper_log,
+            header: vec![
+                Line::from(format!("Directory: {}", app.scraper_directory)),
+                Line::from(format!("Deep Search credits left: {}", app.credits.balance())),
+            ],
+            help: "PgUp/PgDn to scroll | Tab to filter",
+        }
         .render(right_column_chunks[0], buf);
 
+        let progress = &app.scraper_progress;
+        let eta = match progress.eta() {
+            Some(eta) => format!("{}:{:02}", eta.as_secs() / 60, eta.as_secs() % 60),
+            None => "--:--".to_string(),
+        };
+        let label = format!(
+            "{}/{} processed | {} failed | ETA {eta}",
+            progress.processed, progress.found, progress.failed
+        );
+
         // Right Column: Progress Gauge (bottom section, inside its own Paragraph)
         let progress_gauge = Gauge::default()
             .block(Block::default().borders(Borders::ALL).title("Progress"))
@@ -115,7 +163,8 @@ impl ScraperTab {
                     .fg(tailwind::PURPLE.c500)
                     .bg(tailwind::GRAY.c700),
             )
-            .percent(15); //app.scraper_progress
+            .percent(progress.percent())
+            .label(label);
 
         Paragraph::new("")
             .block(
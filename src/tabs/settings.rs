@@ -1,15 +1,105 @@
-use ratatui::widgets::Widget;
-use ratatui::{buffer::Buffer, layout::Rect, widgets::Paragraph};
+use ratatui::style::palette::tailwind::{GRAY, RED};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Row, StatefulWidget, Table, TableState, Widget};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Color,
+    widgets::{Block, Padding, Paragraph},
+};
+use crate::settings::{Field, FIELDS};
 use crate::tabs::tab_renderer::TabRenderer;
 use crate::tabs::SelectedTab;
-use crate::App;
+use crate::{App, AppMode, SETTINGS_LAST_FIELD};
 
 pub struct SettingsTab;
 
 impl TabRenderer for SettingsTab {
     fn render(&mut self, area: Rect, buf: &mut Buffer, app: &App) {
-        Paragraph::new("Settings tab content goes here.")
-            .block(SelectedTab::Settings.block())
-            .render(area, buf);
+        let is_editing = app.mode == AppMode::InsideTab;
+        let block = SelectedTab::Settings.block(app.settings.theme);
+        let inner_chunks = Layout::vertical([
+            Constraint::Min(0),    // Settings table
+            Constraint::Length(4), // Help and problem of the selected setting
+            Constraint::Length(3), // License key file input
+            Constraint::Length(1), // Status
+        ])
+        .split(block.inner(area));
+        block.render(area, buf);
+
+        // One row per setting, red while it can't be saved
+        let form = &app.settings_form;
+        let rows = FIELDS.iter().enumerate().map(|(index, field)| {
+            let text = &form.text[index];
+            let value = match text.as_str() {
+                "" => Line::from(placeholder(*field)).fg(Color::DarkGray),
+                text => Line::from(text),
+            };
+            let style = match form.errors[index] {
+                Some(_) => Style::default().fg(RED.c400),
+                None => Style::default(),
+            };
+            Row::new(vec![Line::from(field.label()), value]).style(style)
+        });
+        let selected = (app.edit_selected_field < FIELDS.len()).then_some(app.edit_selected_field);
+        let highlight_style = if is_editing {
+            Style::default().fg(RED.c500).bg(GRAY.c800)
+        } else {
+            Style::default()
+        };
+        let table = Table::new(rows, [Constraint::Length(24), Constraint::Min(0)])
+            .header(Row::new(vec!["Setting", "Value"]).bold())
+            .highlight_style(highlight_style);
+        let mut table_state = TableState::default().with_selected(selected);
+        StatefulWidget::render(table, inner_chunks[0], buf, &mut table_state);
+
+        // What the selected setting does, and what's wrong with it
+        let (title, details) = match selected {
+            Some(index) => {
+                let mut details = vec![Line::from(FIELDS[index].help())];
+                if let Some(error) = &form.errors[index] {
+                    details.push(Line::from(error.as_str()).fg(RED.c400));
+                }
+                (FIELDS[index].label(), details)
+            }
+            None => ("License Key File".to_string(), vec![Line::from("Enter to redeem the file for Deep Search credits.")]),
+        };
+        Paragraph::new(details)
+            .block(Block::bordered().title(title).padding(Padding::horizontal(1)))
+            .wrap(ratatui::widgets::Wrap { trim: true })
+            .render(inner_chunks[1], buf);
+
+        let license_highlight = if app.edit_selected_field == SETTINGS_LAST_FIELD && is_editing {
+            Color::Yellow
+        } else {
+            Color::White
+        };
+        Paragraph::new(app.settings_license.as_str())
+            .block(
+                Block::bordered()
+                    .title("License Key File (Enter to redeem for Deep Search credits)")
+                    .border_style(license_highlight)
+                    .padding(Padding::horizontal(1)),
+            )
+            .render(inner_chunks[2], buf);
+
+        let status = match app.settings_status.as_str() {
+            "" if form.is_changed(&app.settings) => "Unsaved changes, Ctrl-S to save",
+            status => status,
+        };
+        Paragraph::new(status).render(inner_chunks[3], buf);
+    }
+}
+
+/// What an empty field stands for.
+fn placeholder(field: Field) -> String {
+    match field {
+        Field::ScraperThreads => format!("Auto ({})", std::thread::available_parallelism().map_or(1, |threads| threads.get())),
+        Field::Provider | Field::SpotifySource => "None".to_string(),
+        Field::Extractor => "yt-dlp".to_string(),
+        Field::DownloadDirectory => "Working directory".to_string(),
+        Field::DownloadQuality => "Best available".to_string(),
+        _ => String::new(),
     }
-}
\ No newline at end of file
+}
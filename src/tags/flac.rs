@@ -0,0 +1,243 @@
+// src/tags/flac.rs
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::tags::vorbis::Comments;
+use crate::tags::{id3v2, splice_file};
+use crate::Metadata;
+
+const STREAMINFO: u8 = 0;
+const PADDING: u8 = 1;
+const VORBIS_COMMENT: u8 = 4;
+
+/// Padding added when the metadata no longer fits and the file has to be rewritten.
+const DEFAULT_PADDING: usize = 1024;
+
+/// Largest block the 24-bit length in a metadata block header can describe.
+const MAX_BLOCK_LEN: usize = 0xFF_FFFF;
+
+struct Block {
+    kind: u8,
+    data: Vec<u8>,
+}
+
+/// The metadata blocks of a FLAC file and where they sit in it.
+struct FlacFile {
+    /// Offset of the "fLaC" marker; non-zero when an ID3v2 tag precedes it.
+    start: usize,
+    blocks: Vec<Block>,
+    /// Offset of the first audio frame.
+    audio_start: usize,
+}
+
+impl FlacFile {
+    fn read(path: &Path) -> io::Result<FlacFile> {
+        let mut file = File::open(path)?;
+
+        // Some taggers put an ID3v2 tag in front of the stream
+        let start = id3v2::Tag::read_from(&mut file)?.map_or(0, |tag| tag.size);
+        file.seek(SeekFrom::Start(start as u64))?;
+
+        let mut marker = [0u8; 4];
+        file.read_exact(&mut marker)?;
+        if &marker != b"fLaC" {
+            return Err(invalid("not a FLAC stream"));
+        }
+
+        let mut blocks = Vec::new();
+        let mut offset = start + 4;
+        loop {
+            let mut header = [0u8; 4];
+            file.read_exact(&mut header)?;
+            let is_last = header[0] & 0x80 != 0;
+            let kind = header[0] & 0x7F;
+            let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+
+            let mut data = vec![0u8; len];
+            file.read_exact(&mut data)?;
+            blocks.push(Block { kind, data });
+            offset += 4 + len;
+            if is_last {
+                break;
+            }
+        }
+
+        if blocks.first().map(|block| block.kind) != Some(STREAMINFO) {
+            return Err(invalid("missing STREAMINFO block"));
+        }
+
+        Ok(FlacFile { start, blocks, audio_start: offset })
+    }
+
+    fn comments(&self) -> io::Result<Option<Comments>> {
+        self.blocks
+            .iter()
+            .find(|block| block.kind == VORBIS_COMMENT)
+            .map(|block| Comments::parse(&block.data))
+            .transpose()
+    }
+}
+
+/// Read the Vorbis comments of the FLAC file at `path` into `metadata`.
+pub fn read_into(path: &Path, metadata: &mut Metadata) -> io::Result<()> {
+    if let Some(comments) = FlacFile::read(path)?.comments()? {
+        comments.apply_to(metadata);
+    }
+    Ok(())
+}
+
+/// Write `metadata` to the Vorbis comment block of the FLAC file at `path`.
+/// Padding blocks are shrunk or grown to absorb the size change, so the audio
+/// frames only move when the new comments don't fit.
+pub fn write(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    let flac = FlacFile::read(path)?;
+    let mut comments = flac.comments()?.unwrap_or_default();
+    comments.update_from(metadata);
+
+    let mut blocks = flac
+        .blocks
+        .into_iter()
+        .filter(|block| block.kind != PADDING && block.kind != VORBIS_COMMENT)
+        .collect::<Vec<_>>();
+    // Keep the comments right after STREAMINFO, where most readers look first
+    blocks.insert(1, Block { kind: VORBIS_COMMENT, data: comments.to_bytes() });
+
+    let available = flac.audio_start - flac.start - 4;
+    let needed = blocks.iter().map(|block| 4 + block.data.len()).sum::<usize>();
+
+    let padding = if available == needed {
+        None
+    } else if available >= needed + 4 {
+        Some(available - needed - 4)
+    } else {
+        Some(DEFAULT_PADDING)
+    };
+    if let Some(padding) = padding {
+        blocks.push(Block { kind: PADDING, data: vec![0; padding] });
+    }
+
+    if let Some(block) = blocks.iter().find(|block| block.data.len() > MAX_BLOCK_LEN) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("FLAC: a metadata block of {} bytes is larger than the format allows", block.data.len()),
+        ));
+    }
+
+    let mut header = Vec::with_capacity(needed + 4 + padding.unwrap_or(0));
+    header.extend_from_slice(b"fLaC");
+    let last = blocks.len() - 1;
+    for (index, block) in blocks.iter().enumerate() {
+        let flag = if index == last { 0x80 } else { 0 };
+        let len = (block.data.len() as u32).to_be_bytes();
+        header.push(flag | block.kind);
+        header.extend_from_slice(&len[1..]);
+        header.extend_from_slice(&block.data);
+    }
+
+    if flac.start + header.len() == flac.audio_start {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.seek(SeekFrom::Start(flac.start as u64))?;
+        file.write_all(&header)?;
+        return file.sync_all();
+    }
+
+    splice_file(path, flac.start as u64, flac.audio_start as u64, &header)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("FLAC: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    const AUDIO: &[u8] = b"\xFF\xF8 audio frames";
+    const APPLICATION: u8 = 2;
+
+    fn flac_bytes(blocks: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = b"fLaC".to_vec();
+        for (index, (kind, data)) in blocks.iter().enumerate() {
+            let flag = if index == blocks.len() - 1 { 0x80 } else { 0 };
+            bytes.push(flag | kind);
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+            bytes.extend_from_slice(data);
+        }
+        bytes.extend_from_slice(AUDIO);
+        bytes
+    }
+
+    fn metadata() -> Metadata {
+        Metadata {
+            title: "Title".to_string(),
+            artist: "Artist".to_string(),
+            year: 2010,
+            disc_no: 2,
+            ..Metadata::default()
+        }
+    }
+
+    fn read(path: &Path) -> Metadata {
+        let mut metadata = Metadata::default();
+        read_into(path, &mut metadata).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn write_uses_the_padding_in_place() {
+        let blocks = [(STREAMINFO, vec![0x11; 34]), (APPLICATION, b"appl".to_vec()), (PADDING, vec![0; 200])];
+        let file = TempFile::new("flac-in-place", &flac_bytes(&blocks));
+        let len = file.bytes().len();
+
+        write(file.path(), &metadata()).unwrap();
+        let bytes = file.bytes();
+        assert_eq!(bytes.len(), len);
+        assert!(bytes.ends_with(AUDIO));
+        let read_back = read(file.path());
+        assert_eq!((read_back.title.as_str(), read_back.artist.as_str()), ("Title", "Artist"));
+        assert_eq!((read_back.year, read_back.disc_no), (2010, 2));
+
+        let flac = FlacFile::read(file.path()).unwrap();
+        let kinds = flac.blocks.iter().map(|block| block.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [STREAMINFO, VORBIS_COMMENT, APPLICATION, PADDING]);
+        assert_eq!(flac.blocks[0].data, [0x11; 34]);
+        assert_eq!(flac.blocks[2].data, b"appl");
+    }
+
+    #[test]
+    fn write_moves_the_audio_when_the_comments_grow() {
+        // An empty ID3v2.4 tag with 16 bytes of padding in front of the stream
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x10".to_vec();
+        bytes.resize(26, 0);
+        let id3_len = bytes.len();
+        bytes.extend(flac_bytes(&[(STREAMINFO, vec![0x22; 34])]));
+        let file = TempFile::new("flac-grow", &bytes);
+
+        write(file.path(), &metadata()).unwrap();
+        let bytes = file.bytes();
+        assert!(bytes.ends_with(AUDIO));
+        assert_eq!(&bytes[id3_len..id3_len + 4], b"fLaC");
+        assert_eq!(read(file.path()).title, "Title");
+        let flac = FlacFile::read(file.path()).unwrap();
+        assert_eq!(flac.blocks.last().map(|block| (block.kind, block.data.len())), Some((PADDING, DEFAULT_PADDING)));
+    }
+
+    #[test]
+    fn oversized_blocks_are_refused() {
+        let file = TempFile::new("flac-oversized", &flac_bytes(&[(STREAMINFO, vec![0; 34])]));
+        let metadata = Metadata { comments: "x".repeat(MAX_BLOCK_LEN), ..Metadata::default() };
+
+        let error = write(file.path(), &metadata).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(file.bytes(), flac_bytes(&[(STREAMINFO, vec![0; 34])]));
+    }
+
+    #[test]
+    fn streams_without_streaminfo_are_rejected() {
+        let file = TempFile::new("flac-no-streaminfo", &flac_bytes(&[(PADDING, vec![0; 8])]));
+        assert_eq!(FlacFile::read(file.path()).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+}
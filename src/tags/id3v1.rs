@@ -0,0 +1,239 @@
+// src/tags/id3v1.rs
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::Metadata;
+
+/// Genre names indexed by the ID3v1 genre byte, including the Winamp extensions.
+pub const GENRES: [&str; 192] = [
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop",
+    "Jazz", "Metal", "New Age", "Oldies", "Other", "Pop", "R&B", "Rap",
+    "Reggae", "Rock", "Techno", "Industrial", "Alternative", "Ska", "Death Metal", "Pranks",
+    "Soundtrack", "Euro-Techno", "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk", "Fusion", "Trance",
+    "Classical", "Instrumental", "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise",
+    "AlternRock", "Bass", "Soul", "Punk", "Space", "Meditative", "Instrumental Pop", "Instrumental Rock",
+    "Ethnic", "Gothic", "Darkwave", "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream",
+    "Southern Rock", "Comedy", "Cult", "Gangsta", "Top 40", "Christian Rap", "Pop/Funk", "Jungle",
+    "Native American", "Cabaret", "New Wave", "Psychadelic", "Rave", "Showtunes", "Trailer", "Lo-Fi",
+    "Tribal", "Acid Punk", "Acid Jazz", "Polka", "Retro", "Musical", "Rock & Roll", "Hard Rock",
+    "Folk", "Folk-Rock", "National Folk", "Swing", "Fast Fusion", "Bebob", "Latin", "Revival",
+    "Celtic", "Bluegrass", "Avantgarde", "Gothic Rock", "Progressive Rock", "Psychedelic Rock", "Symphonic Rock", "Slow Rock",
+    "Big Band", "Chorus", "Easy Listening", "Acoustic", "Humour", "Speech", "Chanson", "Opera",
+    "Chamber Music", "Sonata", "Symphony", "Booty Bass", "Primus", "Porn Groove", "Satire", "Slow Jam",
+    "Club", "Tango", "Samba", "Folklore", "Ballad", "Power Ballad", "Rhythmic Soul", "Freestyle",
+    "Duet", "Punk Rock", "Drum Solo", "A capella", "Euro-House", "Dance Hall", "Goa", "Drum & Bass",
+    "Club-House", "Hardcore Techno", "Terror", "Indie", "BritPop", "Negerpunk", "Polsk Punk", "Beat",
+    "Christian Gangsta Rap", "Heavy Metal", "Black Metal", "Crossover", "Contemporary Christian", "Christian Rock", "Merengue", "Salsa",
+    "Thrash Metal", "Anime", "Jpop", "Synthpop", "Abstract", "Art Rock", "Baroque", "Bhangra",
+    "Big Beat", "Breakbeat", "Chillout", "Downtempo", "Dub", "EBM", "Eclectic", "Electro",
+    "Electroclash", "Emo", "Experimental", "Garage", "Global", "IDM", "Illbient", "Industro-Goth",
+    "Jam Band", "Krautrock", "Leftfield", "Lounge", "Math Rock", "New Romantic", "Nu-Breakz", "Post-Punk",
+    "Post-Rock", "Psytrance", "Shoegaze", "Space Rock", "Trop Rock", "World Music", "Neoclassical", "Audiobook",
+    "Audio Theatre", "Neue Deutsche Welle", "Podcast", "Indie Rock", "G-Funk", "Dubstep", "Garage Rock", "Psybient",
+];
+
+const TAG_SIZE: usize = 128;
+
+/// A 128-byte ID3v1 (or ID3v1.1, when `track` is set) trailer.
+#[derive(Clone, Default)]
+pub struct Tag {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    pub track: Option<u8>,
+    pub genre: u8,
+}
+
+/// Look up the name of an ID3v1 genre index.
+pub fn genre_name(index: u8) -> Option<&'static str> {
+    GENRES.get(index as usize).copied()
+}
+
+/// Look up the ID3v1 genre index of a genre name, ignoring case.
+pub fn genre_index(name: &str) -> Option<u8> {
+    GENRES
+        .iter()
+        .position(|genre| genre.eq_ignore_ascii_case(name.trim()))
+        .map(|index| index as u8)
+}
+
+impl Tag {
+    /// Read the ID3v1 trailer at the end of `path`, if there is one.
+    pub fn read_from_path(path: &Path) -> io::Result<Option<Tag>> {
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() < TAG_SIZE as u64 {
+            return Ok(None);
+        }
+
+        let mut bytes = [0u8; TAG_SIZE];
+        file.seek(SeekFrom::End(-(TAG_SIZE as i64)))?;
+        file.read_exact(&mut bytes)?;
+        Ok(Self::parse(&bytes))
+    }
+
+    fn parse(bytes: &[u8; TAG_SIZE]) -> Option<Tag> {
+        if &bytes[0..3] != b"TAG" {
+            return None;
+        }
+
+        // ID3v1.1 steals the last two comment bytes for a zero and the track number
+        let track = (bytes[125] == 0 && bytes[126] != 0).then_some(bytes[126]);
+        let comment_end = if track.is_some() { 125 } else { 127 };
+
+        Some(Tag {
+            title: decode_field(&bytes[3..33]),
+            artist: decode_field(&bytes[33..63]),
+            album: decode_field(&bytes[63..93]),
+            year: decode_field(&bytes[93..97]),
+            comment: decode_field(&bytes[97..comment_end]),
+            track,
+            genre: bytes[127],
+        })
+    }
+
+    /// Build a trailer from `metadata`, truncating fields that don't fit.
+    pub fn from_metadata(metadata: &Metadata) -> Tag {
+        Tag {
+            title: metadata.title.clone(),
+            artist: metadata.artist.clone(),
+            album: metadata.album.clone(),
+            year: if metadata.year == 0 { String::new() } else { metadata.year.to_string() },
+            comment: metadata.comments.clone(),
+            track: u8::try_from(metadata.track).ok().filter(|&track| track != 0),
+            genre: genre_index(&metadata.genre).unwrap_or(255),
+        }
+    }
+
+    /// Fill the fields of `metadata` that are still empty. ID3v1 is only ever
+    /// a fallback for what the richer tag formats didn't provide.
+    pub fn apply_to(&self, metadata: &mut Metadata) {
+        fill(&mut metadata.title, &self.title);
+        fill(&mut metadata.artist, &self.artist);
+        fill(&mut metadata.album, &self.album);
+        fill(&mut metadata.comments, &self.comment);
+        if let Some(genre) = genre_name(self.genre) {
+            fill(&mut metadata.genre, genre);
+        }
+        if metadata.year == 0 {
+            metadata.year = self.year.trim().parse().unwrap_or(0);
+        }
+        if metadata.track == 0 {
+            metadata.track = self.track.unwrap_or(0) as u16;
+        }
+    }
+
+    fn to_bytes(&self) -> [u8; TAG_SIZE] {
+        let mut bytes = [0u8; TAG_SIZE];
+        bytes[0..3].copy_from_slice(b"TAG");
+        encode_field(&mut bytes[3..33], &self.title);
+        encode_field(&mut bytes[33..63], &self.artist);
+        encode_field(&mut bytes[63..93], &self.album);
+        encode_field(&mut bytes[93..97], &self.year);
+        match self.track {
+            Some(track) => {
+                encode_field(&mut bytes[97..125], &self.comment);
+                bytes[126] = track;
+            }
+            None => encode_field(&mut bytes[97..127], &self.comment),
+        }
+        bytes[127] = self.genre;
+        bytes
+    }
+
+    /// Write this trailer to the end of `path`, replacing an existing one.
+    pub fn write_to_path(&self, path: &Path) -> io::Result<()> {
+        let has_tag = Self::read_from_path(path)?.is_some();
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        if has_tag {
+            file.seek(SeekFrom::End(-(TAG_SIZE as i64)))?;
+        } else {
+            file.seek(SeekFrom::End(0))?;
+        }
+        file.write_all(&self.to_bytes())?;
+        file.sync_all()
+    }
+}
+
+fn fill(field: &mut String, value: &str) {
+    if field.is_empty() && !value.is_empty() {
+        *field = value.to_string();
+    }
+}
+
+/// ID3v1 fields are ISO-8859-1, padded with zeros or (by some taggers) spaces.
+fn decode_field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    bytes[..end]
+        .iter()
+        .map(|&b| b as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn encode_field(field: &mut [u8], value: &str) {
+    let latin1 = value.chars().map(|c| u8::try_from(c as u32).unwrap_or(b'?'));
+    for (slot, byte) in field.iter_mut().zip(latin1) {
+        *slot = byte;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    #[test]
+    fn round_trip_truncates_to_the_field_widths() {
+        let metadata = Metadata {
+            title: "A title that is far too long for thirty bytes".to_string(),
+            artist: "Motörhead".to_string(),
+            year: 1980,
+            track: 7,
+            genre: "heavy metal".to_string(),
+            comments: "Comment".to_string(),
+            ..Metadata::default()
+        };
+        let tag = Tag::parse(&Tag::from_metadata(&metadata).to_bytes()).unwrap();
+        assert_eq!(tag.title, "A title that is far too long f");
+        assert_eq!(tag.artist, "Motörhead");
+        assert_eq!(tag.year, "1980");
+        assert_eq!(tag.track, Some(7));
+        assert_eq!(genre_name(tag.genre), Some("Heavy Metal"));
+        assert_eq!(tag.comment, "Comment");
+    }
+
+    #[test]
+    fn write_appends_once_then_replaces() {
+        let file = TempFile::new("id3v1-write", b"audio");
+        let mut tag = Tag { title: "First".to_string(), genre: 255, ..Tag::default() };
+        tag.write_to_path(file.path()).unwrap();
+        tag.title = "Second".to_string();
+        tag.write_to_path(file.path()).unwrap();
+
+        let bytes = file.bytes();
+        assert_eq!(bytes.len(), 5 + TAG_SIZE);
+        assert!(bytes.starts_with(b"audio"));
+        let read = Tag::read_from_path(file.path()).unwrap().unwrap();
+        assert_eq!((read.title.as_str(), read.track), ("Second", None));
+    }
+
+    #[test]
+    fn apply_to_only_fills_empty_fields() {
+        let tag = Tag {
+            title: "Title".to_string(),
+            artist: "Artist".to_string(),
+            year: "1999".to_string(),
+            genre: 17,
+            ..Tag::default()
+        };
+        let mut metadata = Metadata { artist: "Kept".to_string(), ..Metadata::default() };
+        tag.apply_to(&mut metadata);
+        assert_eq!((metadata.title.as_str(), metadata.artist.as_str()), ("Title", "Kept"));
+        assert_eq!((metadata.year, metadata.genre.as_str()), (1999, "Rock"));
+    }
+}
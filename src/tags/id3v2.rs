@@ -0,0 +1,918 @@
+// src/tags/id3v2.rs
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::tags::{id3v1, leading_number, splice_file, with_number, Picture};
+use crate::Metadata;
+
+/// A single frame as found in the tag. The payload has already been
+/// stripped of unsynchronisation, grouping bytes and data length indicators,
+/// unless the frame is compressed or encrypted (see `is_raw`).
+#[derive(Clone)]
+pub struct Frame {
+    pub id: String,
+    /// Frame flags in the ID3v2.4 layout: the status flags (tag/file alter
+    /// preservation, read only), and for raw frames the format flags as well.
+    pub flags: u16,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// Whether the frame is compressed or encrypted. We can't decode those, so
+    /// `data` is kept exactly as stored, extra header bytes included, and
+    /// written back unchanged.
+    pub fn is_raw(&self) -> bool {
+        self.flags & 0x000C != 0
+    }
+}
+
+/// An ID3v2 tag read from the start of a file.
+#[derive(Clone, Default)]
+pub struct Tag {
+    pub frames: Vec<Frame>,
+    /// Number of bytes the tag occupies on disk, header and footer included.
+    pub size: usize,
+}
+
+/// `APIC` picture type of the front cover.
+const FRONT_COVER: u8 = 3;
+
+/// Padding added when the tag no longer fits and the file has to be rewritten,
+/// so that the next few edits can be saved in place.
+const DEFAULT_PADDING: usize = 1024;
+
+/// Frames that ID3v2.4 dropped. `TDRC` replaces the date/time ones and is
+/// written from `Metadata::year`.
+const V24_REMOVED_FRAMES: [&str; 5] = ["TYER", "TDAT", "TIME", "TRDA", "TSIZ"];
+
+/// ID3v2.2 uses three character frame IDs; map them onto their ID3v2.3/2.4
+/// equivalents so the rest of the code only deals with one set. `PIC` also
+/// needs its payload converted, see `parse_frames`. The encrypted meta frame
+/// `CRM` has no later equivalent.
+const V22_FRAME_IDS: [(&str, &str); 62] = [
+    ("BUF", "RBUF"),
+    ("CNT", "PCNT"),
+    ("COM", "COMM"),
+    ("CRA", "AENC"),
+    ("ETC", "ETCO"),
+    ("EQU", "EQUA"),
+    ("GEO", "GEOB"),
+    ("IPL", "IPLS"),
+    ("LNK", "LINK"),
+    ("MCI", "MCDI"),
+    ("MLL", "MLLT"),
+    ("PIC", "APIC"),
+    ("POP", "POPM"),
+    ("REV", "RVRB"),
+    ("RVA", "RVAD"),
+    ("SLT", "SYLT"),
+    ("STC", "SYTC"),
+    ("TAL", "TALB"),
+    ("TBP", "TBPM"),
+    ("TCM", "TCOM"),
+    ("TCO", "TCON"),
+    ("TCR", "TCOP"),
+    ("TDA", "TDAT"),
+    ("TDY", "TDLY"),
+    ("TEN", "TENC"),
+    ("TFT", "TFLT"),
+    ("TIM", "TIME"),
+    ("TKE", "TKEY"),
+    ("TLA", "TLAN"),
+    ("TLE", "TLEN"),
+    ("TMT", "TMED"),
+    ("TOA", "TOPE"),
+    ("TOF", "TOFN"),
+    ("TOL", "TOLY"),
+    ("TOR", "TORY"),
+    ("TOT", "TOAL"),
+    ("TP1", "TPE1"),
+    ("TP2", "TPE2"),
+    ("TP3", "TPE3"),
+    ("TP4", "TPE4"),
+    ("TPA", "TPOS"),
+    ("TPB", "TPUB"),
+    ("TRC", "TSRC"),
+    ("TRD", "TRDA"),
+    ("TRK", "TRCK"),
+    ("TSI", "TSIZ"),
+    ("TSS", "TSSE"),
+    ("TT1", "TIT1"),
+    ("TT2", "TIT2"),
+    ("TT3", "TIT3"),
+    ("TXT", "TEXT"),
+    ("TXX", "TXXX"),
+    ("TYE", "TYER"),
+    ("UFI", "UFID"),
+    ("ULT", "USLT"),
+    ("WAF", "WOAF"),
+    ("WAR", "WOAR"),
+    ("WAS", "WOAS"),
+    ("WCM", "WCOM"),
+    ("WCP", "WCOP"),
+    ("WPB", "WPUB"),
+    ("WXX", "WXXX"),
+];
+
+impl Tag {
+    /// Read the ID3v2 tag at the start of `path`, if there is one.
+    pub fn read_from_path(path: &Path) -> io::Result<Option<Tag>> {
+        let mut file = File::open(path)?;
+        Self::read_from(&mut file)
+    }
+
+    /// Read an ID3v2 tag from the current position of `reader`.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Tag>> {
+        let mut header = [0u8; 10];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        if &header[0..3] != b"ID3" || !(2..=4).contains(&header[3]) || header[4] == 0xFF {
+            return Ok(None);
+        }
+
+        let major = header[3];
+        let flags = header[5];
+        let body_size = synchsafe(&header[6..10]) as usize;
+
+        let mut body = vec![0u8; body_size];
+        reader.read_exact(&mut body)?;
+
+        let has_footer = major == 4 && flags & 0x10 != 0;
+        let size = 10 + body_size + if has_footer { 10 } else { 0 };
+
+        let tag_unsync = flags & 0x80 != 0;
+        if tag_unsync && major < 4 {
+            body = remove_unsynchronisation(&body);
+        }
+
+        // ID3v2.2 used this bit for a compression scheme that was never defined
+        if major == 2 && flags & 0x40 != 0 {
+            return Ok(Some(Tag { frames: Vec::new(), size }));
+        }
+
+        let mut offset = 0;
+        if major > 2 && flags & 0x40 != 0 {
+            offset = match major {
+                3 => 4 + read_u32(&body, 0)? as usize,
+                _ => synchsafe(body.get(0..4).ok_or_else(truncated)?) as usize,
+            };
+        }
+
+        let frames = parse_frames(&body, offset, major, tag_unsync);
+        Ok(Some(Tag { frames, size }))
+    }
+
+    /// Return the first frame with the given ID that we can decode.
+    pub fn frame(&self, id: &str) -> Option<&Frame> {
+        self.frames.iter().find(|frame| frame.id == id && !frame.is_raw())
+    }
+
+    /// Return the first value of a text frame.
+    pub fn text(&self, id: &str) -> Option<String> {
+        self.frame(id)
+            .and_then(|frame| decode_text_frame(&frame.data).into_iter().next())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Return the comment text, preferring a comment without a description
+    /// (descriptions are mostly used by players for private data such as iTunNORM).
+    pub fn comment(&self) -> Option<String> {
+        let comments = self
+            .frames
+            .iter()
+            .filter(|frame| frame.id == "COMM" && !frame.is_raw())
+            .filter_map(|frame| decode_comment_frame(&frame.data))
+            .collect::<Vec<_>>();
+
+        comments
+            .iter()
+            .find(|(description, _)| description.is_empty())
+            .or_else(|| comments.iter().find(|(description, _)| !description.starts_with("iTun")))
+            .map(|(_, text)| text.clone())
+            .filter(|text| !text.is_empty())
+    }
+
+    /// The image data of the front cover, or else of the first picture.
+    pub fn front_cover(&self) -> Option<&[u8]> {
+        let pictures = self.frames.iter().filter(|frame| frame.id == "APIC" && !frame.is_raw());
+        let mut pictures = pictures.map(|frame| &frame.data).collect::<Vec<_>>();
+        if let Some(front) = pictures.iter().position(|data| picture_type(data) == Some(FRONT_COVER)) {
+            pictures.swap(0, front);
+        }
+        // The description follows the MIME type and picture type, terminated in the frame's encoding
+        let data = pictures.first()?;
+        let mime_len = data.get(1..)?.iter().position(|&b| b == 0)?;
+        let (_, image) = split_once_terminated(data[0], data.get(mime_len + 3..)?);
+        Some(image)
+    }
+
+    /// Copy every field this tag carries into `metadata`, leaving the others untouched.
+    pub fn apply_to(&self, metadata: &mut Metadata) {
+        if let Some(title) = self.text("TIT2") {
+            metadata.title = title;
+        }
+        if let Some(artist) = self.text("TPE1") {
+            metadata.artist = artist;
+        }
+        if let Some(album) = self.text("TALB") {
+            metadata.album = album;
+        }
+        if let Some(year) = self.text("TDRC").or_else(|| self.text("TYER")) {
+            metadata.year = leading_number(&year);
+        }
+        if let Some(genre) = self.text("TCON") {
+            metadata.genre = resolve_genre(&genre);
+        }
+        if let Some(track) = self.text("TRCK") {
+            metadata.track = leading_number(&track);
+        }
+        if let Some(album_artist) = self.text("TPE2") {
+            metadata.album_artist = album_artist;
+        }
+        if let Some(composer) = self.text("TCOM") {
+            metadata.composer = composer;
+        }
+        if let Some(disc_no) = self.text("TPOS") {
+            metadata.disc_no = leading_number(&disc_no);
+        }
+        if let Some(comments) = self.comment() {
+            metadata.comments = comments;
+        }
+    }
+
+    /// Replace the frames backing `Metadata` with the values in `metadata`.
+    /// Frames we don't map onto a field are kept as they are.
+    pub fn update_from(&mut self, metadata: &Metadata) {
+        // Read before the ID3v2.3 date frames are dropped, so a full date survives the move to TDRC
+        let year = self.text("TDRC").or_else(|| self.text("TYER"));
+        let track = self.text("TRCK");
+        let disc_no = self.text("TPOS");
+
+        // ID3v2.2 frames we could not translate have no ID3v2.4 representation
+        self.frames.retain(|frame| frame.id.len() == 4 && !V24_REMOVED_FRAMES.contains(&frame.id.as_str()));
+        for frame in self.frames.iter_mut().filter(|frame| frame.id == "TORY") {
+            frame.id = "TDOR".to_string();
+        }
+
+        self.set_text("TIT2", &metadata.title);
+        self.set_text("TPE1", &metadata.artist);
+        self.set_text("TALB", &metadata.album);
+        self.set_text("TDRC", &with_number(year.as_deref(), metadata.year));
+        self.set_text("TCON", &metadata.genre);
+        self.set_text("TRCK", &with_number(track.as_deref(), metadata.track));
+        self.set_text("TPE2", &metadata.album_artist);
+        self.set_text("TCOM", &metadata.composer);
+        self.set_text("TPOS", &with_number(disc_no.as_deref(), metadata.disc_no));
+        self.set_comment(&metadata.comments);
+    }
+
+    /// Set a text frame, or remove it when `value` is empty. A compressed or
+    /// encrypted frame is only replaced by a new value, as an empty one may
+    /// just mean we couldn't read it.
+    pub fn set_text(&mut self, id: &str, value: &str) {
+        if !value.is_empty() {
+            self.frames.retain(|frame| frame.id != id || !frame.is_raw());
+        }
+        let data = (!value.is_empty()).then(|| encode_text_frame(value));
+        self.replace_frame(id, data, |_| true);
+    }
+
+    /// Set the comment without a description, leaving described comments alone.
+    pub fn set_comment(&mut self, text: &str) {
+        let data = (!text.is_empty()).then(|| {
+            let mut data = vec![3];
+            data.extend_from_slice(b"eng");
+            data.push(0);
+            data.extend_from_slice(text.as_bytes());
+            data
+        });
+        self.replace_frame("COMM", data, |frame| {
+            decode_comment_frame(&frame.data).is_none_or(|(description, _)| description.is_empty())
+        });
+    }
+
+    /// Set the front cover, replacing any other front cover picture.
+    pub fn set_front_cover(&mut self, picture: &Picture) {
+        // UTF-8 encoding, the MIME type, picture type 3 (front cover) and an empty description
+        let mut data = vec![3];
+        data.extend_from_slice(picture.mime.as_bytes());
+        data.extend_from_slice(&[0, FRONT_COVER, 0]);
+        data.extend_from_slice(&picture.data);
+        self.replace_frame("APIC", Some(data), |frame| picture_type(&frame.data) == Some(FRONT_COVER));
+    }
+
+    /// Replace the first frame with `id` matching `filter` in place (to keep the
+    /// frame order stable), dropping any further matches. Raw frames never match.
+    fn replace_frame(&mut self, id: &str, data: Option<Vec<u8>>, filter: impl Fn(&Frame) -> bool) {
+        let matches = |frame: &Frame| frame.id == id && !frame.is_raw() && filter(frame);
+        let position = self.frames.iter().position(matches);
+        let mut index = 0;
+        self.frames.retain(|frame| {
+            let keep = Some(index) == position || !matches(frame);
+            index += 1;
+            keep
+        });
+
+        match (position, data) {
+            (Some(position), Some(data)) => {
+                self.frames[position].data = data;
+            }
+            (Some(position), None) => {
+                self.frames.remove(position);
+            }
+            (None, Some(data)) => self.frames.push(Frame { id: id.to_string(), flags: 0, data }),
+            (None, None) => {}
+        }
+    }
+
+    /// Serialize the frames as an ID3v2.4 frame area, without header or padding.
+    fn frames_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for frame in &self.frames {
+            bytes.extend_from_slice(frame.id.as_bytes());
+            bytes.extend_from_slice(&to_synchsafe(frame.data.len() as u32));
+            bytes.extend_from_slice(&frame.flags.to_be_bytes());
+            bytes.extend_from_slice(&frame.data);
+        }
+        bytes
+    }
+
+    /// Write this tag to the start of `path` as ID3v2.4, replacing whatever
+    /// ID3v2 tag is already there. When the new tag fits in the space of the
+    /// old one it is written in place, so the audio data is never touched.
+    pub fn write_to_path(&self, path: &Path) -> io::Result<()> {
+        let frames = self.frames_to_bytes();
+        let existing_size = Tag::read_from_path(path)?.map_or(0, |tag| tag.size);
+
+        if existing_size >= frames.len() + 10 && existing_size > 10 {
+            let mut file = OpenOptions::new().write(true).open(path)?;
+            file.write_all(&tag_bytes(&frames, existing_size - 10))?;
+            return file.sync_all();
+        }
+
+        // The tag grew, so the audio has to move
+        splice_file(path, 0, existing_size as u64, &tag_bytes(&frames, frames.len() + DEFAULT_PADDING))
+    }
+}
+
+/// Build a complete ID3v2.4 tag with a body of `body_size` bytes, the frames
+/// followed by zero padding.
+fn tag_bytes(frames: &[u8], body_size: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(body_size + 10);
+    bytes.extend_from_slice(b"ID3");
+    bytes.extend_from_slice(&[4, 0, 0]);
+    bytes.extend_from_slice(&to_synchsafe(body_size as u32));
+    bytes.extend_from_slice(frames);
+    bytes.resize(body_size + 10, 0);
+    bytes
+}
+
+/// Turn the ID3v1 genre references `TCON` may contain ("(17)", "17" or
+/// "(17)Rock") into a plain genre name.
+fn resolve_genre(value: &str) -> String {
+    let value = value.trim();
+    if let Ok(index) = value.parse::<u8>() {
+        return id3v1::genre_name(index).unwrap_or(value).to_string();
+    }
+
+    let Some(reference) = value.strip_prefix('(') else {
+        return value.to_string();
+    };
+    let Some((reference, refinement)) = reference.split_once(')') else {
+        return value.to_string();
+    };
+    if !refinement.is_empty() {
+        return refinement.to_string();
+    }
+
+    match reference {
+        "RX" => "Remix".to_string(),
+        "CR" => "Cover".to_string(),
+        _ => reference
+            .parse::<u8>()
+            .ok()
+            .and_then(id3v1::genre_name)
+            .unwrap_or(value)
+            .to_string(),
+    }
+}
+
+/// The picture type of an `APIC` frame payload, which follows the encoding byte and MIME type.
+fn picture_type(data: &[u8]) -> Option<u8> {
+    let mime_len = data.get(1..)?.iter().position(|&b| b == 0)?;
+    data.get(mime_len + 2).copied()
+}
+
+/// Encode a text frame payload as UTF-8.
+pub fn encode_text_frame(value: &str) -> Vec<u8> {
+    let mut data = vec![3];
+    data.extend_from_slice(value.as_bytes());
+    data
+}
+
+/// Parse the frame area of a tag of version `major`, starting at `offset`,
+/// up to the padding. ID3v2.2 frames get their ID3v2.3/2.4 IDs, and flags are
+/// translated to the ID3v2.4 layout.
+fn parse_frames(body: &[u8], mut offset: usize, major: u8, tag_unsync: bool) -> Vec<Frame> {
+    let header_len = if major == 2 { 6 } else { 10 };
+    let id_len = if major == 2 { 3 } else { 4 };
+    let mut frames = Vec::new();
+
+    while offset + header_len <= body.len() {
+        let raw_id = &body[offset..offset + id_len];
+        if !raw_id.iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+            // Reached the padding, or garbage we can't make sense of
+            break;
+        }
+        let id = String::from_utf8_lossy(raw_id).into_owned();
+
+        let (size, raw_flags) = match major {
+            2 => (
+                u32::from_be_bytes([0, body[offset + 3], body[offset + 4], body[offset + 5]]) as usize,
+                0,
+            ),
+            3 => (
+                u32::from_be_bytes([body[offset + 4], body[offset + 5], body[offset + 6], body[offset + 7]]) as usize,
+                u16::from_be_bytes([body[offset + 8], body[offset + 9]]),
+            ),
+            _ => (
+                synchsafe(&body[offset + 4..offset + 8]) as usize,
+                u16::from_be_bytes([body[offset + 8], body[offset + 9]]),
+            ),
+        };
+
+        offset += header_len;
+        if offset + size > body.len() {
+            break;
+        }
+        let mut data = &body[offset..offset + size];
+        offset += size;
+
+        let (flags, compressed_or_encrypted, grouped, unsync, has_length) = match major {
+            2 => (0, false, false, false, false),
+            3 => (
+                (raw_flags & 0xE000) >> 1,
+                raw_flags & 0x00C0 != 0,
+                raw_flags & 0x0020 != 0,
+                false,
+                false,
+            ),
+            _ => (
+                raw_flags & 0x7000,
+                raw_flags & 0x000C != 0,
+                raw_flags & 0x0040 != 0,
+                raw_flags & 0x0002 != 0 || tag_unsync,
+                raw_flags & 0x0001 != 0,
+            ),
+        };
+
+        // We neither decompress nor decrypt, so these frames are kept as they are
+        if compressed_or_encrypted {
+            let raw = match major {
+                3 => raw_v23_frame(raw_flags, data),
+                // Tag wide unsynchronisation isn't written back, so it moves to the frame
+                _ => Some((raw_flags & 0x704F | if tag_unsync { 0x0002 } else { 0 }, data.to_vec())),
+            };
+            if let Some((flags, data)) = raw {
+                frames.push(Frame { id, flags, data });
+            }
+            continue;
+        }
+        if grouped {
+            data = data.get(1..).unwrap_or_default();
+        }
+        if has_length {
+            data = data.get(4..).unwrap_or_default();
+        }
+        let data = if unsync {
+            remove_unsynchronisation(data)
+        } else {
+            data.to_vec()
+        };
+
+        if major == 2 {
+            // Frames without a later equivalent can't be written back as ID3v2.4
+            let Some((_, new_id)) = V22_FRAME_IDS.iter().find(|(old, _)| *old == id) else {
+                continue;
+            };
+            let data = if id == "PIC" { v22_picture_to_apic(&data) } else { data };
+            frames.push(Frame { id: new_id.to_string(), flags, data });
+        } else {
+            frames.push(Frame { id, flags, data });
+        }
+    }
+
+    frames
+}
+
+/// Reorder the extra header bytes of a compressed or encrypted ID3v2.3 frame
+/// (decompressed size, encryption method, group) into the ID3v2.4 order
+/// (group, encryption method, data length indicator) and translate its flags
+/// to match, so it can be written back without being decoded.
+fn raw_v23_frame(raw_flags: u16, data: &[u8]) -> Option<(u16, Vec<u8>)> {
+    let mut flags = (raw_flags & 0xE000) >> 1;
+    let mut rest = data;
+    let mut length = None;
+    if raw_flags & 0x0080 != 0 {
+        let (size, tail) = rest.split_at_checked(4)?;
+        length = Some(u32::from_be_bytes([size[0], size[1], size[2], size[3]]));
+        rest = tail;
+    }
+    let mut method = None;
+    if raw_flags & 0x0040 != 0 {
+        let (byte, tail) = rest.split_first()?;
+        method = Some(*byte);
+        rest = tail;
+    }
+    let mut group = None;
+    if raw_flags & 0x0020 != 0 {
+        let (byte, tail) = rest.split_first()?;
+        group = Some(*byte);
+        rest = tail;
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() + 1);
+    if let Some(group) = group {
+        flags |= 0x0040;
+        bytes.push(group);
+    }
+    if let Some(method) = method {
+        flags |= 0x0004;
+        bytes.push(method);
+    }
+    if let Some(length) = length {
+        // Compression requires a data length indicator in ID3v2.4
+        flags |= 0x0009;
+        bytes.extend_from_slice(&to_synchsafe(length));
+    }
+    bytes.extend_from_slice(rest);
+    Some((flags, bytes))
+}
+
+/// Turn an ID3v2.2 `PIC` payload, which names its image format in three
+/// characters, into an `APIC` one with a MIME type.
+fn v22_picture_to_apic(data: &[u8]) -> Vec<u8> {
+    let Some((format, rest)) = data.get(1..).and_then(|data| data.split_at_checked(3)) else {
+        return data.to_vec();
+    };
+    let mime = match format.to_ascii_uppercase().as_slice() {
+        b"JPG" => "image/jpeg".to_string(),
+        b"PNG" => "image/png".to_string(),
+        format => format!("image/{}", String::from_utf8_lossy(format).to_ascii_lowercase()),
+    };
+    let mut apic = vec![data[0]];
+    apic.extend_from_slice(mime.as_bytes());
+    apic.push(0);
+    apic.extend_from_slice(rest);
+    apic
+}
+
+/// Decode the payload of a text frame (`T***`) into its values. ID3v2.4 allows
+/// several values separated by a null character.
+pub fn decode_text_frame(data: &[u8]) -> Vec<String> {
+    let Some((&encoding, text)) = data.split_first() else {
+        return Vec::new();
+    };
+
+    let mut values = split_terminated(encoding, text)
+        .into_iter()
+        .map(|value| decode_string(encoding, value))
+        .collect::<Vec<_>>();
+
+    // A trailing terminator leaves an empty value behind
+    while values.len() > 1 && values.last().is_some_and(|value| value.is_empty()) {
+        values.pop();
+    }
+    values
+}
+
+/// Decode the payload of a `COMM` frame into its description and text.
+pub fn decode_comment_frame(data: &[u8]) -> Option<(String, String)> {
+    let encoding = *data.first()?;
+    let rest = data.get(4..)?;
+    let (description, text) = split_once_terminated(encoding, rest);
+    Some((
+        decode_string(encoding, description),
+        decode_string(encoding, trim_terminator(encoding, text)),
+    ))
+}
+
+fn terminator_len(encoding: u8) -> usize {
+    if encoding == 1 || encoding == 2 {
+        2
+    } else {
+        1
+    }
+}
+
+fn find_terminator(encoding: u8, data: &[u8]) -> Option<usize> {
+    if terminator_len(encoding) == 2 {
+        (0..data.len() / 2).map(|i| i * 2).find(|&i| data[i] == 0 && data[i + 1] == 0)
+    } else {
+        data.iter().position(|&b| b == 0)
+    }
+}
+
+fn split_once_terminated(encoding: u8, data: &[u8]) -> (&[u8], &[u8]) {
+    match find_terminator(encoding, data) {
+        Some(end) => (&data[..end], &data[end + terminator_len(encoding)..]),
+        None => (data, &[]),
+    }
+}
+
+fn split_terminated(encoding: u8, mut data: &[u8]) -> Vec<&[u8]> {
+    let mut parts = Vec::new();
+    loop {
+        match find_terminator(encoding, data) {
+            Some(end) => {
+                parts.push(&data[..end]);
+                data = &data[end + terminator_len(encoding)..];
+            }
+            None => {
+                parts.push(data);
+                return parts;
+            }
+        }
+    }
+}
+
+fn trim_terminator(encoding: u8, data: &[u8]) -> &[u8] {
+    match find_terminator(encoding, data) {
+        Some(end) => &data[..end],
+        None => data,
+    }
+}
+
+/// Decode a single string in one of the four ID3v2 text encodings.
+fn decode_string(encoding: u8, data: &[u8]) -> String {
+    match encoding {
+        0 => data.iter().map(|&b| b as char).collect(),
+        1 | 2 => {
+            let (big_endian, data) = match data {
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                // UTF-16 without a BOM is big endian per the spec
+                _ => (true, data),
+            };
+            let units = data
+                .chunks_exact(2)
+                .map(|pair| {
+                    if big_endian {
+                        u16::from_be_bytes([pair[0], pair[1]])
+                    } else {
+                        u16::from_le_bytes([pair[0], pair[1]])
+                    }
+                })
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+fn synchsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, &b| (acc << 7) | (b & 0x7F) as u32)
+}
+
+fn to_synchsafe(value: u32) -> [u8; 4] {
+    [
+        ((value >> 21) & 0x7F) as u8,
+        ((value >> 14) & 0x7F) as u8,
+        ((value >> 7) & 0x7F) as u8,
+        (value & 0x7F) as u8,
+    ]
+}
+
+fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(truncated)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "ID3v2 tag is truncated")
+}
+
+/// Undo the unsynchronisation scheme, which inserts a zero byte after every 0xFF.
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0u8;
+    for &byte in data {
+        if !(previous == 0xFF && byte == 0x00) {
+            output.push(byte);
+        }
+        previous = byte;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::tests::jpeg;
+    use crate::testing::TempFile;
+
+    const AUDIO: &[u8] = b"\xFF\xFB\x90\x00 not really an MPEG frame";
+
+    /// A tag of version `major` with the given header flags around `frames`.
+    fn tag(major: u8, flags: u8, frames: &[u8]) -> Vec<u8> {
+        let mut bytes = b"ID3".to_vec();
+        bytes.extend_from_slice(&[major, 0, flags]);
+        bytes.extend_from_slice(&to_synchsafe(frames.len() as u32));
+        bytes.extend_from_slice(frames);
+        bytes
+    }
+
+    fn v23_frame(id: &str, flags: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = id.as_bytes().to_vec();
+        bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&flags.to_be_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn v24_frame(id: &str, flags: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = id.as_bytes().to_vec();
+        bytes.extend_from_slice(&to_synchsafe(data.len() as u32));
+        bytes.extend_from_slice(&flags.to_be_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn read(bytes: &[u8]) -> Tag {
+        Tag::read_from(&mut &bytes[..]).unwrap().unwrap()
+    }
+
+    #[test]
+    fn synchsafe_round_trip() {
+        for value in [0, 0x7F, 0x80, 300, 0x0FFF_FFFF] {
+            let bytes = to_synchsafe(value);
+            assert!(bytes.iter().all(|&b| b & 0x80 == 0));
+            assert_eq!(synchsafe(&bytes), value);
+        }
+        assert_eq!(to_synchsafe(300), [0, 0, 2, 0x2C]);
+    }
+
+    #[test]
+    fn unsynchronisation_is_removed() {
+        assert_eq!(remove_unsynchronisation(&[0xFF, 0x00, 0xE0, 0x41, 0xFF, 0x00, 0x00]), [0xFF, 0xE0, 0x41, 0xFF, 0x00]);
+        assert_eq!(remove_unsynchronisation(&[0x00, 0xFF]), [0x00, 0xFF]);
+    }
+
+    #[test]
+    fn reads_a_v23_tag_with_tag_wide_unsynchronisation() {
+        // "A\xFFB" in Latin-1, with a zero stuffed after the 0xFF. ID3v2.3
+        // frame sizes count the bytes after the stuffing is removed.
+        let mut frames = v23_frame("TIT2", 0, b"\x00A\xFFB");
+        frames.insert(frames.len() - 1, 0);
+        let tag = read(&tag(3, 0x80, &frames));
+        assert_eq!(tag.text("TIT2").as_deref(), Some("A\u{FF}B"));
+        assert_eq!(tag.size, 10 + frames.len());
+    }
+
+    #[test]
+    fn reads_a_v24_frame_with_its_own_unsynchronisation_and_length() {
+        let mut data = to_synchsafe(4).to_vec();
+        data.extend_from_slice(b"\x00\xFF\x00\xE0Z");
+        let tag = read(&tag(4, 0, &v24_frame("TPE1", 0x0003, &data)));
+        assert_eq!(tag.frame("TPE1").unwrap().data, b"\x00\xFF\xE0Z");
+    }
+
+    #[test]
+    fn write_read_round_trip_keeps_the_audio() {
+        let mut bytes = tag(3, 0, &v23_frame("TIT2", 0, b"\x00Old"));
+        bytes.extend_from_slice(AUDIO);
+        let file = TempFile::new("id3v2-round-trip", &bytes);
+
+        let metadata = Metadata {
+            title: "Title".to_string(),
+            artist: "Ärtist".to_string(),
+            year: 2004,
+            track: 9,
+            comments: "Comment".to_string(),
+            ..Metadata::default()
+        };
+        let mut tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        tag.update_from(&metadata);
+        tag.write_to_path(file.path()).unwrap();
+
+        let written = file.bytes();
+        assert_eq!(&written[..4], b"ID3\x04");
+        assert!(written.ends_with(AUDIO));
+        let mut read = Metadata::default();
+        Tag::read_from_path(file.path()).unwrap().unwrap().apply_to(&mut read);
+        assert_eq!(read.title, "Title");
+        assert_eq!(read.artist, "Ärtist");
+        assert_eq!(read.year, 2004);
+        assert_eq!(read.track, 9);
+        assert_eq!(read.comments, "Comment");
+
+        // The padding left by the first write takes the next edit in place
+        let len = written.len();
+        let mut tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        tag.set_text("TIT2", "A slightly longer title");
+        tag.write_to_path(file.path()).unwrap();
+        assert_eq!(file.bytes().len(), len);
+        let tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        assert_eq!(tag.text("TIT2").as_deref(), Some("A slightly longer title"));
+    }
+
+    #[test]
+    fn saving_keeps_full_dates_and_totals() {
+        let mut frames = v24_frame("TDRC", 0, b"\x032004-05-06");
+        frames.extend(v24_frame("TRCK", 0, b"\x033/12"));
+        frames.extend(v24_frame("TPOS", 0, b"\x031/2"));
+        let mut bytes = tag(4, 0, &frames);
+        bytes.extend_from_slice(AUDIO);
+        let file = TempFile::new("id3v2-numbers", &bytes);
+
+        let mut metadata = Metadata::default();
+        Tag::read_from_path(file.path()).unwrap().unwrap().apply_to(&mut metadata);
+        assert_eq!((metadata.year, metadata.track, metadata.disc_no), (2004, 3, 1));
+        metadata.title = "Title".to_string();
+        let mut tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        tag.update_from(&metadata);
+        tag.write_to_path(file.path()).unwrap();
+
+        let tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        assert_eq!(tag.text("TDRC").as_deref(), Some("2004-05-06"));
+        assert_eq!(tag.text("TRCK").as_deref(), Some("3/12"));
+        assert_eq!(tag.text("TPOS").as_deref(), Some("1/2"));
+
+        // A changed number keeps the total, and a cleared one drops the frame
+        let mut tag = tag;
+        tag.update_from(&Metadata { track: 4, disc_no: 0, ..metadata });
+        assert_eq!(tag.text("TRCK").as_deref(), Some("4/12"));
+        assert_eq!(tag.text("TPOS"), None);
+    }
+
+    #[test]
+    fn compressed_v23_frames_are_kept_in_v24_layout() {
+        // Decompressed size, then the group byte, then the (unreadable) payload
+        let payload = b"\x00\x00\x01\x00\x07compressed";
+        let mut frames = v23_frame("TXXX", 0x00A0, payload);
+        frames.extend(v23_frame("TIT2", 0, b"\x00Title"));
+        let mut bytes = tag(3, 0, &frames);
+        bytes.extend_from_slice(AUDIO);
+        let file = TempFile::new("id3v2-compressed", &bytes);
+
+        let mut tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        tag.set_text("TIT2", "New Title");
+        tag.write_to_path(file.path()).unwrap();
+
+        let tag = Tag::read_from_path(file.path()).unwrap().unwrap();
+        let raw = tag.frames.iter().find(|frame| frame.id == "TXXX").unwrap();
+        assert!(raw.is_raw());
+        assert_eq!(raw.flags, 0x0049);
+        assert_eq!(raw.data, b"\x07\x00\x00\x02\x00compressed");
+        assert!(tag.frame("TXXX").is_none());
+        assert_eq!(tag.text("TIT2").as_deref(), Some("New Title"));
+    }
+
+    #[test]
+    fn encrypted_frames_survive_clearing_the_field() {
+        let mut tag = read(&tag(4, 0, &v24_frame("TPE1", 0x0004, b"\x80secret")));
+        assert_eq!(tag.text("TPE1"), None);
+        tag.set_text("TPE1", "");
+        assert_eq!(tag.frames.len(), 1);
+        tag.set_text("TPE1", "Artist");
+        assert_eq!(tag.frames.len(), 1);
+        assert_eq!(tag.text("TPE1").as_deref(), Some("Artist"));
+    }
+
+    #[test]
+    fn v22_frames_get_their_later_ids() {
+        let frame = |id: &[u8], data: &[u8]| {
+            let mut bytes = id.to_vec();
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+            bytes.extend_from_slice(data);
+            bytes
+        };
+        let mut picture = b"\x00JPG\x03\x00".to_vec();
+        picture.extend(jpeg());
+        let mut frames = frame(b"TT2", b"\x00Title");
+        frames.extend(frame(b"XYZ", b"unknown"));
+        frames.extend(frame(b"PIC", &picture));
+
+        let tag = read(&tag(2, 0, &frames));
+        let ids = tag.frames.iter().map(|frame| frame.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["TIT2", "APIC"]);
+        assert_eq!(tag.text("TIT2").as_deref(), Some("Title"));
+        assert!(tag.frame("APIC").unwrap().data.starts_with(b"\x00image/jpeg\x00\x03"));
+        assert_eq!(tag.front_cover(), Some(jpeg().as_slice()));
+    }
+
+    #[test]
+    fn numeric_genres_are_resolved() {
+        assert_eq!(resolve_genre("17"), "Rock");
+        assert_eq!(resolve_genre("(17)"), "Rock");
+        assert_eq!(resolve_genre("(17)Hard Rock"), "Hard Rock");
+        assert_eq!(resolve_genre("(RX)"), "Remix");
+        assert_eq!(resolve_genre("Shoegaze"), "Shoegaze");
+    }
+}
@@ -0,0 +1,306 @@
+// src/tags/mod.rs
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::Metadata;
+
+pub mod flac;
+pub mod id3v1;
+pub mod id3v2;
+pub mod mp4;
+pub mod ogg;
+pub mod vorbis;
+
+/// Options controlling how `write_metadata` saves a file.
+#[derive(Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Add an ID3v1.1 trailer to files that don't have one yet, for players
+    /// that don't understand ID3v2. Existing trailers are always kept in sync.
+    pub id3v1: bool,
+}
+
+/// An image embedded in a tag, such as album cover art.
+pub struct Picture {
+    pub mime: &'static str,
+    pub data: Vec<u8>,
+}
+
+impl Picture {
+    /// Wrap image data, telling its type from its first bytes. Only JPEG and
+    /// PNG are accepted, the formats every player shows.
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Picture> {
+        let mime = match data.as_slice() {
+            [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+            [0x89, b'P', b'N', b'G', ..] => "image/png",
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not a JPEG or PNG image")),
+        };
+        Ok(Picture { mime, data })
+    }
+}
+
+/// The audio containers we can read and write tags for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Mp3,
+    Flac,
+    Ogg,
+    Mp4,
+}
+
+impl Format {
+    /// Detect the container of the file at `path` from its first bytes, so
+    /// misnamed files are still recognised. Returns `None` for anything else.
+    pub fn detect(path: &Path) -> io::Result<Option<Format>> {
+        let mut file = File::open(path)?;
+
+        // FLAC files occasionally carry an ID3v2 tag in front of the stream
+        let start = id3v2::Tag::read_from(&mut file)?.map_or(0, |tag| tag.size);
+        file.seek(SeekFrom::Start(start as u64))?;
+
+        let mut magic = [0u8; 8];
+        if file.read_exact(&mut magic).is_err() {
+            return Ok((start > 0).then_some(Format::Mp3));
+        }
+
+        Ok(match &magic {
+            [b'f', b'L', b'a', b'C', ..] => Some(Format::Flac),
+            [b'O', b'g', b'g', b'S', ..] => Some(Format::Ogg),
+            [_, _, _, _, b'f', b't', b'y', b'p'] => Some(Format::Mp4),
+            // MPEG audio frame sync
+            [0xFF, second, ..] if second & 0xE0 == 0xE0 => Some(Format::Mp3),
+            _ if start > 0 => Some(Format::Mp3),
+            _ => None,
+        })
+    }
+}
+
+/// Read the tags stored in the file at `path` into a fresh `Metadata`.
+/// Files without any tag still produce an entry with `path` and `file_name` set.
+pub fn read_metadata(path: &Path) -> io::Result<Metadata> {
+    let mut metadata = Metadata {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().into_owned(),
+        ..Metadata::default()
+    };
+
+    match Format::detect(path)? {
+        Some(Format::Flac) => flac::read_into(path, &mut metadata)?,
+        Some(Format::Ogg) => ogg::read_into(path, &mut metadata)?,
+        Some(Format::Mp4) => mp4::read_into(path, &mut metadata)?,
+        // Anything else may still be an MP3 with nothing but an ID3v1 trailer
+        Some(Format::Mp3) | None => {
+            if let Some(tag) = id3v2::Tag::read_from_path(path)? {
+                tag.apply_to(&mut metadata);
+            }
+            if let Some(tag) = id3v1::Tag::read_from_path(path)? {
+                tag.apply_to(&mut metadata);
+            }
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Write `metadata` to the file at `path` in the tag format native to its
+/// container (ID3v2.4 for MP3). Tag fields that don't correspond to a
+/// `Metadata` field are carried over from the existing tag.
+pub fn write_metadata(path: &Path, metadata: &Metadata, options: WriteOptions) -> io::Result<()> {
+    match Format::detect(path)? {
+        Some(Format::Flac) => flac::write(path, metadata),
+        Some(Format::Ogg) => ogg::write(path, metadata),
+        Some(Format::Mp4) => mp4::write(path, metadata),
+        Some(Format::Mp3) | None => {
+            let mut tag = id3v2::Tag::read_from_path(path)?.unwrap_or_default();
+            tag.update_from(metadata);
+            tag.write_to_path(path)?;
+
+            if options.id3v1 || id3v1::Tag::read_from_path(path)?.is_some() {
+                id3v1::Tag::from_metadata(metadata).write_to_path(path)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Copy the tag fields `from` has over those of `metadata`, keeping the ones it leaves empty.
+pub fn overlay(metadata: &mut Metadata, from: Metadata) {
+    let set_text = |field: &mut String, value: String| {
+        if !value.is_empty() {
+            *field = value;
+        }
+    };
+    set_text(&mut metadata.file_name, from.file_name);
+    set_text(&mut metadata.title, from.title);
+    set_text(&mut metadata.artist, from.artist);
+    set_text(&mut metadata.album, from.album);
+    set_text(&mut metadata.genre, from.genre);
+    set_text(&mut metadata.album_artist, from.album_artist);
+    set_text(&mut metadata.composer, from.composer);
+    set_text(&mut metadata.comments, from.comments);
+
+    let set_number = |field: &mut u16, value: u16| {
+        if value != 0 {
+            *field = value;
+        }
+    };
+    set_number(&mut metadata.year, from.year);
+    set_number(&mut metadata.track, from.track);
+    set_number(&mut metadata.disc_no, from.disc_no);
+}
+
+/// The front cover of the file at `path`, if it has one. Only MP3 and MP4
+/// covers are read so far.
+pub fn read_cover(path: &Path) -> io::Result<Option<Picture>> {
+    match Format::detect(path)? {
+        Some(Format::Mp4) => mp4::read_cover(path),
+        Some(Format::Mp3) | None => {
+            let tag = id3v2::Tag::read_from_path(path)?;
+            let cover = tag.as_ref().and_then(id3v2::Tag::front_cover);
+            cover.map(|data| Picture::from_bytes(data.to_vec())).transpose()
+        }
+        Some(_) => Ok(None),
+    }
+}
+
+/// Embed `picture` as the front cover of the file at `path`. Only MP3 and MP4
+/// files can be given a cover so far.
+pub fn write_cover(path: &Path, picture: &Picture) -> io::Result<()> {
+    match Format::detect(path)? {
+        Some(Format::Mp4) => mp4::write_cover(path, picture),
+        Some(Format::Mp3) | None => {
+            let mut tag = id3v2::Tag::read_from_path(path)?.unwrap_or_default();
+            tag.set_front_cover(picture);
+            tag.write_to_path(path)
+        }
+        Some(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "covers can only be embedded in MP3 and MP4 files")),
+    }
+}
+
+/// Rewrite the file at `path` with the bytes in `start..end` replaced by
+/// `replacement`. Goes through a temporary file next to the original so an
+/// interrupted write never leaves a truncated file behind.
+pub(crate) fn splice_file(path: &Path, start: u64, end: u64, replacement: &[u8]) -> io::Result<()> {
+    let mut source = File::open(path)?;
+
+    let temp_path = path.with_extension("tagtmp");
+    let result = (|| {
+        let mut temp = File::create(&temp_path)?;
+        io::copy(&mut (&mut source).take(start), &mut temp)?;
+        temp.write_all(replacement)?;
+        source.seek(SeekFrom::Start(end))?;
+        io::copy(&mut source, &mut temp)?;
+        temp.sync_all()
+    })();
+
+    match result {
+        Ok(()) => fs::rename(&temp_path, path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Parse the number at the start of a field such as "3/12" or "2021-05-01".
+pub fn leading_number(value: &str) -> u16 {
+    let digits = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    digits.parse().unwrap_or(0)
+}
+
+/// Format a numeric field for a tag, where zero means the field is unset.
+pub(crate) fn number_or_empty(value: u16) -> String {
+    if value == 0 {
+        String::new()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Format a numeric field for a tag that already holds `existing`, such as
+/// "2004-05-06" or "3/12": only its leading number is replaced, so the rest of
+/// the date or the "/total" part survives, and an unchanged number keeps the
+/// value as it is. Zero means the field is unset.
+pub(crate) fn with_number(existing: Option<&str>, value: u16) -> String {
+    let Some(existing) = existing.filter(|existing| value != 0 && leading_number(existing) != 0) else {
+        return number_or_empty(value);
+    };
+    if leading_number(existing) == value {
+        return existing.to_string();
+    }
+    let rest = existing.trim().trim_start_matches(|c: char| c.is_ascii_digit());
+    format!("{value}{rest}")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    /// The smallest JPEG header `Picture::from_bytes` accepts, followed by some image data.
+    pub(crate) fn jpeg() -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        data.extend((0..=255).cycle().take(600));
+        data
+    }
+
+    #[test]
+    fn overlay_keeps_fields_the_source_leaves_empty() {
+        let mut metadata = Metadata {
+            title: "Inferred".to_string(),
+            artist: "Inferred Artist".to_string(),
+            year: 1999,
+            track: 4,
+            ..Metadata::default()
+        };
+        let from = Metadata { artist: "Tagged Artist".to_string(), year: 2001, ..Metadata::default() };
+
+        overlay(&mut metadata, from);
+        assert_eq!(metadata.title, "Inferred");
+        assert_eq!(metadata.artist, "Tagged Artist");
+        assert_eq!(metadata.year, 2001);
+        assert_eq!(metadata.track, 4);
+    }
+
+    #[test]
+    fn leading_number_parses_partial_fields() {
+        assert_eq!(leading_number("3/12"), 3);
+        assert_eq!(leading_number(" 2021-05-01"), 2021);
+        assert_eq!(leading_number("side A"), 0);
+        assert_eq!(number_or_empty(0), "");
+        assert_eq!(number_or_empty(7), "7");
+    }
+
+    #[test]
+    fn with_number_keeps_what_follows_the_number() {
+        assert_eq!(with_number(Some("2004-05-06"), 2004), "2004-05-06");
+        assert_eq!(with_number(Some("2004-05-06"), 2005), "2005-05-06");
+        assert_eq!(with_number(Some("03/12"), 3), "03/12");
+        assert_eq!(with_number(Some("3/12"), 4), "4/12");
+        assert_eq!(with_number(Some("3/12"), 0), "");
+        assert_eq!(with_number(Some("side A"), 2), "2");
+        assert_eq!(with_number(None, 7), "7");
+    }
+
+    #[test]
+    fn picture_type_is_sniffed_from_the_data() {
+        assert_eq!(Picture::from_bytes(jpeg()).unwrap().mime, "image/jpeg");
+        assert_eq!(Picture::from_bytes(b"\x89PNG\r\n\x1a\n".to_vec()).unwrap().mime, "image/png");
+        assert!(Picture::from_bytes(b"RIFF\0\0\0\0WEBP".to_vec()).is_err());
+    }
+
+    #[test]
+    fn splice_file_replaces_a_byte_range() {
+        let file = TempFile::new("splice", b"0123456789");
+        splice_file(file.path(), 2, 5, b"abcdef").unwrap();
+        assert_eq!(file.bytes(), b"01abcdef56789");
+    }
+}
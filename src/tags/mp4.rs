@@ -0,0 +1,608 @@
+// src/tags/mp4.rs
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::tags::{id3v1, leading_number, splice_file, with_number, Picture};
+use crate::Metadata;
+
+/// Free space left in `meta` when the file has to be rewritten anyway, so the
+/// next few edits fit in place.
+const DEFAULT_PADDING: usize = 1024;
+
+// iTunes item atoms; 0xA9 is the "©" these names start with
+const TITLE: &[u8; 4] = b"\xA9nam";
+const ARTIST: &[u8; 4] = b"\xA9ART";
+const ALBUM: &[u8; 4] = b"\xA9alb";
+const YEAR: &[u8; 4] = b"\xA9day";
+const GENRE: &[u8; 4] = b"\xA9gen";
+const GENRE_ID: &[u8; 4] = b"gnre";
+const TRACK: &[u8; 4] = b"trkn";
+const DISC: &[u8; 4] = b"disk";
+const ALBUM_ARTIST: &[u8; 4] = b"aART";
+const COMPOSER: &[u8; 4] = b"\xA9wrt";
+const COMMENT: &[u8; 4] = b"\xA9cmt";
+const COVER: &[u8; 4] = b"covr";
+
+// Well-known types of a `data` atom
+const TYPE_IMPLICIT: u32 = 0;
+const TYPE_UTF8: u32 = 1;
+const TYPE_JPEG: u32 = 13;
+const TYPE_PNG: u32 = 14;
+
+#[derive(Clone)]
+struct Atom {
+    kind: [u8; 4],
+    /// Payload, without the size and type header.
+    data: Vec<u8>,
+}
+
+impl Atom {
+    fn new(kind: &[u8; 4], data: Vec<u8>) -> Atom {
+        Atom { kind: *kind, data }
+    }
+
+    fn len(&self) -> usize {
+        8 + self.data.len()
+    }
+
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&(self.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.kind);
+        bytes.extend_from_slice(&self.data);
+    }
+}
+
+fn parse_atoms(mut data: &[u8]) -> io::Result<Vec<Atom>> {
+    let mut atoms = Vec::new();
+    while data.len() >= 8 {
+        let mut size = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = data[4..8].try_into().unwrap();
+        let mut header = 8;
+        if size == 1 {
+            let large = data.get(8..16).ok_or_else(|| invalid("atom is truncated"))?;
+            size = u64::from_be_bytes(large.try_into().unwrap()) as usize;
+            header = 16;
+        } else if size == 0 {
+            size = data.len();
+        }
+        if size < header || size > data.len() {
+            return Err(invalid("atom is truncated"));
+        }
+        atoms.push(Atom { kind, data: data[header..size].to_vec() });
+        data = &data[size..];
+    }
+    Ok(atoms)
+}
+
+fn atoms_to_bytes(atoms: &[Atom]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for atom in atoms {
+        atom.write_to(&mut bytes);
+    }
+    bytes
+}
+
+/// Where the `moov` atom sits in the file, and what follows it.
+struct Layout {
+    moov_offset: u64,
+    moov_len: u64,
+    moov: Vec<u8>,
+    /// Size of a `free` atom directly after `moov`, which can absorb growth.
+    free_after: u64,
+    /// Whether media data follows `moov`, whose chunk offsets then move with it.
+    mdat_after: bool,
+}
+
+impl Layout {
+    fn read(path: &Path) -> io::Result<Layout> {
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut moov = None;
+        let mut free_after = 0;
+        let mut mdat_after = false;
+        let mut previous_was_moov = false;
+        let mut offset = 0;
+
+        while offset + 8 <= file_len {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut header = [0u8; 8];
+            file.read_exact(&mut header)?;
+            let kind = &header[4..8];
+            let mut size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as u64;
+            let mut header_len = 8;
+            if size == 1 {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)?;
+                size = u64::from_be_bytes(large);
+                header_len = 16;
+            } else if size == 0 {
+                size = file_len - offset;
+            }
+            if size < header_len || offset + size > file_len {
+                return Err(invalid("top-level atom is truncated"));
+            }
+
+            if kind == b"moov" {
+                let mut data = vec![0u8; (size - header_len) as usize];
+                file.read_exact(&mut data)?;
+                moov = Some((offset, size, data));
+            } else if moov.is_some() {
+                if previous_was_moov && kind == b"free" {
+                    free_after = size;
+                }
+                mdat_after |= kind == b"mdat";
+            }
+
+            previous_was_moov = kind == b"moov";
+            offset += size;
+        }
+
+        let (moov_offset, moov_len, moov) = moov.ok_or_else(|| invalid("no moov atom"))?;
+        Ok(Layout { moov_offset, moov_len, moov, free_after, mdat_after })
+    }
+
+    /// The `ilst` item list, if the file has one.
+    fn items(&self) -> io::Result<Option<Vec<Atom>>> {
+        let moov = parse_atoms(&self.moov)?;
+        let Some(udta) = moov.iter().find(|atom| &atom.kind == b"udta") else {
+            return Ok(None);
+        };
+        let udta = parse_atoms(&udta.data)?;
+        let Some(meta) = udta.iter().find(|atom| &atom.kind == b"meta") else {
+            return Ok(None);
+        };
+        // `meta` is a full box: four bytes of version and flags come before its children
+        let meta = parse_atoms(meta.data.get(4..).unwrap_or_default())?;
+        meta.iter()
+            .find(|atom| &atom.kind == b"ilst")
+            .map(|ilst| parse_atoms(&ilst.data))
+            .transpose()
+    }
+}
+
+/// Read the iTunes metadata of the MP4/M4A file at `path` into `metadata`.
+pub fn read_into(path: &Path, metadata: &mut Metadata) -> io::Result<()> {
+    let Some(items) = Layout::read(path)?.items()? else {
+        return Ok(());
+    };
+
+    if let Some(title) = text(&items, TITLE) {
+        metadata.title = title;
+    }
+    if let Some(artist) = text(&items, ARTIST) {
+        metadata.artist = artist;
+    }
+    if let Some(album) = text(&items, ALBUM) {
+        metadata.album = album;
+    }
+    if let Some(year) = text(&items, YEAR) {
+        metadata.year = leading_number(&year);
+    }
+    if let Some(genre) = text(&items, GENRE).or_else(|| genre_from_id(&items)) {
+        metadata.genre = genre;
+    }
+    if let Some((track, _)) = pair(&items, TRACK) {
+        metadata.track = track;
+    }
+    if let Some(album_artist) = text(&items, ALBUM_ARTIST) {
+        metadata.album_artist = album_artist;
+    }
+    if let Some(composer) = text(&items, COMPOSER) {
+        metadata.composer = composer;
+    }
+    if let Some((disc_no, _)) = pair(&items, DISC) {
+        metadata.disc_no = disc_no;
+    }
+    if let Some(comments) = text(&items, COMMENT) {
+        metadata.comments = comments;
+    }
+    Ok(())
+}
+
+/// The cover art of the MP4/M4A file at `path`, if it has any.
+pub fn read_cover(path: &Path) -> io::Result<Option<Picture>> {
+    let Some(items) = Layout::read(path)?.items()? else {
+        return Ok(None);
+    };
+    data(&items, COVER).map(|(_, payload)| Picture::from_bytes(payload)).transpose()
+}
+
+/// Write `metadata` to the `ilst` atom of the MP4/M4A file at `path`, keeping
+/// items we don't map (cover art included).
+pub fn write(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    let layout = Layout::read(path)?;
+    let mut items = layout.items()?.unwrap_or_default();
+
+    set_text(&mut items, TITLE, &metadata.title);
+    set_text(&mut items, ARTIST, &metadata.artist);
+    set_text(&mut items, ALBUM, &metadata.album);
+    // ©day often holds a full release date, which is kept while the year matches
+    let year = with_number(text(&items, YEAR).as_deref(), metadata.year);
+    set_text(&mut items, YEAR, &year);
+    set_text(&mut items, GENRE, &metadata.genre);
+    items.retain(|item| &item.kind != GENRE_ID);
+    set_pair(&mut items, TRACK, metadata.track, 8);
+    set_text(&mut items, ALBUM_ARTIST, &metadata.album_artist);
+    set_text(&mut items, COMPOSER, &metadata.composer);
+    set_pair(&mut items, DISC, metadata.disc_no, 6);
+    set_text(&mut items, COMMENT, &metadata.comments);
+    write_items(path, &layout, &items)
+}
+
+/// Make `picture` the cover art of the MP4/M4A file at `path`, replacing any other.
+pub fn write_cover(path: &Path, picture: &Picture) -> io::Result<()> {
+    let layout = Layout::read(path)?;
+    let mut items = layout.items()?.unwrap_or_default();
+    let data_type = if picture.mime == "image/png" { TYPE_PNG } else { TYPE_JPEG };
+    set_item(&mut items, COVER, Some((data_type, picture.data.clone())));
+    write_items(path, &layout, &items)
+}
+
+/// Replace the item list of the file at `path`, laid out as in `layout`, with
+/// `items`. Free space in `meta` or right after `moov` absorbs size changes;
+/// otherwise the chunk offsets are patched and the file is rewritten.
+fn write_items(path: &Path, layout: &Layout, items: &[Atom]) -> io::Result<()> {
+    let ilst = Atom::new(b"ilst", atoms_to_bytes(items));
+
+    // Try to reuse the space of the old item list and the padding after it
+    let moov = build_moov(&layout.moov, &ilst, Padding::Reuse)?;
+    let new_len = moov.len() as u64;
+    if new_len == layout.moov_len {
+        return write_at(path, layout.moov_offset, &moov);
+    }
+
+    let available = layout.moov_len + layout.free_after;
+    if new_len == available || new_len + 8 <= available {
+        let mut bytes = moov;
+        if new_len < available {
+            Atom::new(b"free", vec![0; (available - new_len - 8) as usize]).write_to(&mut bytes);
+        }
+        return write_at(path, layout.moov_offset, &bytes);
+    }
+
+    let moov = build_moov(&layout.moov, &ilst, Padding::Add(DEFAULT_PADDING))?;
+    let new_len = moov.len() as u64;
+    let mut moov_atoms = parse_atoms(&moov[8..])?;
+    if layout.mdat_after {
+        let delta = new_len as i64 - layout.moov_len as i64;
+        let moov_end = layout.moov_offset + layout.moov_len;
+        shift_chunk_offsets(&mut moov_atoms, moov_end, delta)?;
+    }
+    let moov = atoms_to_bytes(&[Atom::new(b"moov", atoms_to_bytes(&moov_atoms))]);
+    splice_file(path, layout.moov_offset, layout.moov_offset + layout.moov_len, &moov)
+}
+
+enum Padding {
+    /// Keep the total size of `ilst` plus the `free` atom after it, if possible.
+    Reuse,
+    /// Put a `free` atom of this many bytes after `ilst`.
+    Add(usize),
+}
+
+/// Rebuild `moov` with `ilst` in place of the old item list, creating the
+/// `udta`/`meta` containers as needed. Returns the complete atom, header included.
+fn build_moov(moov: &[u8], ilst: &Atom, padding: Padding) -> io::Result<Vec<u8>> {
+    let mut moov = parse_atoms(moov)?;
+    let udta_index = index_or_push(&mut moov, b"udta", Vec::new);
+    let mut udta = parse_atoms(&moov[udta_index].data)?;
+    let meta_index = index_or_push(&mut udta, b"meta", || vec![0; 4]);
+    let meta_data = &udta[meta_index].data;
+    let version_and_flags = meta_data.get(..4).unwrap_or(&[0; 4]).to_vec();
+    let mut meta = parse_atoms(meta_data.get(4..).unwrap_or_default())?;
+
+    if !meta.iter().any(|atom| &atom.kind == b"hdlr") {
+        // Players ignore item lists without the "mdir" handler
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"mdirappl");
+        hdlr.extend_from_slice(&[0; 9]);
+        meta.insert(0, Atom::new(b"hdlr", hdlr));
+    }
+
+    let ilst_index = index_or_push(&mut meta, b"ilst", Vec::new);
+    let old_len = meta[ilst_index].len();
+    let free_len = meta
+        .get(ilst_index + 1)
+        .filter(|atom| &atom.kind == b"free")
+        .map_or(0, Atom::len);
+    if free_len > 0 {
+        meta.remove(ilst_index + 1);
+    }
+    meta[ilst_index] = ilst.clone();
+
+    let free = match padding {
+        Padding::Reuse if ilst.len() + 8 <= old_len + free_len => Some(old_len + free_len - ilst.len() - 8),
+        Padding::Reuse => None,
+        Padding::Add(size) => Some(size),
+    };
+    if let Some(size) = free {
+        meta.insert(ilst_index + 1, Atom::new(b"free", vec![0; size]));
+    }
+
+    let mut meta_data = version_and_flags;
+    meta_data.extend_from_slice(&atoms_to_bytes(&meta));
+    udta[meta_index].data = meta_data;
+    moov[udta_index].data = atoms_to_bytes(&udta);
+    Ok(atoms_to_bytes(&[Atom::new(b"moov", atoms_to_bytes(&moov))]))
+}
+
+fn index_or_push(atoms: &mut Vec<Atom>, kind: &[u8; 4], data: impl FnOnce() -> Vec<u8>) -> usize {
+    match atoms.iter().position(|atom| &atom.kind == kind) {
+        Some(index) => index,
+        None => {
+            atoms.push(Atom::new(kind, data()));
+            atoms.len() - 1
+        }
+    }
+}
+
+/// Move every chunk offset pointing past `moov_end` by `delta` bytes.
+fn shift_chunk_offsets(atoms: &mut [Atom], moov_end: u64, delta: i64) -> io::Result<()> {
+    for atom in atoms.iter_mut() {
+        match &atom.kind {
+            b"trak" | b"mdia" | b"minf" | b"stbl" => {
+                let mut children = parse_atoms(&atom.data)?;
+                shift_chunk_offsets(&mut children, moov_end, delta)?;
+                atom.data = atoms_to_bytes(&children);
+            }
+            b"stco" | b"co64" => {
+                let width = if &atom.kind == b"stco" { 4 } else { 8 };
+                let count = atom
+                    .data
+                    .get(4..8)
+                    .map(|count| u32::from_be_bytes(count.try_into().unwrap()) as usize)
+                    .ok_or_else(|| invalid("chunk offset table is truncated"))?;
+                if atom.data.len() < 8 + count * width {
+                    return Err(invalid("chunk offset table is truncated"));
+                }
+                for entry in atom.data[8..8 + count * width].chunks_exact_mut(width) {
+                    if width == 4 {
+                        let offset = u32::from_be_bytes(entry.try_into().unwrap()) as u64;
+                        if offset >= moov_end {
+                            let shifted = u32::try_from(offset as i64 + delta)
+                                .map_err(|_| invalid("chunk offset overflows stco"))?;
+                            entry.copy_from_slice(&shifted.to_be_bytes());
+                        }
+                    } else {
+                        let offset = u64::from_be_bytes(entry.try_into().unwrap());
+                        if offset >= moov_end {
+                            entry.copy_from_slice(&((offset as i64 + delta) as u64).to_be_bytes());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The payload and type of the first `data` atom of item `kind`.
+fn data(items: &[Atom], kind: &[u8; 4]) -> Option<(u32, Vec<u8>)> {
+    let item = items.iter().find(|item| &item.kind == kind)?;
+    let data = parse_atoms(&item.data).ok()?.into_iter().find(|atom| &atom.kind == b"data")?;
+    let kind = u32::from_be_bytes(data.data.get(0..4)?.try_into().unwrap()) & 0x00FF_FFFF;
+    Some((kind, data.data.get(8..)?.to_vec()))
+}
+
+fn text(items: &[Atom], kind: &[u8; 4]) -> Option<String> {
+    data(items, kind)
+        .map(|(_, payload)| String::from_utf8_lossy(&payload).into_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// `trkn` and `disk` store a number and a total as big endian u16s after two zero bytes.
+fn pair(items: &[Atom], kind: &[u8; 4]) -> Option<(u16, u16)> {
+    let (_, payload) = data(items, kind)?;
+    let number = u16::from_be_bytes(payload.get(2..4)?.try_into().unwrap());
+    let total = payload.get(4..6).map_or(0, |total| u16::from_be_bytes(total.try_into().unwrap()));
+    Some((number, total))
+}
+
+/// `gnre` stores the ID3v1 genre index plus one.
+fn genre_from_id(items: &[Atom]) -> Option<String> {
+    let (_, payload) = data(items, GENRE_ID)?;
+    let id = u16::from_be_bytes(payload.get(0..2)?.try_into().unwrap());
+    id3v1::genre_name(u8::try_from(id.checked_sub(1)?).ok()?).map(str::to_string)
+}
+
+/// Replace item `kind` with a single `data` atom, keeping its position.
+/// `None` removes the item.
+fn set_item(items: &mut Vec<Atom>, kind: &[u8; 4], data: Option<(u32, Vec<u8>)>) {
+    let position = items.iter().position(|item| &item.kind == kind);
+    items.retain(|item| &item.kind != kind);
+
+    let Some((data_type, payload)) = data else {
+        return;
+    };
+    let mut data = data_type.to_be_bytes().to_vec();
+    // Locale
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&payload);
+    let item = Atom::new(kind, atoms_to_bytes(&[Atom::new(b"data", data)]));
+
+    match position {
+        Some(position) => items.insert(position, item),
+        None => items.push(item),
+    }
+}
+
+fn set_text(items: &mut Vec<Atom>, kind: &[u8; 4], value: &str) {
+    let data = (!value.is_empty()).then(|| (TYPE_UTF8, value.as_bytes().to_vec()));
+    set_item(items, kind, data);
+}
+
+/// Set the number of a `trkn`/`disk` item, keeping the total already stored.
+fn set_pair(items: &mut Vec<Atom>, kind: &[u8; 4], number: u16, len: usize) {
+    let total = pair(items, kind).map_or(0, |(_, total)| total);
+    let data = (number != 0).then(|| {
+        let mut payload = vec![0; len];
+        payload[2..4].copy_from_slice(&number.to_be_bytes());
+        payload[4..6].copy_from_slice(&total.to_be_bytes());
+        (TYPE_IMPLICIT, payload)
+    });
+    set_item(items, kind, data);
+}
+
+fn write_at(path: &Path, offset: u64, bytes: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("MP4: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::tests::jpeg;
+    use crate::testing::TempFile;
+
+    fn atom(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        atoms_to_bytes(&[Atom::new(kind, data.to_vec())])
+    }
+
+    /// A track whose single chunk starts at `offset`, in an `stco` or `co64` table.
+    fn trak(table: &[u8; 4], offset: u64) -> Vec<u8> {
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        match table {
+            b"stco" => data.extend_from_slice(&(offset as u32).to_be_bytes()),
+            _ => data.extend_from_slice(&offset.to_be_bytes()),
+        }
+        atom(b"trak", &atom(b"mdia", &atom(b"minf", &atom(b"stbl", &atom(table, &data)))))
+    }
+
+    /// `ftyp`, media data, `moov` and more media data, with a `co64` track
+    /// pointing into the first `mdat` and an `stco` one into the second.
+    fn mp4_bytes() -> Vec<u8> {
+        let mut bytes = atom(b"ftyp", b"M4A \0\0\0\0");
+        bytes.extend(atom(b"mdat", b"FIRST"));
+        let first = bytes.len() as u64 - 5;
+        // The offsets don't change the size of the tables
+        let moov_len = atom(b"moov", &[trak(b"co64", 0), trak(b"stco", 0)].concat()).len();
+        let second = (bytes.len() + moov_len + 8) as u64;
+        let mut moov = trak(b"co64", first);
+        moov.extend(trak(b"stco", second));
+        bytes.extend(atom(b"moov", &moov));
+        bytes.extend(atom(b"mdat", b"SECOND"));
+        assert_eq!(&bytes[second as usize..second as usize + 6], b"SECOND");
+        bytes
+    }
+
+    fn chunk_offsets(atoms: &[Atom]) -> Vec<u64> {
+        let mut offsets = Vec::new();
+        for atom in atoms {
+            match &atom.kind {
+                b"trak" | b"mdia" | b"minf" | b"stbl" => offsets.extend(chunk_offsets(&parse_atoms(&atom.data).unwrap())),
+                b"stco" => offsets.push(u32::from_be_bytes(atom.data[8..12].try_into().unwrap()) as u64),
+                b"co64" => offsets.push(u64::from_be_bytes(atom.data[8..16].try_into().unwrap())),
+                _ => {}
+            }
+        }
+        offsets
+    }
+
+    fn read(path: &Path) -> Metadata {
+        let mut metadata = Metadata::default();
+        read_into(path, &mut metadata).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn write_shifts_the_chunks_after_moov() {
+        let file = TempFile::new("mp4-shift", &mp4_bytes());
+        let metadata = Metadata { title: "Title".to_string(), track: 3, disc_no: 1, ..Metadata::default() };
+        write(file.path(), &metadata).unwrap();
+
+        let bytes = file.bytes();
+        let layout = Layout::read(file.path()).unwrap();
+        let offsets = chunk_offsets(&parse_atoms(&layout.moov).unwrap());
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(&bytes[offsets[0] as usize..offsets[0] as usize + 5], b"FIRST");
+        assert_eq!(&bytes[offsets[1] as usize..offsets[1] as usize + 6], b"SECOND");
+        assert!(layout.mdat_after);
+
+        let read_back = read(file.path());
+        assert_eq!((read_back.title.as_str(), read_back.track, read_back.disc_no), ("Title", 3, 1));
+    }
+
+    #[test]
+    fn write_keeps_a_full_release_date() {
+        let file = TempFile::new("mp4-date", &mp4_bytes());
+        let layout = Layout::read(file.path()).unwrap();
+        let mut items = Vec::new();
+        set_text(&mut items, YEAR, "2004-05-06T07:00:00Z");
+        write_items(file.path(), &layout, &items).unwrap();
+
+        let mut metadata = read(file.path());
+        assert_eq!(metadata.year, 2004);
+        metadata.title = "Title".to_string();
+        write(file.path(), &metadata).unwrap();
+        let items = Layout::read(file.path()).unwrap().items().unwrap().unwrap();
+        assert_eq!(text(&items, YEAR).as_deref(), Some("2004-05-06T07:00:00Z"));
+
+        write(file.path(), &Metadata { year: 2010, ..metadata }).unwrap();
+        assert_eq!(read(file.path()).year, 2010);
+    }
+
+    #[test]
+    fn padding_absorbs_later_edits() {
+        let file = TempFile::new("mp4-in-place", &mp4_bytes());
+        write(file.path(), &Metadata { title: "A long title to start with".to_string(), ..Metadata::default() }).unwrap();
+        let len = file.bytes().len();
+        let offsets = chunk_offsets(&parse_atoms(&Layout::read(file.path()).unwrap().moov).unwrap());
+
+        write(file.path(), &Metadata { title: "Short".to_string(), artist: "Artist".to_string(), ..Metadata::default() })
+            .unwrap();
+        assert_eq!(file.bytes().len(), len);
+        assert_eq!(chunk_offsets(&parse_atoms(&Layout::read(file.path()).unwrap().moov).unwrap()), offsets);
+        assert_eq!(read(file.path()).title, "Short");
+    }
+
+    #[test]
+    fn cover_round_trip_keeps_the_other_items() {
+        let file = TempFile::new("mp4-cover", &mp4_bytes());
+        assert!(read_cover(file.path()).unwrap().is_none());
+        write(file.path(), &Metadata { artist: "Artist".to_string(), ..Metadata::default() }).unwrap();
+
+        write_cover(file.path(), &Picture::from_bytes(jpeg()).unwrap()).unwrap();
+        let items = Layout::read(file.path()).unwrap().items().unwrap().unwrap();
+        assert_eq!(data(&items, COVER).map(|(data_type, _)| data_type), Some(TYPE_JPEG));
+        let cover = read_cover(file.path()).unwrap().unwrap();
+        assert_eq!((cover.mime, cover.data), ("image/jpeg", jpeg()));
+
+        // Writing tags leaves the cover alone
+        write(file.path(), &Metadata { artist: "Other".to_string(), ..Metadata::default() }).unwrap();
+        assert_eq!(read(file.path()).artist, "Other");
+        assert_eq!(read_cover(file.path()).unwrap().map(|cover| cover.data), Some(jpeg()));
+    }
+
+    #[test]
+    fn items_keep_their_totals_and_numeric_genres() {
+        let mut items = Vec::new();
+        set_item(&mut items, TRACK, Some((TYPE_IMPLICIT, vec![0, 0, 0, 2, 0, 10, 0, 0])));
+        set_item(&mut items, GENRE_ID, Some((TYPE_IMPLICIT, vec![0, 18])));
+        set_text(&mut items, TITLE, "Title");
+
+        set_pair(&mut items, TRACK, 5, 8);
+        assert_eq!(pair(&items, TRACK), Some((5, 10)));
+        assert_eq!(genre_from_id(&items).as_deref(), Some("Rock"));
+        set_text(&mut items, TITLE, "");
+        assert_eq!(text(&items, TITLE), None);
+        assert_eq!(items.iter().map(|item| &item.kind).collect::<Vec<_>>(), [TRACK, GENRE_ID]);
+    }
+
+    #[test]
+    fn truncated_atoms_are_rejected() {
+        let mut bytes = atom(b"moov", &[0; 16]);
+        bytes.truncate(20);
+        assert!(parse_atoms(&bytes).is_err());
+        let file = TempFile::new("mp4-no-moov", &atom(b"ftyp", b"M4A "));
+        assert!(Layout::read(file.path()).is_err());
+    }
+}
@@ -0,0 +1,402 @@
+// src/tags/ogg.rs
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use crate::tags::splice_file;
+use crate::tags::vorbis::Comments;
+use crate::Metadata;
+
+/// The codecs whose comment header we know how to find.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Vorbis,
+    Opus,
+}
+
+impl Codec {
+    fn detect(identification: &[u8]) -> io::Result<Codec> {
+        if identification.starts_with(b"\x01vorbis") {
+            Ok(Codec::Vorbis)
+        } else if identification.starts_with(b"OpusHead") {
+            Ok(Codec::Opus)
+        } else {
+            Err(invalid("only Vorbis and Opus streams are supported"))
+        }
+    }
+
+    /// Number of header packets before the audio starts, identification included.
+    fn header_packets(self) -> usize {
+        match self {
+            Codec::Vorbis => 3,
+            Codec::Opus => 2,
+        }
+    }
+
+    fn comment_magic(self) -> &'static [u8] {
+        match self {
+            Codec::Vorbis => b"\x03vorbis",
+            Codec::Opus => b"OpusTags",
+        }
+    }
+}
+
+struct Page {
+    header_type: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    lacing: Vec<u8>,
+    body: Vec<u8>,
+}
+
+impl Page {
+    fn read<R: Read>(reader: &mut R) -> io::Result<Option<Page>> {
+        let mut header = [0u8; 27];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        if &header[0..4] != b"OggS" || header[4] != 0 {
+            return Err(invalid("lost page sync"));
+        }
+
+        let mut lacing = vec![0u8; header[26] as usize];
+        reader.read_exact(&mut lacing)?;
+        let mut body = vec![0u8; lacing.iter().map(|&len| len as usize).sum()];
+        reader.read_exact(&mut body)?;
+
+        Ok(Some(Page {
+            header_type: header[5],
+            granule: u64::from_le_bytes(header[6..14].try_into().unwrap()),
+            serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+            sequence: u32::from_le_bytes(header[18..22].try_into().unwrap()),
+            lacing,
+            body,
+        }))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(27 + self.lacing.len() + self.body.len());
+        bytes.extend_from_slice(b"OggS");
+        bytes.push(0);
+        bytes.push(self.header_type);
+        bytes.extend_from_slice(&self.granule.to_le_bytes());
+        bytes.extend_from_slice(&self.serial.to_le_bytes());
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.push(self.lacing.len() as u8);
+        bytes.extend_from_slice(&self.lacing);
+        bytes.extend_from_slice(&self.body);
+
+        let crc = crc32(&bytes);
+        bytes[22..26].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+}
+
+/// The header packets of the first logical stream and the pages carrying them.
+struct Headers {
+    codec: Codec,
+    serial: u32,
+    packets: Vec<Vec<u8>>,
+    /// Pages up to and including the one the last header packet ends on.
+    pages: Vec<Page>,
+}
+
+impl Headers {
+    fn read<R: Read>(reader: &mut R) -> io::Result<Headers> {
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+        let mut pages = Vec::new();
+        let mut packet = Vec::new();
+        let mut codec = None;
+
+        while let Some(page) = Page::read(reader)? {
+            if pages.first().is_some_and(|first: &Page| first.serial != page.serial) {
+                return Err(invalid("multiplexed streams are not supported"));
+            }
+
+            let mut offset = 0;
+            for &len in &page.lacing {
+                packet.extend_from_slice(&page.body[offset..offset + len as usize]);
+                offset += len as usize;
+                if len < 255 {
+                    packets.push(std::mem::take(&mut packet));
+                }
+            }
+            pages.push(page);
+
+            if codec.is_none() {
+                if let Some(identification) = packets.first() {
+                    codec = Some(Codec::detect(identification)?);
+                }
+            }
+            if let Some(codec) = codec {
+                if packets.len() >= codec.header_packets() {
+                    if packets.len() > codec.header_packets() || !packet.is_empty() {
+                        return Err(invalid("audio data shares a page with the headers"));
+                    }
+                    let serial = pages[0].serial;
+                    return Ok(Headers { codec, serial, packets, pages });
+                }
+            }
+        }
+
+        Err(invalid("stream ends before its headers"))
+    }
+
+    /// The comments, and the bytes following them in the comment header:
+    /// Vorbis's framing bit, or the padding and binary data Opus allows.
+    fn comments(&self) -> io::Result<(Comments, &[u8])> {
+        let magic = self.codec.comment_magic();
+        let packet = &self.packets[1];
+        if !packet.starts_with(magic) {
+            return Err(invalid("missing comment header"));
+        }
+        let (comments, len) = Comments::parse_prefix(&packet[magic.len()..])?;
+        Ok((comments, &packet[magic.len() + len..]))
+    }
+
+    fn set_comments(&mut self, comments: &Comments, trailer: &[u8]) {
+        let mut packet = self.codec.comment_magic().to_vec();
+        packet.extend_from_slice(&comments.to_bytes());
+        match trailer {
+            // Framing bit
+            [] if self.codec == Codec::Vorbis => packet.push(1),
+            trailer => packet.extend_from_slice(trailer),
+        }
+        self.packets[1] = packet;
+    }
+}
+
+/// Read the comment header of the Ogg Vorbis/Opus file at `path` into `metadata`.
+pub fn read_into(path: &Path, metadata: &mut Metadata) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Headers::read(&mut reader)?.comments()?.0.apply_to(metadata);
+    Ok(())
+}
+
+/// Write `metadata` to the comment header of the Ogg Vorbis/Opus file at `path`.
+/// The header pages are repaginated; the audio pages are only rewritten when
+/// the number of header pages changed and their sequence numbers have to follow.
+pub fn write(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    let bytes = fs::read(path)?;
+    let mut reader = bytes.as_slice();
+    let mut headers = Headers::read(&mut reader)?;
+    let audio_start = bytes.len() - reader.len();
+
+    let (mut comments, trailer) = headers.comments()?;
+    let trailer = trailer.to_vec();
+    comments.update_from(metadata);
+    headers.set_comments(&comments, &trailer);
+
+    // The identification header must sit alone on the first page
+    let first_page = &headers.pages[0];
+    if first_page.lacing.iter().filter(|&&len| len < 255).count() != 1 {
+        return Err(invalid("identification header does not end the first page"));
+    }
+    let mut head = first_page.to_bytes();
+    let header_pages = paginate(&headers.packets[1..], headers.serial, first_page.sequence + 1);
+    for page in &header_pages {
+        head.extend_from_slice(&page.to_bytes());
+    }
+
+    if header_pages.len() == headers.pages.len() - 1 {
+        return splice_file(path, 0, audio_start as u64, &head);
+    }
+
+    let shift = header_pages.len() as i64 - (headers.pages.len() as i64 - 1);
+    while let Some(mut page) = Page::read(&mut reader)? {
+        page.sequence = (page.sequence as i64 + shift) as u32;
+        head.extend_from_slice(&page.to_bytes());
+    }
+    splice_file(path, 0, bytes.len() as u64, &head)
+}
+
+/// Lay `packets` out over as many pages as needed, the last packet ending its page.
+fn paginate(packets: &[Vec<u8>], serial: u32, first_sequence: u32) -> Vec<Page> {
+    // (segment length, whether it ends a packet, the segment's data)
+    let mut segments = Vec::new();
+    for packet in packets {
+        let mut chunks = packet.chunks(255).peekable();
+        while let Some(chunk) = chunks.next() {
+            let is_end = chunks.peek().is_none() && chunk.len() < 255;
+            segments.push((chunk.len() as u8, is_end, chunk));
+        }
+        if packet.len() % 255 == 0 {
+            // A packet of an exact multiple of 255 bytes is closed by an empty segment
+            segments.push((0, true, &[][..]));
+        }
+    }
+
+    let mut pages = Vec::new();
+    let mut continued = false;
+    for (index, page_segments) in segments.chunks(255).enumerate() {
+        let ends_packet = page_segments.iter().any(|(_, is_end, _)| *is_end);
+        pages.push(Page {
+            header_type: if continued { 0x01 } else { 0 },
+            // Header pages have a granule position of zero, or -1 when no packet finishes on them
+            granule: if ends_packet { 0 } else { u64::MAX },
+            serial,
+            sequence: first_sequence + index as u32,
+            lacing: page_segments.iter().map(|(len, _, _)| *len).collect(),
+            body: page_segments.iter().flat_map(|(_, _, data)| data.iter().copied()).collect(),
+        });
+        continued = page_segments.last().is_some_and(|(_, is_end, _)| !is_end);
+    }
+    pages
+}
+
+/// The CRC-32 used by Ogg: polynomial 0x04C11DB7, no reflection, zero initial value.
+fn crc32(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &byte| {
+        let mut crc = crc ^ ((byte as u32) << 24);
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Ogg: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    const SERIAL: u32 = 0x1234;
+
+    fn page(header_type: u8, sequence: u32, body: &[u8]) -> Page {
+        Page {
+            header_type,
+            granule: sequence as u64 * 960,
+            serial: SERIAL,
+            sequence,
+            lacing: vec![body.len() as u8],
+            body: body.to_vec(),
+        }
+    }
+
+    /// A stream with `packets` as its headers (the identification header alone
+    /// on the first page) followed by two audio pages.
+    fn stream(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = Page { granule: 0, ..page(0x02, 0, &packets[0]) }.to_bytes();
+        let header_pages = paginate(&packets[1..], SERIAL, 1);
+        let next = header_pages.len() as u32 + 1;
+        for page in header_pages {
+            bytes.extend(page.to_bytes());
+        }
+        bytes.extend(page(0, next, &[0xAA; 100]).to_bytes());
+        bytes.extend(page(0x04, next + 1, &[0xBB; 50]).to_bytes());
+        bytes
+    }
+
+    /// Every page in `bytes`, checking that each one's CRC is correct.
+    fn pages(bytes: &[u8]) -> Vec<Page> {
+        let mut reader = bytes;
+        let mut pages = Vec::new();
+        let mut rewritten = Vec::new();
+        while let Some(page) = Page::read(&mut reader).unwrap() {
+            rewritten.extend(page.to_bytes());
+            pages.push(page);
+        }
+        assert!(rewritten == bytes, "page CRCs don't match their contents");
+        pages
+    }
+
+    fn comment_packet(magic: &[u8], comments: &Comments, trailer: &[u8]) -> Vec<u8> {
+        let mut packet = magic.to_vec();
+        packet.extend(comments.to_bytes());
+        packet.extend_from_slice(trailer);
+        packet
+    }
+
+    #[test]
+    fn crc_matches_the_ogg_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0x89A1_897F);
+    }
+
+    #[test]
+    fn paginate_splits_long_packets_across_pages() {
+        let packets = vec![vec![1; 255 * 300], vec![2; 255]];
+        let pages = paginate(&packets, SERIAL, 5);
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!((pages[0].sequence, pages[0].header_type, pages[0].granule), (5, 0, u64::MAX));
+        assert_eq!(pages[0].lacing, [255; 255]);
+        assert_eq!((pages[1].sequence, pages[1].header_type, pages[1].granule), (6, 0x01, 0));
+        // The rest of the first packet, its empty closing segment, then the second packet
+        let mut lacing = vec![255; 45];
+        lacing.extend([0, 255, 0]);
+        assert_eq!(pages[1].lacing, lacing);
+
+        let body = pages.iter().flat_map(|page| page.body.iter().copied()).collect::<Vec<_>>();
+        assert_eq!(body, packets.concat());
+    }
+
+    #[test]
+    fn vorbis_write_repaginates_and_renumbers_the_audio() {
+        let comments = Comments { vendor: "vendor".to_string(), fields: vec![("TITLE".to_string(), "Old".to_string())] };
+        let packets = vec![
+            b"\x01vorbis identification".to_vec(),
+            comment_packet(b"\x03vorbis", &comments, b"\x01"),
+            b"\x05vorbis setup".to_vec(),
+        ];
+        let file = TempFile::new("ogg-vorbis", &stream(&packets));
+        assert_eq!(pages(&file.bytes()).len(), 4);
+
+        // Large enough to need a second header page
+        let metadata = Metadata { title: "New".to_string(), comments: "c".repeat(70_000), ..Metadata::default() };
+        write(file.path(), &metadata).unwrap();
+
+        let bytes = file.bytes();
+        let pages = pages(&bytes);
+        assert_eq!(pages.len(), 5);
+        assert_eq!(pages.iter().map(|page| page.sequence).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        assert_eq!(pages[3].body, [0xAA; 100]);
+        assert_eq!((pages[4].header_type, pages[4].body.as_slice()), (0x04, &[0xBB; 50][..]));
+
+        let headers = Headers::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(headers.packets[2], packets[2]);
+        let (comments, trailer) = headers.comments().unwrap();
+        assert_eq!(trailer, b"\x01");
+        assert_eq!(comments.vendor, "vendor");
+        let mut read = Metadata::default();
+        read_into(file.path(), &mut read).unwrap();
+        assert_eq!(read.title, "New");
+        assert_eq!(read.comments.len(), 70_000);
+    }
+
+    #[test]
+    fn opus_write_keeps_the_data_after_the_comments() {
+        let comments = Comments { vendor: "libopus".to_string(), fields: Vec::new() };
+        let packets = vec![b"OpusHead\x01\x02".to_vec(), comment_packet(b"OpusTags", &comments, b"\x01binary")];
+        let file = TempFile::new("ogg-opus", &stream(&packets));
+        let mut audio = page(0, 2, &[0xAA; 100]).to_bytes();
+        audio.extend(page(0x04, 3, &[0xBB; 50]).to_bytes());
+
+        write(file.path(), &Metadata { artist: "Artist".to_string(), ..Metadata::default() }).unwrap();
+
+        let bytes = file.bytes();
+        // Same number of header pages, so the audio pages are untouched
+        assert!(bytes.ends_with(&audio));
+        let headers = Headers::read(&mut bytes.as_slice()).unwrap();
+        let (comments, trailer) = headers.comments().unwrap();
+        assert_eq!(comments.get(&["ARTIST"]).as_deref(), Some("Artist"));
+        assert_eq!(trailer, b"\x01binary");
+        pages(&bytes);
+    }
+
+    #[test]
+    fn other_codecs_are_rejected() {
+        let file = TempFile::new("ogg-flac", &stream(&[b"\x7FFLAC".to_vec(), Vec::new()]));
+        let error = read_into(file.path(), &mut Metadata::default()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
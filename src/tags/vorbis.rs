@@ -0,0 +1,236 @@
+// src/tags/vorbis.rs
+
+use std::io;
+
+use crate::tags::{leading_number, with_number};
+use crate::Metadata;
+
+/// A Vorbis comment block, shared by FLAC and the Ogg codecs.
+#[derive(Clone, Default)]
+pub struct Comments {
+    pub vendor: String,
+    /// Fields in file order. Keys are compared case-insensitively.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Comments {
+    /// Parse a comment block (without any codec-specific packet header).
+    pub fn parse(data: &[u8]) -> io::Result<Comments> {
+        Self::parse_prefix(data).map(|(comments, _)| comments)
+    }
+
+    /// Parse the comment block at the start of `data`, also returning the
+    /// number of bytes it takes up, so whatever follows it can be kept.
+    pub fn parse_prefix(data: &[u8]) -> io::Result<(Comments, usize)> {
+        let mut offset = 0;
+        let vendor = read_string(data, &mut offset)?;
+        let count = read_u32_le(data, &mut offset)?;
+
+        let mut fields = Vec::new();
+        for _ in 0..count {
+            let field = read_string(data, &mut offset)?;
+            if let Some((key, value)) = field.split_once('=') {
+                fields.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        Ok((Comments { vendor, fields }, offset))
+    }
+
+    /// Serialize the comment block (without any codec-specific packet header).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.vendor.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.vendor.as_bytes());
+        bytes.extend_from_slice(&(self.fields.len() as u32).to_le_bytes());
+        for (key, value) in &self.fields {
+            let field = format!("{key}={value}");
+            bytes.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(field.as_bytes());
+        }
+        bytes
+    }
+
+    /// Return the first non-empty value of any of `keys`.
+    pub fn get(&self, keys: &[&str]) -> Option<String> {
+        self.fields
+            .iter()
+            .find(|(key, value)| keys.iter().any(|k| k.eq_ignore_ascii_case(key)) && !value.is_empty())
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Replace every field named by one of `keys` with a single `value` under
+    /// `keys[0]`, keeping the position of the first one. Empty values remove the field.
+    pub fn set(&mut self, keys: &[&str], value: &str) {
+        let matches = |key: &str| keys.iter().any(|k| k.eq_ignore_ascii_case(key));
+        let position = self.fields.iter().position(|(key, _)| matches(key));
+        self.fields.retain(|(key, _)| !matches(key));
+
+        if value.is_empty() {
+            return;
+        }
+        let field = (keys[0].to_string(), value.to_string());
+        match position {
+            Some(position) => self.fields.insert(position.min(self.fields.len()), field),
+            None => self.fields.push(field),
+        }
+    }
+
+    /// Copy every field these comments carry into `metadata`.
+    pub fn apply_to(&self, metadata: &mut Metadata) {
+        if let Some(title) = self.get(TITLE) {
+            metadata.title = title;
+        }
+        if let Some(artist) = self.get(ARTIST) {
+            metadata.artist = artist;
+        }
+        if let Some(album) = self.get(ALBUM) {
+            metadata.album = album;
+        }
+        if let Some(year) = self.get(DATE) {
+            metadata.year = leading_number(&year);
+        }
+        if let Some(genre) = self.get(GENRE) {
+            metadata.genre = genre;
+        }
+        if let Some(track) = self.get(TRACK) {
+            metadata.track = leading_number(&track);
+        }
+        if let Some(album_artist) = self.get(ALBUM_ARTIST) {
+            metadata.album_artist = album_artist;
+        }
+        if let Some(composer) = self.get(COMPOSER) {
+            metadata.composer = composer;
+        }
+        if let Some(disc_no) = self.get(DISC) {
+            metadata.disc_no = leading_number(&disc_no);
+        }
+        if let Some(comments) = self.get(COMMENT) {
+            metadata.comments = comments;
+        }
+    }
+
+    /// Replace the fields backing `Metadata` with the values in `metadata`.
+    /// Fields that already hold the value are left alone, so a field given
+    /// several times (two ARTIST entries, say) survives an unrelated edit.
+    pub fn update_from(&mut self, metadata: &Metadata) {
+        self.update(TITLE, &metadata.title);
+        self.update(ARTIST, &metadata.artist);
+        self.update(ALBUM, &metadata.album);
+        self.update(DATE, &with_number(self.get(DATE).as_deref(), metadata.year));
+        self.update(GENRE, &metadata.genre);
+        self.update(TRACK, &with_number(self.get(TRACK).as_deref(), metadata.track));
+        self.update(ALBUM_ARTIST, &metadata.album_artist);
+        self.update(COMPOSER, &metadata.composer);
+        self.update(DISC, &with_number(self.get(DISC).as_deref(), metadata.disc_no));
+        self.update(COMMENT, &metadata.comments);
+    }
+
+    fn update(&mut self, keys: &[&str], value: &str) {
+        if self.get(keys).unwrap_or_default() != value {
+            self.set(keys, value);
+        }
+    }
+}
+
+// Field names for each `Metadata` field; the first one is used when writing
+const TITLE: &[&str] = &["TITLE"];
+const ARTIST: &[&str] = &["ARTIST"];
+const ALBUM: &[&str] = &["ALBUM"];
+const DATE: &[&str] = &["DATE", "YEAR"];
+const GENRE: &[&str] = &["GENRE"];
+const TRACK: &[&str] = &["TRACKNUMBER"];
+const ALBUM_ARTIST: &[&str] = &["ALBUMARTIST", "ALBUM ARTIST"];
+const COMPOSER: &[&str] = &["COMPOSER"];
+const DISC: &[&str] = &["DISCNUMBER"];
+const COMMENT: &[&str] = &["COMMENT", "DESCRIPTION"];
+
+fn read_u32_le(data: &[u8], offset: &mut usize) -> io::Result<u32> {
+    let bytes = data.get(*offset..*offset + 4).ok_or_else(truncated)?;
+    *offset += 4;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_string(data: &[u8], offset: &mut usize) -> io::Result<String> {
+    let len = read_u32_le(data, offset)? as usize;
+    let bytes = data.get(*offset..*offset + len).ok_or_else(truncated)?;
+    *offset += len;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Vorbis comment block is truncated")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_keeps_what_follows_the_block() {
+        let comments = Comments {
+            vendor: "test vendor".to_string(),
+            fields: vec![("TITLE".to_string(), "Title".to_string()), ("artist".to_string(), "Ärtist".to_string())],
+        };
+        let mut bytes = comments.to_bytes();
+        let len = bytes.len();
+        bytes.extend_from_slice(b"\x01trailer");
+
+        let (parsed, parsed_len) = Comments::parse_prefix(&bytes).unwrap();
+        assert_eq!(parsed_len, len);
+        assert_eq!(parsed.vendor, "test vendor");
+        assert_eq!(parsed.fields, comments.fields);
+        assert!(Comments::parse(&bytes[..len - 1]).is_err());
+    }
+
+    #[test]
+    fn set_replaces_every_alias_in_place() {
+        let mut comments = Comments::default();
+        for (key, value) in [("TITLE", "Title"), ("year", "1999"), ("DATE", "1999-01-01"), ("ARTIST", "Artist")] {
+            comments.fields.push((key.to_string(), value.to_string()));
+        }
+
+        comments.set(DATE, "2004");
+        let keys = comments.fields.iter().map(|(key, value)| format!("{key}={value}")).collect::<Vec<_>>();
+        assert_eq!(keys, ["TITLE=Title", "DATE=2004", "ARTIST=Artist"]);
+        comments.set(TITLE, "");
+        assert_eq!(comments.get(TITLE), None);
+    }
+
+    #[test]
+    fn update_from_keeps_unchanged_fields_as_they_are() {
+        let mut comments = Comments::default();
+        for (key, value) in [("ARTIST", "One"), ("ARTIST", "Two"), ("DATE", "2004-05-06"), ("TRACKNUMBER", "3/12")] {
+            comments.fields.push((key.to_string(), value.to_string()));
+        }
+        let mut metadata = Metadata::default();
+        comments.apply_to(&mut metadata);
+        metadata.title = "Title".to_string();
+
+        comments.update_from(&metadata);
+        let fields = comments.fields.iter().map(|(key, value)| format!("{key}={value}")).collect::<Vec<_>>();
+        assert_eq!(fields, ["ARTIST=One", "ARTIST=Two", "DATE=2004-05-06", "TRACKNUMBER=3/12", "TITLE=Title"]);
+
+        comments.update_from(&Metadata { artist: "Three".to_string(), track: 4, ..metadata });
+        assert_eq!(comments.get(ARTIST).as_deref(), Some("Three"));
+        assert_eq!(comments.fields.iter().filter(|(key, _)| key == "ARTIST").count(), 1);
+        assert_eq!(comments.get(TRACK).as_deref(), Some("4/12"));
+        assert_eq!(comments.get(DATE).as_deref(), Some("2004-05-06"));
+    }
+
+    #[test]
+    fn apply_to_reads_numbers_and_fallback_keys() {
+        let mut comments = Comments::default();
+        let fields = [("YEAR", "2004-05-06"), ("TRACKNUMBER", "3/12"), ("Album Artist", "Various"), ("DESCRIPTION", "Notes")];
+        for (key, value) in fields {
+            comments.fields.push((key.to_string(), value.to_string()));
+        }
+
+        let mut metadata = Metadata::default();
+        comments.apply_to(&mut metadata);
+        assert_eq!(metadata.year, 2004);
+        assert_eq!(metadata.track, 3);
+        assert_eq!(metadata.album_artist, "Various");
+        assert_eq!(metadata.comments, "Notes");
+    }
+}
@@ -0,0 +1,33 @@
+// src/testing.rs
+
+// Helpers shared by the unit tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file in the system temp directory that is removed again when dropped.
+pub(crate) struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Create the file with `bytes` as its contents. `name` has to be unique
+    /// across the test suite, as tests run in parallel.
+    pub(crate) fn new(name: &str, bytes: &[u8]) -> TempFile {
+        let path = std::env::temp_dir().join(format!("mp3-cli-test-{}-{name}", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        TempFile(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        fs::read(&self.0).unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}